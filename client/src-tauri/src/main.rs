@@ -20,7 +20,7 @@ use crate::{
 use cs310_shared::{
   constants::{init, store_json_location},
   networking::{self, Message},
-  nodes::get_node_register,
+  nodes::{get_node_register, load_node_definitions},
   store::Store,
 };
 
@@ -55,7 +55,12 @@ fn main() {
   init(path, false); // Sets up utility functions
   println!("Initialised");
 
-  let register = get_node_register(); // Gets the complete register of node types
+  // Gets the complete register of node types, including those defined in files
+  let (definitions, errors) = load_node_definitions();
+  let (register, register_errors) = get_node_register(&definitions);
+  for error in errors.iter().chain(&register_errors) {
+    println!("{}", error);
+  }
 
   let (tx, rx) = mpsc::channel(); // Thread stopper communication - allows a message to be send from the main thread, so that all threads can then be stopped
   let shared_state = SharedState {
@@ -89,11 +94,15 @@ fn main() {
       tauri_commands::get_node_inputs,
       tauri_commands::get_output_directory,
       tauri_commands::get_clip_type,
+      tauri_commands::get_clip_format,
       tauri_commands::get_connection_status,
       tauri_commands::create_composited_clip,
       tauri_commands::add_link,
       tauri_commands::update_node,
       tauri_commands::add_node,
+      tauri_commands::create_node_group,
+      tauri_commands::solo_node,
+      tauri_commands::lint_pipeline,
       tauri_commands::delete_node,
       tauri_commands::delete_links,
       tauri_commands::update_clip,
@@ -9,6 +9,7 @@ use cs310_shared::{
   networking,
   store::Store,
   task::NetworkTask,
+  validation::ValidationError,
 };
 use uuid::Uuid;
 
@@ -186,6 +187,24 @@ pub fn network_task_manager_thread(shared_state: Arc<Mutex<SharedState>>) {
               networking::send_message(&mut stream, networking::Message::UpdateNode).unwrap();
               networking::send_as_file(&mut stream, &bytes);
 
+              // the server can reject a change which passed validation here, e.g. if its node types differ
+              if let networking::Message::InvalidNode =
+                networking::receive_message(&mut stream).unwrap()
+              {
+                let errors = networking::receive_file_as_bytes(&mut stream);
+                let errors = serde_json::from_slice::<Vec<ValidationError>>(&errors);
+                if let Ok(errors) = errors {
+                  let lock = shared_state.lock().unwrap();
+                  lock
+                    .window
+                    .as_ref()
+                    .unwrap()
+                    .emit("node-validation-error", (node_id.clone(), errors))
+                    .unwrap();
+                }
+                continue;
+              }
+
               let mut lock = shared_state.lock().unwrap();
               let composited_clip_id = lock
                 .store
@@ -214,6 +233,25 @@ pub fn network_task_manager_thread(shared_state: Arc<Mutex<SharedState>>) {
             networking::send_message(&mut stream, networking::Message::AddLink).unwrap();
             networking::send_as_file(&mut stream, &bytes);
 
+            // the server can reject a link which passed the check here, e.g. if its copy of the pipeline differs
+            if let networking::Message::InvalidLink =
+              networking::receive_message(&mut stream).unwrap()
+            {
+              let reason = networking::receive_file_as_bytes(&mut stream);
+              let reason = String::from_utf8_lossy(&reason).to_string();
+              let mut lock = shared_state.lock().unwrap();
+              let store = lock.store.as_mut().unwrap();
+              store
+                .pipeline
+                .links
+                .retain(|existing| existing.get_id() != link.get_id());
+              let store = store.clone();
+              let window = lock.window.as_ref().unwrap();
+              window.emit("store-update", store).unwrap();
+              window.emit("link-rejected", reason).unwrap();
+              continue;
+            }
+
             let mut lock = shared_state.lock().unwrap();
 
             let group = lock
@@ -311,6 +349,38 @@ pub fn network_task_manager_thread(shared_state: Arc<Mutex<SharedState>>) {
             networking::send_data(&mut stream, &clip_type_bytes).unwrap();
             networking::send_as_file(&mut stream, &clip);
           }
+          NetworkTask::CreateNodeGroup(group_id) => {
+            let lock = shared_state.lock().unwrap();
+            let group = lock.store.as_ref().unwrap().node_groups.get(&group_id);
+            if group.is_none() {
+              continue;
+            }
+            let bytes = serde_json::to_vec(group.unwrap()).unwrap();
+
+            drop(lock);
+            let mut stream = networking::connect_to_server().unwrap();
+            networking::send_message(&mut stream, networking::Message::CreateNodeGroup).unwrap();
+            networking::send_as_file(&mut stream, &bytes);
+          }
+          NetworkTask::SoloNode => {
+            // Solo a node (or stop soloing), and reset the video preview data for every composited clip, as they all show the solo node
+            let lock = shared_state.lock().unwrap();
+            let bytes = serde_json::to_vec(&lock.store.as_ref().unwrap().solo_node).unwrap();
+
+            drop(lock);
+            let mut stream = networking::connect_to_server().unwrap();
+            networking::send_message(&mut stream, networking::Message::SoloNode).unwrap();
+            networking::send_as_file(&mut stream, &bytes);
+
+            let mut lock = shared_state.lock().unwrap();
+            for status in lock.video_preview_data.values_mut() {
+              *status = VideoPreviewStatus::LengthRequested;
+            }
+            let window = lock.window.as_ref().unwrap();
+            window
+              .emit("video-preview-data-update", lock.video_preview_data.clone())
+              .unwrap();
+          }
         }
       }
       should_checksum = true;
@@ -29,9 +29,13 @@ pub fn store_fetcher_thread(state: Arc<Mutex<SharedState>>) {
       let mut json_file = File::create(store_json_location()).unwrap();
       networking::receive_file(&mut stream, &mut json_file);
 
-      let store = Store::from_file(store_json_location());
+      let node_register = state.lock().unwrap().node_register.clone();
+      let store = Store::from_file(store_json_location(), &node_register);
 
-      if let Ok(store) = store {
+      if let Ok((store, changes)) = store {
+        for change in changes {
+          println!("Migrated node {}", change);
+        }
         set_connection_status(&state, ConnectionStatus::Connected);
         let mut state = state.lock().unwrap();
         let mut video_preview_data = HashMap::new();
@@ -10,11 +10,15 @@ use cs310_shared::{
   cache::Cache,
   clip::{self, ClipType, CompositedClip, SourceClip},
   constants::media_output_location,
-  node::{Node, NodeTypeInput, NodeTypeOutput, PipeableType},
-  nodes::NodeRegister,
-  pipeline::Link,
+  error::PipelineError,
+  lint::{self, LintIssue},
+  node::{Node, NodeCategory, NodeTypeInput, NodeTypeOutput, PipeableType, StreamFormat},
+  node_group::{GroupEndpoint, NodeGroup},
+  nodes::{get_node_palette, NodeRegister},
+  pipeline::{get_soloed_store, Link},
   store::Store,
   task::Task,
+  validation::{validate_new_node, validate_node, ValidationError},
   ID,
 };
 
@@ -32,8 +36,12 @@ pub async fn import_media(
     }
   }
 
-  let dialog = tauri::api::dialog::FileDialogBuilder::new()
-    .add_filter("Media", &["mp4", "mkv", "mp3", "avi", "wav", "flv", "webm"]);
+  let dialog = tauri::api::dialog::FileDialogBuilder::new().add_filter(
+    "Media",
+    &[
+      "mp4", "mkv", "mp3", "avi", "wav", "flv", "webm", "png", "jpg", "jpeg",
+    ],
+  );
 
   // #[cfg(not(target_os = "linux"))]
   // let dialog = dialog
@@ -91,16 +99,20 @@ pub async fn import_media(
   }
 }
 
-/// Executed when the page loads and requests the initial state data for the React UI
+/// Executed when the page loads and requests the initial state data for the React UI, along with the node type ids to list under each category of the node palette
 #[tauri::command]
 pub fn get_initial_data(
   state: tauri::State<SharedStateWrapper>,
-) -> Result<(Store, NodeRegister), String> {
+) -> Result<(Store, NodeRegister, Vec<(NodeCategory, Vec<String>)>), String> {
   let state = state.0.lock().unwrap();
   if state.store.is_none() {
     Err(format!("Store is not yet set"))
   } else {
-    Ok((state.store.clone().unwrap(), state.node_register.clone()))
+    Ok((
+      state.store.clone().unwrap(),
+      state.node_register.clone(),
+      get_node_palette(&state.node_register),
+    ))
   }
 }
 
@@ -109,11 +121,11 @@ pub fn get_initial_data(
 pub fn get_node_outputs(
   state: tauri::State<SharedStateWrapper>,
   node: Node,
-) -> Result<HashMap<String, NodeTypeOutput>, String> {
+) -> Result<HashMap<String, NodeTypeOutput>, PipelineError> {
   {
     let lock = state.0.lock().unwrap();
     if lock.store.is_none() {
-      return Err(format!("Store is not yet set"));
+      return Err(PipelineError::from(format!("Store is not yet set")));
     }
   }
 
@@ -125,7 +137,7 @@ pub fn get_node_outputs(
     &Cache::new(),
   );
   if res.is_err() {
-    return Err(format!("Could not get result!: {}", res.unwrap_err()));
+    return Err(res.unwrap_err());
   }
   let (node_type_data, _, _) = res.unwrap();
 
@@ -134,7 +146,7 @@ pub fn get_node_outputs(
   println!("Got data for node: {:?}", data);
 
   if data.is_none() {
-    return Err(format!("Data for node not found"));
+    return Err(PipelineError::from(format!("Data for node not found")));
   }
   let (_, _, outputs) = data.unwrap();
 
@@ -146,11 +158,11 @@ pub fn get_node_outputs(
 pub fn get_node_inputs(
   state: tauri::State<SharedStateWrapper>,
   node: Node,
-) -> Result<HashMap<String, NodeTypeInput>, String> {
+) -> Result<HashMap<String, NodeTypeInput>, PipelineError> {
   {
     let lock = state.0.lock().unwrap();
     if lock.store.is_none() {
-      return Err(format!("Store is not yet set"));
+      return Err(PipelineError::from(format!("Store is not yet set")));
     }
   }
 
@@ -162,14 +174,14 @@ pub fn get_node_inputs(
     &Cache::new(),
   );
   if res.is_err() {
-    return Err(format!("Could not get result!: {}", res.unwrap_err()));
+    return Err(res.unwrap_err());
   }
   let (node_type_data, _, _) = res.unwrap();
 
   let data = node_type_data.get(&node.id);
 
   if data.is_none() {
-    return Err(format!("Data for node not found"));
+    return Err(PipelineError::from(format!("Data for node not found")));
   }
   let (_, inputs, _) = data.unwrap();
 
@@ -230,6 +242,52 @@ pub fn get_clip_type(
   })
 }
 
+/// Returns the format of the targeted clip (its length, resolution, etc.), as far as it can be worked out without rendering it
+#[tauri::command]
+pub fn get_clip_format(
+  state: tauri::State<SharedStateWrapper>,
+  clip_type: ClipType,
+  id: ID,
+) -> Result<StreamFormat, String> {
+  let state = state.0.lock().unwrap();
+  if state.store.is_none() {
+    return Err(format!("Store is not yet set"));
+  }
+  let store = state.store.as_ref().unwrap();
+
+  match clip_type {
+    ClipType::Source => {
+      let clip = store.clips.source.get(&id);
+      if clip.is_none() {
+        return Err(format!("Clip not found"));
+      }
+      Ok(clip.unwrap().get_stream_format())
+    }
+    ClipType::Composited => {
+      // the preview plays the soloed node, so the length should match it
+      let store = get_soloed_store(store, &state.node_register, &id);
+      if store.is_err() {
+        return Err(store.unwrap_err().to_string());
+      }
+      let store = store.unwrap();
+      let res =
+        store
+          .pipeline
+          .generate_pipeline(&store, &state.node_register, false, &Cache::new());
+      if res.is_err() {
+        return Err(res.unwrap_err().to_string());
+      }
+      let (_, clip_data, _) = res.unwrap();
+      Ok(
+        clip_data
+          .get(&id)
+          .map(|piped_type| piped_type.format)
+          .unwrap_or_default(),
+      )
+    }
+  }
+}
+
 #[tauri::command]
 pub fn get_connection_status(state: tauri::State<SharedStateWrapper>) -> ConnectionStatus {
   state.0.lock().unwrap().connection_status.clone()
@@ -252,8 +310,13 @@ pub fn create_composited_clip(state: tauri::State<SharedStateWrapper>) {
 }
 
 #[tauri::command]
-pub fn add_link(state: tauri::State<SharedStateWrapper>, link: Link) {
+pub fn add_link(state: tauri::State<SharedStateWrapper>, link: Link) -> Result<(), String> {
   let mut lock = state.0.lock().unwrap();
+  if let Some(store) = lock.store.as_ref() {
+    store
+      .pipeline
+      .check_link(&link, store, &lock.node_register, &Cache::new())?;
+  }
   lock.tasks.push(Task::AddLink(link));
 
   lock
@@ -262,11 +325,18 @@ pub fn add_link(state: tauri::State<SharedStateWrapper>, link: Link) {
     .unwrap()
     .send(true)
     .unwrap();
+  Ok(())
 }
 
 #[tauri::command]
-pub fn update_node(state: tauri::State<SharedStateWrapper>, node: Node) {
+pub fn update_node(
+  state: tauri::State<SharedStateWrapper>,
+  mut node: Node,
+) -> Result<(), Vec<ValidationError>> {
   let mut lock = state.0.lock().unwrap();
+  if let Some(store) = lock.store.as_ref() {
+    validate_node(&mut node, store, &lock.node_register)?;
+  }
   lock.tasks.push(Task::UpdateNode(node.id.clone(), node));
 
   lock
@@ -275,11 +345,17 @@ pub fn update_node(state: tauri::State<SharedStateWrapper>, node: Node) {
     .unwrap()
     .send(true)
     .unwrap();
+  Ok(())
 }
 
 #[tauri::command]
-pub fn add_node(state: tauri::State<SharedStateWrapper>, node: Node) {
+pub fn add_node(state: tauri::State<SharedStateWrapper>, mut node: Node) {
   let mut lock = state.0.lock().unwrap();
+  if let Some(store) = lock.store.as_ref() {
+    for error in validate_new_node(&mut node, store, &lock.node_register) {
+      println!("Replaced invalid property with default: {}", error);
+    }
+  }
   lock.tasks.push(Task::AddNode(node));
 
   lock
@@ -290,6 +366,74 @@ pub fn add_node(state: tauri::State<SharedStateWrapper>, node: Node) {
     .unwrap();
 }
 
+/// Saves a selection of nodes as a node group, which can then be added to the pipeline like any other node type. Returns the ID of the group.
+#[tauri::command]
+pub fn create_node_group(
+  state: tauri::State<SharedStateWrapper>,
+  node_ids: Vec<ID>,
+  name: String,
+  description: String,
+  inputs: Vec<GroupEndpoint>,
+  outputs: Vec<GroupEndpoint>,
+  properties: Vec<GroupEndpoint>,
+) -> Result<ID, String> {
+  let mut lock = state.0.lock().unwrap();
+  if lock.store.is_none() {
+    return Err(format!("Store is not yet set"));
+  }
+  let group = NodeGroup::from_selection(
+    lock.store.as_ref().unwrap(),
+    &node_ids,
+    name,
+    description,
+    inputs,
+    outputs,
+    properties,
+  )?;
+  group.validate(&lock.node_register)?;
+  let id = group.id.clone();
+  lock.tasks.push(Task::CreateNodeGroup(group));
+
+  lock
+    .task_manager_notifier
+    .as_ref()
+    .unwrap()
+    .send(true)
+    .unwrap();
+  Ok(id)
+}
+
+/// Checks the whole pipeline for problems, including links which no longer fit the types in the pipeline, returning every issue found
+#[tauri::command]
+pub fn lint_pipeline(state: tauri::State<SharedStateWrapper>) -> Result<Vec<LintIssue>, String> {
+  let lock = state.0.lock().unwrap();
+  if lock.store.is_none() {
+    return Err(format!("Store is not yet set"));
+  }
+  let store = lock.store.as_ref().unwrap();
+  let mut issues = lint::lint_pipeline(store, &lock.node_register);
+  issues.extend(lint::find_invalid_links(
+    store,
+    &lock.node_register,
+    &Cache::new(),
+  ));
+  Ok(issues)
+}
+
+/// Solos a node, so the video preview only renders the pipeline up to that node; `None` stops soloing
+#[tauri::command]
+pub fn solo_node(state: tauri::State<SharedStateWrapper>, id: Option<ID>) {
+  let mut lock = state.0.lock().unwrap();
+  lock.tasks.push(Task::SoloNode(id));
+
+  lock
+    .task_manager_notifier
+    .as_ref()
+    .unwrap()
+    .send(true)
+    .unwrap();
+}
+
 #[tauri::command]
 pub fn delete_node(state: tauri::State<SharedStateWrapper>, id: Uuid) {
   let mut lock = state.0.lock().unwrap();
@@ -1,12 +1,13 @@
 use std::{
   collections::HashMap,
   fs::File,
+  net::TcpStream,
   sync::{Arc, Mutex},
   thread,
   time::Duration,
 };
 
-use cs310_shared::{constants::CHUNK_FILENAME_NUMBER_LENGTH, networking};
+use cs310_shared::{constants::CHUNK_FILENAME_NUMBER_LENGTH, error::PipelineError, networking};
 use ges::prelude::DiscovererStreamInfoExt;
 use glib::Cast;
 use gst_pbutils::DiscovererVideoInfo;
@@ -215,7 +216,9 @@ pub fn video_preview_length_requested(
         .emit("video-preview-data-update", lock.video_preview_data.clone())
         .unwrap();
     }
-    networking::Message::CouldNotGetLength => {}
+    networking::Message::CouldNotGetLength => {
+      receive_pipeline_error(&mut stream, shared_state);
+    }
     _ => {
       panic!("Unknown message!: {:?}", msg);
     }
@@ -264,6 +267,7 @@ pub fn video_preview_data(
       let message = networking::receive_message(&mut stream)?;
       match message {
         networking::Message::CouldNotGeneratePreview => {
+          receive_pipeline_error(&mut stream, shared_state.clone());
           return Err(std::io::Error::from_raw_os_error(22));
         }
         networking::Message::NewChunk => {
@@ -305,6 +309,24 @@ pub fn video_preview_data(
   Ok(())
 }
 
+/// Receives why the server could not generate the pipeline, and passes it on to the UI so the error can be shown on the node it was found in
+fn receive_pipeline_error(stream: &mut TcpStream, shared_state: Arc<Mutex<SharedState>>) {
+  let bytes = networking::receive_file_as_bytes(stream);
+  let error = serde_json::from_slice::<PipelineError>(&bytes);
+  if error.is_err() {
+    println!("Could not read the pipeline error: {}", error.unwrap_err());
+    return;
+  }
+
+  let lock = shared_state.lock().unwrap();
+  lock
+    .window
+    .as_ref()
+    .unwrap()
+    .emit("pipeline-error", error.unwrap())
+    .unwrap();
+}
+
 /// Looks at the supplied file, and will return the codec string that should be supplied to MSE when creating the source buffer.
 fn get_codec_string(filename: String) -> Result<(String, bool), glib::Error> {
   let discoverer = gst_pbutils::Discoverer::new(gst::ClockTime::from_seconds(10)).unwrap();
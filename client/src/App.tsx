@@ -82,7 +82,7 @@ class App extends React.Component<Props, State> {
 			if (!this.state.initialConnectionDone) {
 				Communicator.invoke('get_initial_data', null, (data) => {
 					let node_register = data[1];
-					EditorNode.deserialiseRegister(node_register);
+					EditorNode.deserialiseRegister(node_register, data[2]);
 					this.setState({
 						Store: Store.deserialise(data[0])
 					})
@@ -137,6 +137,18 @@ class App extends React.Component<Props, State> {
 			EditorNode.moveCacheData(from, to);
 		})
 
+		// the server could not generate the video preview, so show why on the node which caused it
+		Communicator.on('pipeline-error', (error) => {
+			EditorNode.putPipelineError(error);
+			EventBus.dispatch(EventBus.EVENTS.NODE_EDITOR.FORCE_UPDATE, null);
+		});
+
+		// the server rejected a change to a node, so show why on the node
+		Communicator.on('node-validation-error', (data) => {
+			EditorNode.putValidationErrors(data[0], data[1]);
+			EventBus.dispatch(EventBus.EVENTS.NODE_EDITOR.FORCE_UPDATE, null);
+		});
+
 
 		// Getters
 		EventBus.registerGetter(EventBus.GETTERS.APP.STORE, () => this.state.Store);
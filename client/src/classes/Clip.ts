@@ -32,6 +32,16 @@ interface SourceClipInfo {
     subtitle_streams: Array<SubtitleStreamInfo>;
 }
 
+/**
+ * The format of a clip's streams, as far as it can be worked out without rendering the clip
+ */
+export interface StreamFormat {
+    framerate?: number,
+    resolution?: [number, number],
+    duration?: number, // in milliseconds
+    sample_rate?: number,
+}
+
 //#endregion Metadata Info
 
 export class SourceClip {
@@ -200,6 +210,25 @@ export class CompositedClip {
             });
         })
     }
+    private _format: StreamFormat = null;
+    getFormat() {
+        return this._format;
+    }
+    async fetchFormat() {
+        return await new Promise((res, rej) => {
+            // The format is worked out from the pipeline, so the length is usually known before the server has rendered anything
+            Communicator.invoke('get_clip_format', {
+                clipType: 'Composited',
+                id: this.id
+            }, (format: StreamFormat) => {
+                this._format = format;
+                if (format.duration != null && this._duration_ms == null) {
+                    this._duration_ms = format.duration;
+                }
+                res(format);
+            });
+        })
+    }
 }
 
 export class ClipIdentifier {
@@ -67,7 +67,7 @@ export default class Communicator {
     /**
      * Invokes a certain Rust command in the backend; `callback` can be used to capture returned data
      */
-    static invoke(event: string, args?: InvokeArgs, callback?: Callback) {
+    static invoke(event: string, args?: InvokeArgs, callback?: Callback, errorCallback?: Callback) {
         let promise = invoke(event, args);
         if (callback) {
             promise.then(callback);
@@ -75,6 +75,9 @@ export default class Communicator {
         promise.catch(err => {
             console.log("Invoke error caught!");
             console.log(err);
+            if (errorCallback) {
+                errorCallback(err);
+            }
         })
     }
 
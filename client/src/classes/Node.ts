@@ -1,9 +1,10 @@
 import Communicator, { ID } from "./Communicator";
 import Utils from "./Utils";
 import { v4 } from 'uuid';
-import { NodeRegistration, NodeRegistrationOutput, NodeRegistrationInput } from "./NodeRegistration";
+import { NodeCategory, NodeRegistration, NodeRegistrationOutput, NodeRegistrationInput } from "./NodeRegistration";
 import Cache from "./Cache";
 import EventBus from "./EventBus";
+import Store from "./Store";
 
 
 
@@ -52,17 +53,21 @@ export default class EditorNode {
     node_type: string; // the identifier of its node type
     properties: Map<string, any>; // all the current properties set on the node
     group: ID; // the ID of its `group` which helps to determine what nodes are shown when a composited clip is opened in the node editor
+    bypassed: boolean; // if set, the node's primary media input is passed straight through to its output
+    type_version: string; // the version of the node type which the properties were saved with
 
     public static NodeRegister: Map<string, NodeRegistration> = new Map(); // A map of all node types in the application
+    public static NodePalette: Array<[NodeCategory, Array<string>]> = []; // the node type ids listed under each category of the node palette
 
 
     /**
      * Takes the node registrations supplied by the Rust backend, and populates the NodeRegister accordingly
      */
-    public static deserialiseRegister(obj: any) {
+    public static deserialiseRegister(obj: any, palette: Array<[NodeCategory, Array<string>]> = []) {
         for (let node_type in obj) {
             EditorNode.NodeRegister.set(node_type, NodeRegistration.deserialise(obj[node_type]));
         }
+        EditorNode.NodePalette = palette;
     }
 
     constructor(
@@ -70,13 +75,17 @@ export default class EditorNode {
         id: ID,
         node_type: string,
         properties: Map<string, any>,
-        group: ID
+        group: ID,
+        bypassed = false,
+        type_version = '1.0.0'
     ) {
         this.position = position;
         this.id = id;
         this.node_type = node_type;
         this.properties = properties;
         this.group = group;
+        this.bypassed = bypassed;
+        this.type_version = type_version;
     }
 
 
@@ -94,6 +103,23 @@ export default class EditorNode {
         Cache.put(cacheOutputs2, Cache.get(cacheOutputs1));
     }
 
+    /**
+     * Stores an error from generating the pipeline (e.g. on the server when getting the video preview), so it is shown on the node it was found in
+     */
+    static putPipelineError(error: any) {
+        if (!error || !error.node_id) {
+            return;
+        }
+        Cache.put(this.cacheID(error.node_id) + "error", error);
+    }
+
+
+    /**
+     * Stores why a change to a node was rejected, so it can be shown on the node
+     */
+    static putValidationErrors(node_id: ID, errors: Array<any>) {
+        Cache.put(this.cacheID(node_id) + "validation", errors.map(error => Utils.formatValidationError(error)).join('\n'));
+    }
 
     static deserialise(obj: any) {
         if (Utils.propsUndefined(obj.position, obj.id, obj.node_type, obj.properties, obj.group)) {
@@ -103,7 +129,7 @@ export default class EditorNode {
         for (let p in obj.properties) {
             properties.set(p, obj.properties[p]);
         }
-        return new EditorNode(Position.deserialise(obj.position), obj.id, obj.node_type, properties, obj.group);
+        return new EditorNode(Position.deserialise(obj.position), obj.id, obj.node_type, properties, obj.group, obj.bypassed || false, obj.type_version);
     }
 
     serialise() {
@@ -113,6 +139,8 @@ export default class EditorNode {
         obj.node_type = this.node_type;
         obj.properties = {};
         obj.group = this.group;
+        obj.bypassed = this.bypassed;
+        obj.type_version = this.type_version;
         for (let [id, prop] of this.properties.entries()) {
             obj.properties[id] = prop;
         }
@@ -153,7 +181,12 @@ export default class EditorNode {
                     inputs.set(prop, NodeRegistrationInput.deserialise(data[prop]));
                 }
                 Cache.put(cacheID, inputs);
+                Cache.clear(this.cacheID + "error");
                 res(inputs);
+            }, (err) => {
+                // keep the last known inputs, so the node can still be edited to fix the error
+                Cache.put(this.cacheID + "error", err);
+                res(this.getInputsSync());
             });
         });
     }
@@ -178,11 +211,31 @@ export default class EditorNode {
                     outputs.set(prop, NodeRegistrationOutput.deserialise(data[prop]));
                 }
                 Cache.put(cacheID, outputs);
+                Cache.clear(this.cacheID + "error");
                 res(outputs);
+            }, (err) => {
+                Cache.put(this.cacheID + "error", err);
+                res(this.getOutputsSync());
             })
         });
     }
 
+    /**
+     * If the node's last change was rejected, or its inputs and outputs could not be obtained because of an error in this node (e.g. an invalid expression), returns the error
+     */
+    getErrorSync(): string {
+        let validation_error = Cache.get(this.cacheID + "validation");
+        if (validation_error) {
+            return validation_error;
+        }
+        let error = Cache.get(this.cacheID + "error");
+        // the whole pipeline fails to generate, so only show the error on the node it was found in
+        if (error && error.node_id === this.id) {
+            return Utils.formatPipelineError(error);
+        }
+        return null;
+    }
+
     // Updates a node's position, and calls `save`
     savePosition(newPosition) {
         this.position = Position.deserialise(newPosition);
@@ -195,6 +248,9 @@ export default class EditorNode {
     async save() {
         Communicator.invoke('update_node', {
             node: this.serialise()
+        }, () => Cache.clear(this.cacheID + "validation"), (errors) => {
+            // the change was rejected, so show why on the node
+            EditorNode.putValidationErrors(this.id, errors);
         });
         await new Promise((res, rej) => {
             setTimeout(res, 50);
@@ -218,9 +274,34 @@ export default class EditorNode {
             if (type.type == 'Number') {
                 props.set(prop, type.getNumberRestrictions().default);
             }
+            else if (type.type == 'Boolean') {
+                props.set(prop, type.getBooleanDefault());
+            }
+            else if (type.type == 'String') {
+                props.set(prop, '');
+            }
         }
 
-        return new EditorNode(position, v4(), type, props, group);
+        return new EditorNode(position, v4(), type, props, group, false, register_entry.version);
+    }
+
+    /**
+     * Whether a property can be keyframed; a property of a group node can be if the property inside the group it stands for can be
+     */
+    isAnimatable(property: string) {
+        let node_type = this.node_type;
+        if (node_type === 'node_group') {
+            let group = Store.getCurrentStore().node_groups.get(this.properties.get('node_group'));
+            let group_property = group?.properties.find(group_property => group_property.name === property);
+            let inner_node = group_property ? group.nodes[group_property.endpoint.node_id] : null;
+            if (!inner_node) {
+                return false;
+            }
+            node_type = inner_node.node_type;
+            property = group_property.endpoint.property;
+        }
+        let registration = EditorNode.NodeRegister.get(node_type);
+        return registration ? registration.animatable.includes(property) : false;
     }
 
     /**
@@ -231,7 +312,13 @@ export default class EditorNode {
         let property_entry = this.getInputsSync().get(property);
 
         let hasChanged = false;
-        if (property_entry.property_type.type == 'Number') {
+        if (property_entry.property_type.type == 'Number' && Utils.isKeyframed(newValue)) {
+            let restrictions = property_entry.property_type.getNumberRestrictions();
+            for (let keyframe of newValue.keyframes) {
+                keyframe.value = Math.min(Math.max(keyframe.value, restrictions.min), restrictions.max);
+            }
+        }
+        else if (property_entry.property_type.type == 'Number') {
             let restrictions = property_entry.property_type.getNumberRestrictions();
 
             let originalValue: number = parseFloat(newValue);
@@ -253,6 +340,12 @@ export default class EditorNode {
             }
             newValue = Number(newValue);
         }
+        else if (property_entry.property_type.type == 'String') {
+            newValue = String(newValue).substring(0, property_entry.property_type.getStringMaxLength());
+        }
+        else if (property_entry.property_type.type == 'Boolean') {
+            newValue = Boolean(newValue);
+        }
 
 
         if (this.properties.get(property) != newValue || typeof newValue == 'object') {
@@ -0,0 +1,65 @@
+import { ID } from "./Communicator";
+import { LinkEndpoint } from "./Pipeline";
+import Utils from "./Utils";
+
+
+/**
+ * An input, output or property of a group node, which stands for one of a node inside the group
+ */
+export class GroupEndpoint {
+    name: string;
+    display_name: string;
+    endpoint: LinkEndpoint;
+
+    constructor(name: string, display_name: string, endpoint: LinkEndpoint) {
+        this.name = name;
+        this.display_name = display_name;
+        this.endpoint = endpoint;
+    }
+
+    static deserialise(obj: any) {
+        if (Utils.propsUndefined(obj.name, obj.display_name, obj.endpoint)) {
+            throw new Error("Could not deserialise");
+        }
+
+        return new GroupEndpoint(obj.name, obj.display_name, LinkEndpoint.deserialise(obj.endpoint));
+    }
+}
+
+
+/**
+ * A set of linked nodes saved as a reusable node type; the nodes and links are only used by the Rust backend, so are kept as they were received
+ */
+export default class NodeGroup {
+    id: ID;
+    name: string;
+    description: string;
+    nodes: any;
+    links: any;
+    inputs: Array<GroupEndpoint>;
+    outputs: Array<GroupEndpoint>;
+    properties: Array<GroupEndpoint>;
+
+    constructor(id: ID, name: string, description: string, nodes: any, links: any,
+        inputs: Array<GroupEndpoint>, outputs: Array<GroupEndpoint>, properties: Array<GroupEndpoint>) {
+        this.id = id;
+        this.name = name;
+        this.description = description;
+        this.nodes = nodes;
+        this.links = links;
+        this.inputs = inputs;
+        this.outputs = outputs;
+        this.properties = properties;
+    }
+
+    static deserialise(obj: any) {
+        if (Utils.propsUndefined(obj.id, obj.name, obj.nodes, obj.links, obj.inputs, obj.outputs, obj.properties)) {
+            throw new Error("Could not deserialise");
+        }
+
+        return new NodeGroup(obj.id, obj.name, obj.description || "", obj.nodes, obj.links,
+            obj.inputs.map(GroupEndpoint.deserialise),
+            obj.outputs.map(GroupEndpoint.deserialise),
+            obj.properties.map(GroupEndpoint.deserialise));
+    }
+}
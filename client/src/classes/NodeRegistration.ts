@@ -59,6 +59,20 @@ export class PropertyType {
         }
         return this.extra_data as NumberRestrictions;
     }
+    // Can only be called if the property is a string type
+    getStringMaxLength() {
+        if (this.type != 'String') {
+            throw new Error("Cannot get string length from non-string type!");
+        }
+        return this.extra_data as number;
+    }
+    // Can only be called if the property is a boolean type
+    getBooleanDefault() {
+        if (this.type != 'Boolean') {
+            throw new Error("Cannot get boolean default from non-boolean type!");
+        }
+        return this.extra_data as boolean;
+    }
     // Can only be called if the property is pipeable (i.e. it's an input/can be piped into via the node editor)
     getPipeableType() {
         if (this.type != 'Pipeable') {
@@ -108,22 +122,54 @@ export class NodeRegistrationOutput {
     display_name: string;
     name: string;
     property_type: PipeableType;
+    number_stream: any; // If set, the output is a stream of numbers (a constant, or keyframes) rather than media
 
-    constructor(name: string, display_name: string, description: string, property_type: PipeableType) {
+    constructor(name: string, display_name: string, description: string, property_type: PipeableType, number_stream: any = null) {
         this.name = name;
         this.display_name = display_name;
         this.description = description;
         this.property_type = property_type;
+        this.number_stream = number_stream;
+    }
+
+    isNumberStream() {
+        return this.number_stream !== null && this.number_stream !== undefined;
+    }
+
+    /**
+     * Whether the output can be linked into an input; number streams can only drive number properties, and media can only be piped into pipeable inputs which it has enough streams for
+     */
+    canLinkTo(input: NodeRegistrationInput) {
+        if (this.isNumberStream()) {
+            return input.property_type.type === 'Number';
+        }
+        return input.property_type.type === 'Pipeable'
+            && Utils.pipeableTypeMeetsMinReq(this.property_type, input.property_type.getPipeableType());
     }
 
     static deserialise(obj: any) {
         if (Utils.propsUndefined(obj.name, obj.display_name, obj.description, obj.property_type)) {
             throw new Error("Could not deserialise");
         }
-        return new NodeRegistrationInput(obj.name, obj.display_name, obj.description, obj.property_type);
+        return new NodeRegistrationOutput(obj.name, obj.display_name, obj.description, obj.property_type, obj.number_stream);
     }
 }
 
+/**
+ * The section of the node palette a node type is listed in
+ */
+export type NodeCategory = 'video' | 'audio' | 'generator' | 'utility';
+
+
+/**
+ * Extra information about a property, shown alongside it in the properties panel
+ */
+export interface PropertyHelp {
+    help: string | null,
+    units: string | null
+}
+
+
 /**
  * Specifies a particular node type. Excludes functions to generate inputs/properties/outputs - that is exclusively for the Rust side to handle
  */
@@ -132,12 +178,35 @@ export class NodeRegistration {
     display_name: string;
     id: string;
     default_properties: Map<string, NodeRegistrationInput>;
-
-    constructor(id: string, display_name: string, description: string, default_properties: Map<string, NodeRegistrationInput>) {
+    category: NodeCategory;
+    tags: Array<string>; // extra words the node type can be found by when searching the palette
+    version: string;
+    property_help: Map<string, PropertyHelp>;
+    animatable: Array<string>; // the number properties which can be keyframed
+
+    constructor(id: string, display_name: string, description: string, default_properties: Map<string, NodeRegistrationInput>,
+        category: NodeCategory = 'utility', tags: Array<string> = [], version: string = '1.0.0', property_help: Map<string, PropertyHelp> = new Map(),
+        animatable: Array<string> = []) {
         this.id = id;
         this.display_name = display_name;
         this.description = description;
         this.default_properties = default_properties;
+        this.category = category;
+        this.tags = tags;
+        this.version = version;
+        this.property_help = property_help;
+        this.animatable = animatable;
+    }
+
+    /**
+     * Whether the node type matches a palette search, by its name, description or tags
+     */
+    matchesSearch(search: string) {
+        search = search.trim().toLowerCase();
+        return search === ''
+            || this.display_name.toLowerCase().includes(search)
+            || this.description.toLowerCase().includes(search)
+            || this.tags.some(tag => tag.toLowerCase().includes(search));
     }
 
     static deserialise(obj: any) {
@@ -150,6 +219,12 @@ export class NodeRegistration {
             props.set(prop, NodeRegistrationInput.deserialise(obj.default_properties[prop]));
         }
 
-        return new NodeRegistration(obj.id, obj.display_name, obj.description, props);
+        let property_help = new Map<string, PropertyHelp>();
+        for (let prop in obj.property_help || {}) {
+            property_help.set(prop, obj.property_help[prop]);
+        }
+
+        return new NodeRegistration(obj.id, obj.display_name, obj.description, props,
+            obj.category, obj.tags || [], obj.version, property_help, obj.animatable || []);
     }
 }
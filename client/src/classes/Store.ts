@@ -1,5 +1,6 @@
 import { ID } from "./Communicator";
 import EditorNode from './Node';
+import NodeGroup from "./NodeGroup";
 import Pipeline from "./Pipeline";
 import { CompositedClip, SourceClip } from "./Clip";
 import Utils from "./Utils";
@@ -56,21 +57,29 @@ export default class Store {
     nodes: Map<ID, EditorNode>;
     clips: ClipStore;
     pipeline: Pipeline;
+    node_groups: Map<ID, NodeGroup>;
+    solo_node: ID; // if set, the video preview only renders the pipeline up to this node
 
     constructor(
         nodes?: Map<ID, EditorNode>,
         clips?: ClipStore,
         pipeline?: Pipeline,
-
+        node_groups?: Map<ID, NodeGroup>,
+        solo_node: ID = null,
     ) {
         if (!nodes) {
             nodes = new Map();
             clips = new ClipStore(new Map(), new Map());
             pipeline = new Pipeline([]);
         }
+        if (!node_groups) {
+            node_groups = new Map();
+        }
         this.nodes = nodes;
         this.clips = clips;
         this.pipeline = pipeline;
+        this.node_groups = node_groups;
+        this.solo_node = solo_node;
     }
 
     static deserialise(obj: any) {
@@ -83,7 +92,13 @@ export default class Store {
             nodes.set(id, EditorNode.deserialise(obj.nodes[id]));
         }
 
-        return new Store(nodes, ClipStore.deserialise(obj.clips), Pipeline.deserialise(obj.pipeline));
+        // stores saved before node groups were added have none
+        let node_groups = new Map();
+        for (let id in obj.node_groups || {}) {
+            node_groups.set(id, NodeGroup.deserialise(obj.node_groups[id]));
+        }
+
+        return new Store(nodes, ClipStore.deserialise(obj.clips), Pipeline.deserialise(obj.pipeline), node_groups, obj.solo_node || null);
     }
     serialise() {
 
@@ -91,10 +106,16 @@ export default class Store {
         for (let [k, v] of this.nodes.entries()) {
             nodes[k] = v.serialise();
         }
+        let node_groups: any = {};
+        for (let [k, v] of this.node_groups.entries()) {
+            node_groups[k] = v;
+        }
         return {
             nodes,
             clips: this.clips.serialise(),
             pipeline: this.pipeline,
+            node_groups,
+            solo_node: this.solo_node,
         }
     }
 
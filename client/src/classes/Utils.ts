@@ -90,4 +90,77 @@ export default class Utils {
         Unknown: "gray-300" // fallback option
 
     };
+
+    /**
+     * Formats the keyframes of a keyframed number property so they can be edited, e.g. `0:1.2, 2:5:ease`
+     */
+    static formatKeyframes(value: any) {
+        if (!Utils.isKeyframed(value)) {
+            return '';
+        }
+        return value.keyframes.map((keyframe) => {
+            let text = `${keyframe.time}:${keyframe.value}`;
+            if (keyframe.interpolation && keyframe.interpolation != 'linear') {
+                text += `:${keyframe.interpolation}`;
+            }
+            return text;
+        }).join(', ');
+    }
+
+    /**
+     * Parses keyframes in the form `time:value[:interpolation], ...` (times in seconds; interpolation is linear, hold or ease) into a keyframed number property.
+     * Returns null if the text is not valid
+     */
+    static parseKeyframes(text: string) {
+        let keyframes = [];
+        for (let part of text.split(',')) {
+            part = part.trim();
+            if (part.length == 0) {
+                continue;
+            }
+            let [time, value, interpolation] = part.split(':').map((x) => x.trim());
+            interpolation = interpolation || 'linear';
+            if (isNaN(parseFloat(time)) || isNaN(parseFloat(value)) || !['linear', 'hold', 'ease'].includes(interpolation)) {
+                return null;
+            }
+            keyframes.push({ time: parseFloat(time), value: parseFloat(value), interpolation });
+        }
+        if (keyframes.length == 0) {
+            return null;
+        }
+        return { keyframes };
+    }
+
+    /**
+     * Returns true if a number property's value is a set of keyframes, rather than a constant
+     */
+    static isKeyframed(value: any) {
+        return typeof value === 'object' && value !== null && Array.isArray(value.keyframes);
+    }
+
+    /**
+     * Describes a validation error returned by the Rust backend when a node's properties do not match their types
+     */
+    static formatValidationError(error: any) {
+        if (error.kind === 'UnknownNodeType') {
+            return 'Unknown node type';
+        }
+        if (error.kind.WrongType) {
+            return `${error.property} must be a ${error.kind.WrongType.expected}`;
+        }
+        if (error.kind.TooLong) {
+            return `${error.property} must be at most ${error.kind.TooLong.max_length} characters long`;
+        }
+        return `${error.property} is invalid`;
+    }
+
+    /**
+     * Formats an error from generating the pipeline, naming the property it was found in if known
+     */
+    static formatPipelineError(error: any) {
+        if (error.property) {
+            return `${error.property}: ${error.message}`;
+        }
+        return error.message;
+    }
 }
@@ -187,6 +187,9 @@ class ClipComponent extends React.Component<Props, State> {
         else {
             this.props.clip.fetchType().then(e => this.forceUpdate()); // If the type is not available, get the type, then refresh once it is available
         }
+        if (this.props.clip instanceof CompositedClip && !this.props.clip.getFormat()) {
+            this.props.clip.fetchFormat().then(e => this.forceUpdate()); // Likewise for the format, which gives the length of the clip without waiting for the server
+        }
 
         let text = (
             <div className="flex-1">
@@ -1,10 +1,11 @@
-import { faChevronDown, faTimesCircle, faTrash } from '@fortawesome/free-solid-svg-icons';
+import { faChevronDown, faEye, faForward, faTimesCircle, faTrash } from '@fortawesome/free-solid-svg-icons';
 import { FontAwesomeIcon } from '@fortawesome/react-fontawesome';
 import React from 'react';
 import { Connection, Handle, Position } from 'react-flow-renderer';
 import EditorNode from '../../classes/Node';
 import AnimateHeight from 'react-animate-height';
 import EventBus from '../../classes/EventBus';
+import Communicator from '../../classes/Communicator';
 import Store from '../../classes/Store';
 import ClipDropComponent from '../shared/ClipDropComponent';
 
@@ -40,6 +41,19 @@ export default class EditorNodeComponent extends React.Component<Props, State> {
         this.props.data.node.save();
     }
 
+    toggleBypass() {
+        this.props.data.node.bypassed = !this.props.data.node.bypassed;
+        this.props.data.node.save();
+    }
+
+    /**
+     * Solos the node, so the video preview only shows the pipeline up to it; soloing it again shows the whole pipeline
+     */
+    toggleSolo() {
+        let soloed = Store.getCurrentStore().solo_node === this.props.data.node.id;
+        Communicator.invoke('solo_node', { id: soloed ? null : this.props.data.node.id });
+    }
+
     render() {
 
         if (this.props.data.node.getInputsSync() == null || this.props.data.node.getOutputsSync() == null) return <p>Invalid EditorNodeComponent!</p>;
@@ -56,8 +70,8 @@ export default class EditorNodeComponent extends React.Component<Props, State> {
         for (let [property, prop] of this.props.data.node.getInputsSync().entries()) {
             let accepted_type = prop.property_type;
 
-            if (accepted_type.type === 'Pipeable') {
-                // For each pipeable input, we create a target handle so that other nodes can connect to it
+            if (accepted_type.type === 'Pipeable' || accepted_type.type === 'Number') {
+                // For each pipeable input, we create a target handle so that other nodes can connect to it; number properties can also be driven by number streams
 
 
 
@@ -123,16 +137,32 @@ export default class EditorNodeComponent extends React.Component<Props, State> {
             border = "border-pink-600";
         }
 
+        let error = this.props.data.node.getErrorSync();
+
         let delete_btn = (
             <button className="float-right text-red-600 hover:bg-white hover:bg-opacity-20 px-1 rounded transition-colors" onClick={(e) => { this.props.data.deleteNode(); e.stopPropagation() }}>
                 <FontAwesomeIcon icon={faTrash} />
             </button>
         );
+        let bypass_btn = (
+            <button className={`float-right px-1 rounded transition-colors hover:bg-white hover:bg-opacity-20 ${this.props.data.node.bypassed ? 'text-yellow-400' : 'text-gray-500'}`}
+                title="Bypass" onClick={(e) => { this.toggleBypass(); e.stopPropagation() }}>
+                <FontAwesomeIcon icon={faForward} />
+            </button>
+        );
+        let solo_btn = (
+            <button className={`float-right px-1 rounded transition-colors hover:bg-white hover:bg-opacity-20 ${Store.getCurrentStore().solo_node === this.props.data.node.id ? 'text-blue-400' : 'text-gray-500'}`}
+                title="Solo" onClick={(e) => { this.toggleSolo(); e.stopPropagation() }}>
+                <FontAwesomeIcon icon={faEye} />
+            </button>
+        );
         if (this.props.data.node.node_type === 'output') {
             delete_btn = null;
+            bypass_btn = null;
+            solo_btn = null;
         }
         return (
-            <div className={`bg-gray-900 text-white rounded-md border ${border} pb-1`} onClick={(e) => EventBus.dispatch(EventBus.EVENTS.APP.SET_SELECTION, this.props.data.node)}>
+            <div className={`bg-gray-900 text-white rounded-md border ${border} pb-1 ${this.props.data.node.bypassed ? 'opacity-60' : ''}`} onClick={(e) => EventBus.dispatch(EventBus.EVENTS.APP.SET_SELECTION, this.props.data.node)}>
                 <div className={`transition-colors duration-${EditorNodeComponent.EXPAND_DURATION} p-2 border-b ${this.state.expanded ? "border-gray-800" : 'border-transparent'} `}>
 
                     <span className="text-sm">
@@ -142,8 +172,11 @@ export default class EditorNodeComponent extends React.Component<Props, State> {
                         {node_registration.display_name}
 
                         {delete_btn}
+                        {bypass_btn}
+                        {solo_btn}
                     </span>
                 </div>
+                {error ? <p className="px-2 py-1 text-xs text-red-500">{error}</p> : null}
                 {properties}
             </div>
         );
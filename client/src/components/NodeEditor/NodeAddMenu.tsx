@@ -1,38 +1,97 @@
 
 import { Menu, Transition } from '@headlessui/react'
-import { Fragment } from 'react';
+import { Fragment, useState } from 'react';
 import EventBus from '../../classes/EventBus';
 import EditorNode, { Position } from '../../classes/Node';
+import NodeGroup from '../../classes/NodeGroup';
+import { NodeCategory } from '../../classes/NodeRegistration';
+import Store from '../../classes/Store';
+
+
+const CATEGORY_NAMES: Record<NodeCategory, string> = {
+    video: 'Video',
+    audio: 'Audio',
+    generator: 'Generators',
+    utility: 'Utilities',
+};
 
 
 /**
- * A simple menu for adding nodes to the node editor
+ * A menu for adding nodes to the node editor, with the node types grouped by category and searchable by name, description or tag
  */
 export default function NodeAddMenu() {
+    let [search, setSearch] = useState('');
 
-    function dispatchAddNode(node_type) {
+    function createNode(node_type_id: string) {
         let state = EventBus.getValue(EventBus.GETTERS.NODE_EDITOR.CURRENT_INTERNAL_STATE);
         let x = (state.width / 2 - state.transform[0]) / state.transform[2];
         let y = (state.height / 2 - state.transform[1]) / state.transform[2];
 
         let pos = new Position(x, y);
-        let node = EditorNode.createNode(node_type.id, EventBus.getValue(EventBus.GETTERS.NODE_EDITOR.CURRENT_GROUP), pos);
+        return EditorNode.createNode(node_type_id, EventBus.getValue(EventBus.GETTERS.NODE_EDITOR.CURRENT_GROUP), pos);
+    }
 
-        EventBus.dispatch(EventBus.EVENTS.NODE_EDITOR.ADD_NODE, node);
+    function dispatchAddNode(node_type) {
+        EventBus.dispatch(EventBus.EVENTS.NODE_EDITOR.ADD_NODE, createNode(node_type.id));
     };
 
+    // Group nodes are added as a copy of a particular node group
+    function dispatchAddGroupNode(group: NodeGroup) {
+        let node = createNode('node_group');
+        node.properties.set('node_group', group.id);
+
+        EventBus.dispatch(EventBus.EVENTS.NODE_EDITOR.ADD_NODE, node);
+    }
+
     let register = EditorNode.NodeRegister;
-    let items = [];
-    for (let [id, node_type] of register.entries()) {
-        items.push(
+    let sections = [];
+    for (let [category, ids] of EditorNode.NodePalette) {
+        let items = [];
+        for (let id of ids) {
+            let node_type = register.get(id);
+            if (!node_type || !node_type.matchesSearch(search)) {
+                continue;
+            }
+            items.push(
+                <Menu.Item>
+                    {({ active }) => (
+                        <button
+                            className={`${active ? 'bg-pink-600' : ''
+                                } group flex rounded-md items-center w-full px-2 py-2 text-sm text-white`}
+                            onClick={() => dispatchAddNode(node_type)}
+                            title={node_type.description}
+                        >
+                            {node_type.display_name}
+                        </button>
+                    )
+                    }
+                </Menu.Item >
+            );
+        }
+        if (items.length > 0) {
+            sections.push(
+                <div className="px-1 py-1 ">
+                    <p className="px-2 text-xs text-gray-400">{CATEGORY_NAMES[category] || category}</p>
+                    {items}
+                </div>
+            );
+        }
+    }
+    let groupItems = [];
+    for (let group of Store.getCurrentStore().node_groups.values()) {
+        if (search.trim() !== '' && !group.name.toLowerCase().includes(search.trim().toLowerCase())) {
+            continue;
+        }
+        groupItems.push(
             <Menu.Item>
                 {({ active }) => (
                     <button
                         className={`${active ? 'bg-pink-600' : ''
                             } group flex rounded-md items-center w-full px-2 py-2 text-sm text-white`}
-                        onClick={() => dispatchAddNode(node_type)}
+                        onClick={() => dispatchAddGroupNode(group)}
+                        title={group.description}
                     >
-                        {node_type.display_name}
+                        {group.name}
                     </button>
                 )
                 }
@@ -56,10 +115,18 @@ export default function NodeAddMenu() {
                 leaveFrom="transform opacity-100 scale-100"
                 leaveTo="transform opacity-0 scale-95"
             >
-                <Menu.Items className="absolute right-0 w-56 mt-2 origin-top-right bg-gray-900 divide-y divide-gray-100 rounded-md shadow-lg ring-1 ring-black ring-opacity-5 focus:outline-none">
+                <Menu.Items className="absolute right-0 w-56 mt-2 max-h-96 overflow-y-auto origin-top-right bg-gray-900 divide-y divide-gray-100 rounded-md shadow-lg ring-1 ring-black ring-opacity-5 focus:outline-none">
                     <div className="px-1 py-1 ">
-                        {items}
+                        {/* typing would otherwise move between the menu items */}
+                        <input className="bg-gray-600 px-2 py-1 w-full outline-none text-sm text-white rounded-md" type="text" placeholder="Search" value={search}
+                            onChange={(e) => setSearch(e.target.value)} onKeyDown={(e) => e.stopPropagation()} />
                     </div>
+                    {sections}
+                    {groupItems.length > 0 &&
+                        <div className="px-1 py-1 ">
+                            {groupItems}
+                        </div>
+                    }
                 </Menu.Items>
             </Transition>
         </Menu>
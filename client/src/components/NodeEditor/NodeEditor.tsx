@@ -1,5 +1,5 @@
 import React from 'react';
-import ReactFlow, { Connection, Edge, ReactFlowProvider } from 'react-flow-renderer';
+import ReactFlow, { Connection, Edge, Elements, ReactFlowProvider } from 'react-flow-renderer';
 import EventBus from '../../classes/EventBus';
 import EditorNode, { Position } from '../../classes/Node';
 import { Link, LinkEndpoint } from '../../classes/Pipeline';
@@ -12,6 +12,7 @@ import { Transition, animated } from 'react-spring'
 import { v4 } from 'uuid';
 import CustomEdgeComponent from './CustomEdgeComponent';
 import Communicator from '../../classes/Communicator';
+import NodeGroupCreator from './NodeGroupCreator';
 
 interface Props {
     initial_group?: string;
@@ -26,11 +27,14 @@ interface State {
         message: string,
         type: NotificationType,
         id: string
-    }>
+    }>,
+    selected_nodes: Array<string>,
+    creating_group: boolean
 }
 
 class NodeEditor extends React.Component<Props, State> {
     static NOTIFICATION_TIMEOUT = 5000;
+    static MAX_LINT_NOTIFICATIONS = 5; // any further issues are summarised in one notification
 
 
     reactFlowRef: React.Ref<HTMLDivElement>;
@@ -38,11 +42,14 @@ class NodeEditor extends React.Component<Props, State> {
         super(props);
         this.state = {
             group: props.initial_group || "",
-            notifications: []
+            notifications: [],
+            selected_nodes: [],
+            creating_group: false
         }
 
         this.addNode = this.addNode.bind(this);
         this.changeGroup = this.changeGroup.bind(this);
+        this.linkRejected = this.linkRejected.bind(this);
     }
 
     componentDidMount() {
@@ -53,12 +60,20 @@ class NodeEditor extends React.Component<Props, State> {
         EventBus.on(EventBus.EVENTS.NODE_EDITOR.FORCE_UPDATE, () => {
             this.forceUpdate();
         });
+
+        // the server rejected a link, which has already been removed from the store
+        Communicator.on('link-rejected', this.linkRejected);
     }
 
     componentWillUnmount() {
         EventBus.remove(EventBus.EVENTS.NODE_EDITOR.ADD_NODE, this.addNode);
         EventBus.remove(EventBus.EVENTS.NODE_EDITOR.CHANGE_GROUP, this.changeGroup);
         EventBus.unregisterGetter(EventBus.GETTERS.NODE_EDITOR.CURRENT_GROUP);
+        Communicator.off('link-rejected', this.linkRejected);
+    }
+
+    linkRejected(reason: string) {
+        this.addNotification(`The link was rejected: ${reason}`, 'error');
     }
 
     addNode(node: EditorNode) {
@@ -94,6 +109,28 @@ class NodeEditor extends React.Component<Props, State> {
         });
     }
 
+    /**
+     * Checks the whole pipeline for problems, showing a notification for each of the first few issues found
+     */
+    lintPipeline() {
+        Communicator.invoke('lint_pipeline', null, (issues: Array<any>) => {
+            if (issues.length === 0) {
+                this.addNotification('No problems found in the pipeline', 'success');
+                return;
+            }
+            let store = Store.getCurrentStore();
+            for (let issue of issues.slice(0, NodeEditor.MAX_LINT_NOTIFICATIONS)) {
+                let node = issue.node_id ? store.nodes.get(issue.node_id) : null;
+                let node_name = node ? EditorNode.NodeRegister.get(node.node_type)?.display_name || node.node_type : null;
+                let message = node_name ? `${node_name}: ${issue.message}` : issue.message;
+                this.addNotification(message, issue.severity === 'Warning' ? 'warning' : 'error');
+            }
+            if (issues.length > NodeEditor.MAX_LINT_NOTIFICATIONS) {
+                this.addNotification(`${issues.length - NodeEditor.MAX_LINT_NOTIFICATIONS} more problems were found`, 'info');
+            }
+        }, (err) => this.addNotification(`Could not check the pipeline: ${err}`, 'error'));
+    }
+
     /**
      * Obtains each node's inputs and outputs so they can be displayed in the node editor
      */
@@ -126,6 +163,13 @@ class NodeEditor extends React.Component<Props, State> {
         }
         let link = new Link(new LinkEndpoint(e.source, e.sourceHandle), new LinkEndpoint(e.target, e.targetHandle));
 
+        let output = store.nodes.get(e.source).getOutputsSync()?.get(e.sourceHandle);
+        let input = store.nodes.get(e.target).getInputsSync()?.get(e.targetHandle);
+        if (output && input && !output.canLinkTo(input)) {
+            // number streams can only be linked into number properties, and media into pipeable inputs which it has enough streams for
+            this.addNotification(`${output.display_name} cannot be linked into ${input.display_name}`, 'error');
+            return;
+        }
 
 
         if (store.pipeline.hasCyclesWithLink(store, link)) {
@@ -136,7 +180,7 @@ class NodeEditor extends React.Component<Props, State> {
             // Otherwise, add the link
             Communicator.invoke('add_link', {
                 link
-            });
+            }, null, (err) => this.addNotification(`Could not add the link: ${err}`, 'error'));
 
 
             // Update all node's inputs and outputs
@@ -201,6 +245,16 @@ class NodeEditor extends React.Component<Props, State> {
         this.addNode(node);
     }
 
+    /**
+     * Keeps track of which nodes are selected, so they can be saved as a node group
+     */
+    selectionChanged(elements: Elements | null) {
+        let selected_nodes = (elements || []).filter(e => e.type === 'editor_node').map(e => e.id);
+        this.setState({
+            selected_nodes
+        });
+    }
+
     render() {
         let store = Store.getCurrentStore();
         let elements = [];
@@ -243,7 +297,18 @@ class NodeEditor extends React.Component<Props, State> {
             if (input) {
                 let to_node_type = input.property_type;
                 let output = from_node.getOutputsSync().get(link.from.property);
-                if (output) {
+                if (output && output.isNumberStream()) {
+                    // number streams carry no media, so are drawn as plain edges
+                    elements.push({
+                        id: link.id,
+                        source: link.from.node_id,
+                        sourceHandle: link.from.property,
+                        target: link.to.node_id,
+                        targetHandle: link.to.property,
+                        arrowHeadType: 'arrowclosed',
+                    });
+                }
+                else if (output && to_node_type.type === 'Pipeable') {
                     elements.push({
                         id: link.id,
                         source: link.from.node_id,
@@ -274,9 +339,28 @@ class NodeEditor extends React.Component<Props, State> {
                         onNodeDragStop={(_, node) => store.nodes.get(node.id).savePosition(node.position)}
 
                         onConnect={(e) => this.addLink(e)}
+
+                        onSelectionChange={(e) => this.selectionChanged(e)}
                     />
                     <NodeEditorStateManager />
                 </ReactFlowProvider>
+                <div className="absolute left-2 top-2 z-40 flex">
+                    <button className="mr-2 px-4 py-2 text-sm font-medium text-white bg-gray-900 rounded-md hover:bg-gray-800"
+                        onClick={() => this.lintPipeline()}>
+                        Check Pipeline
+                    </button>
+                    {this.state.selected_nodes.length > 0 &&
+                        <button className="px-4 py-2 text-sm font-medium text-white bg-gray-900 rounded-md hover:bg-gray-800"
+                            onClick={() => this.setState({ creating_group: true })}>
+                            Save as Group
+                        </button>
+                    }
+                </div>
+                {this.state.creating_group &&
+                    <NodeGroupCreator nodeIds={this.state.selected_nodes}
+                        onClose={() => this.setState({ creating_group: false })}
+                        onError={(message) => this.addNotification(message, 'error')} />
+                }
                 <div className="absolute right-2 bottom-2 z-50">
                     <Transition items={this.state.notifications}
                         keys={item => item.id}
@@ -0,0 +1,125 @@
+import { useState } from 'react';
+import Communicator, { ID } from '../../classes/Communicator';
+import EditorNode from '../../classes/Node';
+import { GroupEndpoint } from '../../classes/NodeGroup';
+import { LinkEndpoint } from '../../classes/Pipeline';
+import Store from '../../classes/Store';
+
+interface Props {
+    nodeIds: Array<ID>;
+    onClose: () => void;
+    onError: (message: string) => void;
+}
+
+type EndpointKind = 'inputs' | 'outputs' | 'properties';
+
+interface EndpointChoice {
+    kind: EndpointKind;
+    endpoint: LinkEndpoint;
+    display_name: string;
+    node_name: string;
+}
+
+
+/**
+ * A dialog for saving the selected nodes as a node group, in which the user chooses which inputs, outputs and properties of the nodes the group node should have
+ */
+export default function NodeGroupCreator(props: Props) {
+    let [name, setName] = useState('');
+    let [description, setDescription] = useState('');
+    let [chosen, setChosen] = useState(new Map<string, string>()); // the names given to each chosen endpoint, by endpoint ID
+
+    let store = Store.getCurrentStore();
+    let choices: Array<EndpointChoice> = [];
+    for (let id of props.nodeIds) {
+        let node = store.nodes.get(id);
+        if (!node || !node.getInputsSync() || !node.getOutputsSync()) {
+            continue;
+        }
+        let node_name = EditorNode.NodeRegister.get(node.node_type)?.display_name || node.node_type;
+        for (let input of node.getInputsSync().values()) {
+            let endpoint = new LinkEndpoint(id, input.name);
+            let kind: EndpointKind = input.property_type.type === 'Pipeable' ? 'inputs' : 'properties';
+            // inputs which are linked from inside the selection stay linked
+            let linked_inside = store.pipeline.links.some(link => link.to.id === endpoint.id && props.nodeIds.includes(link.from.node_id));
+            if (kind === 'inputs' && linked_inside) {
+                continue;
+            }
+            choices.push({ kind, endpoint, display_name: input.display_name, node_name });
+        }
+        for (let output of node.getOutputsSync().values()) {
+            choices.push({ kind: 'outputs', endpoint: new LinkEndpoint(id, output.name), display_name: output.display_name, node_name });
+        }
+    }
+
+    function toggle(choice: EndpointChoice) {
+        let new_chosen = new Map(chosen);
+        if (new_chosen.has(choice.endpoint.id)) {
+            new_chosen.delete(choice.endpoint.id);
+        }
+        else {
+            new_chosen.set(choice.endpoint.id, choice.display_name);
+        }
+        setChosen(new_chosen);
+    }
+
+    function rename(choice: EndpointChoice, display_name: string) {
+        let new_chosen = new Map(chosen);
+        new_chosen.set(choice.endpoint.id, display_name);
+        setChosen(new_chosen);
+    }
+
+    function save() {
+        let endpoints = { inputs: [], outputs: [], properties: [] };
+        let names = new Map<string, number>();
+        for (let choice of choices) {
+            let display_name = chosen.get(choice.endpoint.id);
+            if (display_name === undefined) {
+                continue;
+            }
+            // the group node's endpoints are named after their display names, made unique
+            let base = display_name.trim().toLowerCase().replace(/[^a-z0-9]+/g, '_') || choice.endpoint.property;
+            let count = names.get(choice.kind + base) || 0;
+            names.set(choice.kind + base, count + 1);
+            let endpoint_name = count > 0 ? `${base}_${count + 1}` : base;
+            endpoints[choice.kind].push(new GroupEndpoint(endpoint_name, display_name, choice.endpoint));
+        }
+
+        Communicator.invoke('create_node_group', {
+            nodeIds: props.nodeIds,
+            name,
+            description,
+            inputs: endpoints.inputs,
+            outputs: endpoints.outputs,
+            properties: endpoints.properties,
+        }, () => props.onClose(), (err) => props.onError(`Could not save the group: ${err}`));
+    }
+
+    let sections: Array<[EndpointKind, string]> = [['inputs', 'Inputs'], ['properties', 'Properties'], ['outputs', 'Outputs']];
+    return (
+        <div className="absolute inset-0 z-50 flex items-center justify-center bg-black bg-opacity-50">
+            <div className="bg-gray-800 text-white rounded p-4 w-96 max-h-full overflow-y-auto">
+                <h1 className="text-lg mb-2">Save {props.nodeIds.length} node{props.nodeIds.length === 1 ? '' : 's'} as a group</h1>
+                <input className="bg-gray-600 p-2 w-full outline-none mb-1" type="text" placeholder="Name" value={name} onChange={(e) => setName(e.target.value)} />
+                <input className="bg-gray-600 p-2 w-full outline-none mb-2" type="text" placeholder="Description" value={description} onChange={(e) => setDescription(e.target.value)} />
+                {sections.map(([kind, title]) => (
+                    <div key={kind} className="mb-2">
+                        <h2 className="text-sm text-gray-400">{title}</h2>
+                        {choices.filter(choice => choice.kind === kind).map(choice => (
+                            <div key={choice.endpoint.id} className="flex items-center text-xs py-1">
+                                <input type="checkbox" className="mr-2" checked={chosen.has(choice.endpoint.id)} onChange={() => toggle(choice)} />
+                                {chosen.has(choice.endpoint.id) ?
+                                    <input className="bg-gray-600 px-1 flex-1 outline-none" type="text" value={chosen.get(choice.endpoint.id)} onChange={(e) => rename(choice, e.target.value)} />
+                                    : <span className="flex-1">{choice.node_name}: {choice.display_name}</span>}
+                            </div>
+                        ))}
+                    </div>
+                ))}
+                <div className="flex justify-end">
+                    <button className="px-4 py-2 text-sm rounded-md hover:bg-gray-700" onClick={() => props.onClose()}>Cancel</button>
+                    <button className="px-4 py-2 text-sm rounded-md bg-pink-600 hover:bg-pink-700 disabled:opacity-50" disabled={name.trim() === ''} onClick={() => save()}>Save</button>
+                </div>
+            </div>
+        </div>
+    );
+}
@@ -15,6 +15,22 @@ class PropertiesPanel extends React.Component {
         super(props);
     }
 
+    /**
+     * Sets the keyframes of a number property from the text entered; clearing the text makes the property constant again
+     */
+    changeKeyframes(selection: EditorNode, prop: string, value: any, text: string) {
+        if (text.trim().length == 0) {
+            if (Utils.isKeyframed(value)) {
+                selection.changeProperty(prop, value.keyframes[0].value);
+            }
+            return;
+        }
+        let keyframes = Utils.parseKeyframes(text);
+        if (keyframes) {
+            selection.changeProperty(prop, keyframes);
+        }
+    }
+
     /**
      * Rendering all the properties for a node
      */
@@ -38,10 +54,36 @@ class PropertiesPanel extends React.Component {
                 }
                 else if (prop_detail.property_type.type === 'Number') {
                     let details = prop_detail.property_type.getNumberRestrictions();
-                    value = Math.round(value / details.step) * details.step;
+                    let keyframed = Utils.isKeyframed(value);
+                    if (!keyframed) {
+                        value = Math.round(value / details.step) * details.step;
+                    }
+                    let keyframe_value = value;
+                    // properties which are already keyframed keep the keyframe input, so the keyframes can be cleared
+                    let animatable = keyframed || selection.isAnimatable(prop);
                     display = (
                         <div>
-                            <input key={Date.now()} className="bg-gray-600 p-2 w-full outline-none" defaultValue={value} type="number" step={details.step} min={details.min} max={details.max} onBlur={(e) => selection.changeProperty(prop, e.target.value)} onKeyDown={(e) => {
+                            {keyframed ? <p className="text-xs">Keyframed</p> : <input key={Date.now()} className="bg-gray-600 p-2 w-full outline-none" defaultValue={value} type="number" step={details.step} min={details.min} max={details.max} onBlur={(e) => selection.changeProperty(prop, e.target.value)} onKeyDown={(e) => {
+                                if (e.key === 'Enter') {
+                                    e.preventDefault();
+                                    let target: HTMLInputElement & EventTarget = (e.target as any);
+                                    selection.changeProperty(prop, target.value);
+                                }
+                            }} />}
+                            {animatable && <input key={`${Date.now()}-keyframes`} className="bg-gray-600 p-2 mt-1 w-full outline-none text-xs" defaultValue={Utils.formatKeyframes(value)} placeholder="Keyframes (time:value[:hold|ease], ...)" type="text" onBlur={(e) => this.changeKeyframes(selection, prop, keyframe_value, e.target.value)} onKeyDown={(e) => {
+                                if (e.key === 'Enter') {
+                                    e.preventDefault();
+                                    let target: HTMLInputElement & EventTarget = (e.target as any);
+                                    this.changeKeyframes(selection, prop, keyframe_value, target.value);
+                                }
+                            }} />}
+                        </div>
+                    );
+                }
+                else if (prop_detail.property_type.type === 'String') {
+                    display = (
+                        <div>
+                            <input key={Date.now()} className="bg-gray-600 p-2 w-full outline-none" defaultValue={value || ''} type="text" maxLength={prop_detail.property_type.getStringMaxLength()} onBlur={(e) => selection.changeProperty(prop, e.target.value)} onKeyDown={(e) => {
                                 if (e.key === 'Enter') {
                                     e.preventDefault();
                                     let target: HTMLInputElement & EventTarget = (e.target as any);
@@ -51,6 +93,13 @@ class PropertiesPanel extends React.Component {
                         </div>
                     );
                 }
+                else if (prop_detail.property_type.type === 'Boolean') {
+                    display = (
+                        <div>
+                            <input key={Date.now()} type="checkbox" defaultChecked={Boolean(value)} onChange={(e) => selection.changeProperty(prop, e.target.checked)} />
+                        </div>
+                    );
+                }
                 else {
                     if (typeof value === 'object') {
                         value = JSON.stringify(value);
@@ -60,11 +109,13 @@ class PropertiesPanel extends React.Component {
                     }
                     display = <p>{value}</p>;
                 }
+                let help = registration?.property_help.get(prop);
                 props.push(
                     <div className="border p-3 border-gray-600 mt-2">
-                        <p>{prop_detail.display_name}</p>
+                        <p>{prop_detail.display_name}{help?.units && <span className="text-xs text-gray-400"> ({help.units})</span>}</p>
                         {display}
                         <p className="text-xs">{prop_detail.description}</p>
+                        {help?.help && <p className="text-xs text-gray-400 mt-1">{help.help}</p>}
                     </div>
                 )
             }
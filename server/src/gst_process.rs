@@ -22,6 +22,8 @@ use procspawn::JoinHandle;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::node_analysis::analyse_node;
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum IPCMessage {
     Sender(IpcSender<IPCMessage>),
@@ -32,6 +34,8 @@ pub enum IPCMessage {
     ChunksCompleted(Uuid, u32, u32),
     OperationFinished,
     EndProcess,
+    AnalyseNode(Uuid, String, String, Vec<PipedType>),
+    NodeAnalysed(Uuid, Result<String, String>),
     PipelineData(Store, Cache),
     PipelineResponse(
         Result<
@@ -150,6 +154,12 @@ fn process_main(server_name: String) {
                 IPCMessage::GetLength(clip, output_type) => {
                     execute_pipeline(clip, output_type, None, &parent_send);
                 }
+                IPCMessage::AnalyseNode(node_id, node_type, properties, inputs) => {
+                    let result = analyse_node(node_id, node_type, properties, inputs);
+                    parent_send
+                        .send(IPCMessage::NodeAnalysed(node_id, result))
+                        .unwrap();
+                }
                 IPCMessage::EndProcess => {
                     break;
                 }
@@ -165,6 +175,13 @@ fn process_main(server_name: String) {
     }
 }
 
+/**
+ * Gets the number of preview chunks needed for a composited clip lasting `duration` milliseconds
+ */
+pub fn get_number_of_chunks(duration: u64) -> u32 {
+    f64::ceil((duration as f64) / ((CHUNK_LENGTH as f64) * (1000 as f64)) as f64) as u32
+}
+
 /**
  * Will execute the pipeline for a particular composited clip. This assumes that the relevant GES timeline files have already been generated
  */
@@ -189,8 +206,7 @@ fn execute_pipeline(
     let timeline_asset = ges::UriClipAsset::request_sync(timeline_location.as_str()).unwrap();
 
     let total_duration = timeline_asset.duration().unwrap().mseconds();
-    let number_of_chunks =
-        f64::ceil((total_duration as f64) / ((CHUNK_LENGTH as f64) * (1000 as f64)) as f64) as u32;
+    let number_of_chunks = get_number_of_chunks(total_duration);
 
     parent_send
         .send(IPCMessage::CompositedClipLength(
@@ -206,7 +222,7 @@ fn execute_pipeline(
     let pipeline = gst::Pipeline::new(None);
 
     let out_type = output_type.clone();
-    let timeline = out_type.stream_type.create_timeline();
+    let timeline = out_type.create_timeline();
     let layer = timeline.append_layer();
 
     let inpoint = if start_chunk > 0 {
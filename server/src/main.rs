@@ -5,12 +5,18 @@ use cs310_shared::{
     constants::{
         source_files_location, store_json_location, CHUNK_FILENAME_NUMBER_LENGTH, CHUNK_LENGTH,
     },
+    error::PipelineError,
+    lint::{find_invalid_links, lint_pipeline},
     networking::{self, SERVER_PORT},
     node::{Node, NodeTypeInput, NodeTypeOutput, PipedType},
-    nodes::{get_node_register, NodeRegister},
-    pipeline::Link,
+    node_group::NodeGroup,
+    nodes::{
+        effect_node::EffectDefinition, get_node_register, load_node_definitions, NodeRegister,
+    },
+    pipeline::{get_soloed_store, Link},
     store::Store,
     task::Task,
+    validation::{validate_new_node, validate_node},
 };
 use ges::traits::{GESPipelineExt, LayerExt, TimelineExt};
 use gst::prelude::*;
@@ -37,13 +43,14 @@ use std::{
 use uuid::Uuid;
 
 use crate::{
-    gst_process::{IPCMessage, ProcessPool},
+    gst_process::{get_number_of_chunks, IPCMessage, ProcessPool},
     state::VideoChunkStatus,
 };
 
 use threadpool::ThreadPool;
 
 mod gst_process;
+mod node_analysis;
 mod state;
 fn main() {
     SimpleLogger::new().init().unwrap();
@@ -52,9 +59,21 @@ fn main() {
     let current_dir = current_dir.to_str().unwrap();
     cs310_shared::constants::init(format!("{}/application_data", current_dir), true);
 
-    let store = Store::from_file(store_json_location());
+    // the node types are loaded once, and the definitions are given to the processes which generate the pipeline
+    let (node_definitions, errors) = load_node_definitions();
+    let (node_register, register_errors) = get_node_register(&node_definitions);
+    for error in errors.iter().chain(&register_errors) {
+        log::warn!("{}", error);
+    }
+
+    let store = Store::from_file(store_json_location(), &node_register);
     let store = match store {
-        Ok(store) => store,
+        Ok((store, changes)) => {
+            for change in changes {
+                log::info!("Migrated node {}", change);
+            }
+            store
+        }
         Err(_) => {
             let store = Store::new();
 
@@ -70,6 +89,8 @@ fn main() {
         gstreamer_processes: ProcessPool::new(8),
         video_preview_generation: HashMap::new(),
         cache: Cache::new(),
+        node_register,
+        node_definitions,
     }));
 
     let listener = TcpListener::bind(format!("0.0.0.0:{}", SERVER_PORT)).unwrap();
@@ -249,6 +270,9 @@ fn handle_client(mut stream: TcpStream, state: Arc<Mutex<State>>) -> Result<(),
                     networking::send_data(&mut stream, uuid.as_bytes()).unwrap();
 
                     let mut lock = state.lock().unwrap();
+                    for error in validate_new_node(&mut node, &lock.store, &lock.node_register) {
+                        log::warn!("Replaced invalid property with default: {}", error);
+                    }
                     let store = lock.store.borrow_mut();
                     Task::apply_tasks(store, vec![Task::AddNode(node)]);
                 }
@@ -259,13 +283,27 @@ fn handle_client(mut stream: TcpStream, state: Arc<Mutex<State>>) -> Result<(),
                         log::warn!("Client sent invalid JSON!");
                         return Ok(());
                     }
-                    let node = node.unwrap();
+                    let mut node = node.unwrap();
                     let mut lock = state.lock().unwrap();
+                    let valid = validate_node(&mut node, &lock.store, &lock.node_register);
+                    if let Err(errors) = valid {
+                        for error in &errors {
+                            log::warn!("Client sent invalid node: {}", error);
+                        }
+                        // the client's copy of the node is now out of date, so tell it why the change was rejected
+                        networking::send_message(&mut stream, networking::Message::InvalidNode)?;
+                        networking::send_as_file(
+                            &mut stream,
+                            serde_json::to_string(&errors).unwrap().as_bytes(),
+                        );
+                        return Ok(());
+                    }
                     if lock.store.nodes.contains_key(&node.id) {
-                        lock.cache_node_modified(&node.id);
+                        lock.cache_node_updated(&node);
                         let store = lock.store.borrow_mut();
                         Task::apply_tasks(store, vec![Task::UpdateNode(node.id.clone(), node)]);
                     }
+                    networking::send_message(&mut stream, networking::Message::Response)?;
                 }
                 networking::Message::AddLink => {
                     let bytes = networking::receive_file_as_bytes(&mut stream);
@@ -277,11 +315,27 @@ fn handle_client(mut stream: TcpStream, state: Arc<Mutex<State>>) -> Result<(),
                     let link = link.unwrap();
                     let mut lock = state.lock().unwrap();
 
+                    let valid = lock.store.pipeline.check_link(
+                        &link,
+                        &lock.store,
+                        &lock.node_register,
+                        &lock.cache,
+                    );
+                    if valid.is_err() {
+                        let reason = valid.unwrap_err();
+                        log::warn!("Client sent invalid link: {}", reason);
+                        // the client has already added the link to its copy of the store, so tell it to remove it, and why
+                        networking::send_message(&mut stream, networking::Message::InvalidLink)?;
+                        networking::send_as_file(&mut stream, reason.as_bytes());
+                        return Ok(());
+                    }
+
                     if lock.store.nodes.contains_key(&link.to.node_id) {
                         lock.cache_node_modified(&link.to.node_id);
                         let store = lock.store.borrow_mut();
                         Task::apply_tasks(store, vec![Task::AddLink(link)]);
                     }
+                    networking::send_message(&mut stream, networking::Message::Response)?;
                 }
                 networking::Message::DeleteLinks => {
                     let uuid = networking::receive_uuid(&mut stream)?;
@@ -359,6 +413,60 @@ fn handle_client(mut stream: TcpStream, state: Arc<Mutex<State>>) -> Result<(),
                         Task::apply_tasks(store, vec![Task::DeleteNode(uuid)]);
                     }
                 }
+                networking::Message::CreateNodeGroup => {
+                    let bytes = networking::receive_file_as_bytes(&mut stream);
+                    let group = serde_json::from_slice::<NodeGroup>(&bytes);
+                    if group.is_err() {
+                        log::warn!("Client sent invalid JSON!");
+                        return Ok(());
+                    }
+                    let group = group.unwrap();
+                    let mut lock = state.lock().unwrap();
+                    if let Err(error) = group.validate(&lock.node_register) {
+                        log::warn!("Client sent invalid node group: {}", error);
+                        return Ok(());
+                    }
+                    if !lock.store.node_groups.contains_key(&group.id) {
+                        let store = lock.store.borrow_mut();
+                        Task::apply_tasks(store, vec![Task::CreateNodeGroup(group)]);
+                    }
+                }
+                networking::Message::SoloNode => {
+                    let bytes = networking::receive_file_as_bytes(&mut stream);
+                    let solo_node = serde_json::from_slice::<Option<Uuid>>(&bytes);
+                    if solo_node.is_err() {
+                        log::warn!("Client sent invalid JSON!");
+                        return Ok(());
+                    }
+                    let solo_node = solo_node.unwrap();
+                    let mut lock = state.lock().unwrap();
+                    if let Some(id) = solo_node {
+                        if !lock.store.nodes.contains_key(&id) {
+                            log::warn!("Client soloed a node which does not exist");
+                            return Ok(());
+                        }
+                    }
+                    // every composited clip shows the solo node instead, so the previews are out of date
+                    lock.video_preview_generation.clear();
+                    let store = lock.store.borrow_mut();
+                    Task::apply_tasks(store, vec![Task::SoloNode(solo_node)]);
+                }
+                networking::Message::LintPipeline => {
+                    log::info!("[{}] Linting pipeline", operation_id);
+                    let issues = {
+                        let lock = state.lock().unwrap();
+                        let mut issues = lint_pipeline(&lock.store, &lock.node_register);
+                        issues.extend(find_invalid_links(
+                            &lock.store,
+                            &lock.node_register,
+                            &lock.cache,
+                        ));
+                        issues
+                    };
+                    let data = serde_json::to_vec(&issues).unwrap();
+                    networking::send_as_file(&mut stream, &data);
+                    log::info!("[{}] Found {} issues", operation_id, issues.len());
+                }
                 networking::Message::CompositedClipLength => {
                     client_message_composited_clip_length(
                         &mut stream,
@@ -507,19 +615,50 @@ fn client_message_composited_clip_length(
     let composited_clip_id = networking::receive_uuid(stream)?;
 
     let mut lock = state.lock().unwrap();
-    let result = generate_pipeline_in_process(lock.store.clone(), lock.cache.clone());
+
+    // The length can usually be worked out from the nodes' formats, without rendering anything
+    if let Some(duration) = get_static_clip_length(&lock, &composited_clip_id) {
+        let num_chunks = match lock.video_preview_generation.get(&composited_clip_id) {
+            Some((Some(existing), _, chunks)) if *existing == duration => chunks.len() as u32,
+            _ => {
+                let num_chunks = get_number_of_chunks(duration);
+                let statuses = vec![VideoChunkStatus::NotGenerated; num_chunks as usize];
+                lock.video_preview_generation
+                    .insert(composited_clip_id.clone(), (Some(duration), None, statuses));
+                num_chunks
+            }
+        };
+        drop(lock);
+        log::info!(
+            "[{}] Inferred duration: {}; chunks: {}",
+            operation_id,
+            duration,
+            num_chunks
+        );
+        networking::send_message(stream, networking::Message::CompositedClipLength).unwrap();
+        networking::send_data(stream, composited_clip_id.as_bytes()).unwrap();
+        networking::send_data(stream, &duration.to_ne_bytes()).unwrap();
+        networking::send_data(stream, &num_chunks.to_ne_bytes()).unwrap();
+        return Ok(());
+    }
+
+    let result = generate_pipeline_with_analysis(&mut lock, &composited_clip_id);
 
     if result.is_err() {
         drop(lock);
-        networking::send_message(stream, networking::Message::CouldNotGetLength).unwrap();
+        send_pipeline_error(
+            stream,
+            networking::Message::CouldNotGetLength,
+            result.unwrap_err(),
+        );
         return Ok(());
     }
 
-    let (node_type_data, composited_clip_data, output) = result.unwrap();
+    let (node_type_data, composited_clip_data, output_error) = result.unwrap();
 
-    if !output {
+    if let Some(error) = output_error {
         drop(lock);
-        networking::send_message(stream, networking::Message::CouldNotGetLength).unwrap();
+        send_pipeline_error(stream, networking::Message::CouldNotGetLength, error);
         return Ok(());
     }
 
@@ -527,7 +666,13 @@ fn client_message_composited_clip_length(
     let output_type = composited_clip_data.get(&composited_clip_id);
     if clip.is_none() || output_type.is_none() {
         drop(lock);
-        networking::send_message(stream, networking::Message::CouldNotGetLength).unwrap();
+        send_pipeline_error(
+            stream,
+            networking::Message::CouldNotGetLength,
+            PipelineError::from(String::from(
+                "The composited clip does not exist, or has no output node",
+            )),
+        );
         return Ok(());
     }
     let clip = clip.unwrap().clone();
@@ -556,7 +701,11 @@ fn client_message_composited_clip_length(
 
     if process.is_none() {
         drop(lock);
-        networking::send_message(stream, networking::Message::CouldNotGetLength).unwrap();
+        send_pipeline_error(
+            stream,
+            networking::Message::CouldNotGetLength,
+            PipelineError::from(String::from("No GStreamer process is available")),
+        );
         return Ok(());
     }
     let (process, sender, recv) = process.unwrap();
@@ -606,25 +755,35 @@ fn client_message_get_video_preview(
     );
 
     let mut lock = state.lock().unwrap();
-    let result = generate_pipeline_in_process(lock.store.clone(), lock.cache.clone());
+    let result = generate_pipeline_with_analysis(&mut lock, &composited_clip_id);
     if result.is_err() {
         drop(lock);
-        networking::send_message(stream, networking::Message::CouldNotGeneratePreview).unwrap();
+        send_pipeline_error(
+            stream,
+            networking::Message::CouldNotGeneratePreview,
+            result.unwrap_err(),
+        );
         return Ok(());
     }
 
-    let (node_type_data, composited_clip_data, output) = result.unwrap();
+    let (node_type_data, composited_clip_data, output_error) = result.unwrap();
 
-    if !output {
+    if let Some(error) = output_error {
         drop(lock);
-        networking::send_message(stream, networking::Message::CouldNotGeneratePreview).unwrap();
+        send_pipeline_error(stream, networking::Message::CouldNotGeneratePreview, error);
         return Ok(());
     }
     let clip = lock.store.clips.composited.get(&composited_clip_id);
     let output_type = composited_clip_data.get(&composited_clip_id);
     if clip.is_none() || output_type.is_none() {
         drop(lock);
-        networking::send_message(stream, networking::Message::CouldNotGeneratePreview).unwrap();
+        send_pipeline_error(
+            stream,
+            networking::Message::CouldNotGeneratePreview,
+            PipelineError::from(String::from(
+                "The composited clip does not exist, or has no output node",
+            )),
+        );
         return Ok(());
     }
     let clip = clip.unwrap().clone();
@@ -673,7 +832,11 @@ fn client_message_get_video_preview(
     let process = lock.gstreamer_processes.acquire_process();
     if process.is_none() {
         drop(lock);
-        networking::send_message(stream, networking::Message::CouldNotGeneratePreview).unwrap();
+        send_pipeline_error(
+            stream,
+            networking::Message::CouldNotGeneratePreview,
+            PipelineError::from(String::from("No GStreamer process is available")),
+        );
         return Ok(());
     }
     let (process, sender, recv) = process.unwrap();
@@ -750,9 +913,85 @@ fn client_message_get_video_preview(
     Ok(())
 }
 
+/**
+ * Tells the client that the pipeline could not be generated, followed by why as a JSON file, so the client can show the error on the node it was found in
+ */
+fn send_pipeline_error(stream: &mut TcpStream, message: networking::Message, error: PipelineError) {
+    networking::send_message(stream, message).unwrap();
+    networking::send_as_file(stream, serde_json::to_string(&error).unwrap().as_bytes());
+}
+
+/**
+ * Gets the length of a composited clip (in milliseconds) from the formats of the nodes, without generating any timelines
+ * Returns `None` if the length cannot be known this way, e.g. if any nodes need analysing first, in which case the pipeline should be rendered instead
+ */
+fn get_static_clip_length(state: &State, composited_clip_id: &Uuid) -> Option<u64> {
+    if !state
+        .store
+        .clips
+        .composited
+        .contains_key(composited_clip_id)
+    {
+        return None;
+    }
+    let store = get_soloed_store(&state.store, &state.node_register, composited_clip_id).ok()?;
+    let (node_type_data, composited_clip_data, _) = store
+        .pipeline
+        .generate_pipeline(&store, &state.node_register, false, &state.cache)
+        .ok()?;
+    if node_analysis::needs_analysis(&state.store, &node_type_data) {
+        return None;
+    }
+    composited_clip_data
+        .get(composited_clip_id)
+        .and_then(|output_type| output_type.format.duration)
+}
+
+/**
+ * Generates the pipeline for previewing a composited clip, running any node analyses (e.g. audio sync) which are out of date. If any nodes are updated by the analysis, the pipeline is generated again with the new properties
+ */
+fn generate_pipeline_with_analysis(
+    state: &mut State,
+    composited_clip_id: &Uuid,
+) -> Result<
+    (
+        HashMap<
+            Uuid,
+            (
+                HashMap<String, PipedType>,
+                HashMap<String, NodeTypeInput>,
+                HashMap<String, NodeTypeOutput>,
+            ),
+        >,
+        HashMap<Uuid, PipedType>,
+        Option<PipelineError>,
+    ),
+    PipelineError,
+> {
+    let result = generate_pipeline_in_process(
+        state.store.clone(),
+        state.cache.clone(),
+        &state.node_definitions,
+        composited_clip_id,
+    );
+    if let Ok((node_type_data, _, _)) = &result {
+        if node_analysis::run_node_analyses(state, node_type_data) {
+            return generate_pipeline_in_process(
+                state.store.clone(),
+                state.cache.clone(),
+                &state.node_definitions,
+                composited_clip_id,
+            );
+        }
+    }
+    result
+}
+
 fn generate_pipeline_in_process(
     store: Store,
     cache: Cache,
+    node_definitions: &[EffectDefinition],
+    composited_clip_id: &Uuid,
 ) -> Result<
     (
         HashMap<
@@ -764,9 +1003,9 @@ fn generate_pipeline_in_process(
             ),
         >,
         HashMap<Uuid, PipedType>,
-        bool,
+        Option<PipelineError>,
     ),
-    String,
+    PipelineError,
 > {
     // This is quite an unsightly way to do it, however we are limited in options since segmentation faults need to be dealt with.
     let mut i = 0;
@@ -774,25 +1013,42 @@ fn generate_pipeline_in_process(
         i += 1;
         let serialised_store = serde_json::to_string(&store).unwrap();
         let serialised_cache = serde_json::to_string(&cache).unwrap();
+        let serialised_definitions = serde_json::to_string(node_definitions).unwrap();
 
-        let handle = procspawn::spawn((serialised_store, serialised_cache), |(store, cache)| {
-            gst::init().unwrap();
-            ges::init().unwrap();
-
-            let current_dir = std::env::current_dir().unwrap();
-            let current_dir = current_dir.to_str().unwrap();
-            cs310_shared::constants::init(format!("{}/application_data", current_dir), true);
-
-            let store = serde_json::from_str::<Store>(&store).unwrap();
-            let cache = serde_json::from_str::<Cache>(&cache).unwrap();
-
-            let node_register = get_node_register();
-            let res = store
-                .pipeline
-                .generate_pipeline(&store, &node_register, true, &cache);
+        let handle = procspawn::spawn(
+            (
+                serialised_store,
+                serialised_cache,
+                serialised_definitions,
+                *composited_clip_id,
+            ),
+            |(store, cache, node_definitions, composited_clip_id)| {
+                gst::init().unwrap();
+                ges::init().unwrap();
+
+                let current_dir = std::env::current_dir().unwrap();
+                let current_dir = current_dir.to_str().unwrap();
+                cs310_shared::constants::init(format!("{}/application_data", current_dir), true);
+
+                let store = serde_json::from_str::<Store>(&store).unwrap();
+                let cache = serde_json::from_str::<Cache>(&cache).unwrap();
+                let node_definitions =
+                    serde_json::from_str::<Vec<EffectDefinition>>(&node_definitions).unwrap();
+
+                // any errors were already reported when the server loaded the definitions
+                let (node_register, _) = get_node_register(&node_definitions);
+                let store = get_soloed_store(&store, &node_register, &composited_clip_id);
+                if store.is_err() {
+                    return Err(store.unwrap_err());
+                }
+                let store = store.unwrap();
+                let res = store
+                    .pipeline
+                    .generate_pipeline(&store, &node_register, true, &cache);
 
-            res
-        });
+                res
+            },
+        );
 
         let res = handle.join();
 
@@ -801,7 +1057,7 @@ fn generate_pipeline_in_process(
         }
 
         if i > 9 {
-            return Err(String::from("Limited reached"));
+            return Err(PipelineError::from(String::from("Limited reached")));
         }
     }
 }
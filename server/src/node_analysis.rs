@@ -0,0 +1,415 @@
+use std::collections::HashMap;
+
+use cs310_shared::{
+    constants::{intermediate_files_location, AUDIO_ANALYSIS_WINDOW},
+    node::{NodeTypeInput, NodeTypeOutput, PipeableType, PipedType},
+    nodes::{
+        audio_sync_node, ducking_node, get_number_property, silence_removal_node, visualiser_node,
+        ANALYSIS_KEY,
+    },
+    store::Store,
+};
+use ges::traits::{LayerExt, TimelineExt};
+use glib::{ObjectExt, StaticType};
+use gst::prelude::{ElementExt, GstBinExt, GstObjectExt, PadExtManual};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{gst_process::IPCMessage, state::State};
+
+type NodeTypeData = HashMap<
+    Uuid,
+    (
+        HashMap<String, PipedType>,
+        HashMap<String, NodeTypeInput>,
+        HashMap<String, NodeTypeOutput>,
+    ),
+>;
+
+/**
+ * Gets the nodes whose analysis results are out of date, along with the key to store alongside the results and the media to analyse
+ */
+fn get_analysis_requests(
+    store: &Store,
+    node_type_data: &NodeTypeData,
+) -> Vec<(Uuid, String, String, Vec<PipedType>)> {
+    let mut requests = Vec::new();
+    for (id, node) in &store.nodes {
+        let data = node_type_data.get(id);
+        if data.is_none() {
+            continue;
+        }
+        let (piped_inputs, _, _) = data.unwrap();
+
+        let request = match node.node_type.as_str() {
+            audio_sync_node::IDENTIFIER => {
+                audio_sync_node::get_analysis_request(node, piped_inputs, store)
+            }
+            silence_removal_node::IDENTIFIER => {
+                silence_removal_node::get_analysis_request(node, piped_inputs, store)
+            }
+            ducking_node::IDENTIFIER => {
+                ducking_node::get_analysis_request(node, piped_inputs, store)
+            }
+            visualiser_node::IDENTIFIER => {
+                visualiser_node::get_analysis_request(node, piped_inputs, store)
+            }
+            _ => None,
+        };
+        if let Some((key, media)) = request {
+            requests.push((id.clone(), node.node_type.clone(), key, media));
+        }
+    }
+    requests
+}
+
+/**
+ * Whether any nodes in the pipeline need analysing before their outputs (and so their lengths) are known
+ */
+pub fn needs_analysis(store: &Store, node_type_data: &NodeTypeData) -> bool {
+    !get_analysis_requests(store, node_type_data).is_empty()
+}
+
+/**
+ * Finds any nodes in the pipeline whose analysis results (e.g. audio sync offsets, detected silences, or rendered visualisations) are out of date, and runs the analysis for them on a process from the pool.
+ * The results are stored as properties on the relevant nodes, so they are not recomputed on every preview.
+ *
+ * Returns whether any nodes were modified, in which case the pipeline needs to be generated again
+ */
+pub fn run_node_analyses(state: &mut State, node_type_data: &NodeTypeData) -> bool {
+    let requests = get_analysis_requests(&state.store, node_type_data);
+
+    if requests.is_empty() {
+        return false;
+    }
+
+    let process = state.gstreamer_processes.acquire_process();
+    if process.is_none() {
+        log::warn!("No process available to analyse nodes");
+        return false;
+    }
+    let (handle, sender, recv) = process.unwrap();
+
+    let mut modified = false;
+    for (id, node_type, key, media) in requests {
+        log::info!("Analysing node {} ({})", id, node_type);
+        let properties =
+            serde_json::to_string(&state.store.nodes.get(&id).unwrap().properties).unwrap();
+        sender
+            .send(IPCMessage::AnalyseNode(
+                id.clone(),
+                node_type,
+                properties,
+                media,
+            ))
+            .unwrap();
+
+        match recv.recv() {
+            Ok(IPCMessage::NodeAnalysed(_, Ok(updates))) => {
+                let updates = serde_json::from_str::<HashMap<String, Value>>(&updates).unwrap();
+                let node = state.store.nodes.get_mut(&id).unwrap();
+                for (property, value) in updates {
+                    node.properties.insert(property, value);
+                }
+                node.properties
+                    .insert(String::from(ANALYSIS_KEY), Value::String(key));
+
+                state.cache_node_modified(&id);
+                modified = true;
+            }
+            Ok(IPCMessage::NodeAnalysed(_, Err(error))) => {
+                log::warn!("Could not analyse node {}: {}", id, error);
+            }
+            message => {
+                log::error!("Invalid response to node analysis: {:?}", message);
+            }
+        }
+    }
+
+    state
+        .gstreamer_processes
+        .add_process_to_pool((handle, sender, recv));
+
+    modified
+}
+
+/**
+ * Executed on a pool process; gets the audio levels of each of the inputs, and passes them to the relevant node type to compute the updated properties.
+ * Visualiser nodes instead have their visualisation rendered to a file.
+ * The properties are passed to and from the process as JSON, since they cannot be sent directly
+ */
+pub fn analyse_node(
+    node_id: Uuid,
+    node_type: String,
+    properties: String,
+    inputs: Vec<PipedType>,
+) -> Result<String, String> {
+    let properties = serde_json::from_str::<HashMap<String, Value>>(&properties);
+    if properties.is_err() {
+        return Err(String::from("Node properties are malformed"));
+    }
+    let properties = properties.unwrap();
+
+    if node_type == visualiser_node::IDENTIFIER {
+        let updates = render_visualisation(&node_id, &properties, &inputs)?;
+        return Ok(serde_json::to_string(&updates).unwrap());
+    }
+
+    let mut levels = Vec::new();
+    for input in &inputs {
+        levels.push(get_audio_levels(input)?);
+    }
+
+    let updates = match node_type.as_str() {
+        audio_sync_node::IDENTIFIER => audio_sync_node::analyse(&properties, &levels)?,
+        silence_removal_node::IDENTIFIER => silence_removal_node::analyse(&properties, &levels)?,
+        ducking_node::IDENTIFIER => ducking_node::analyse(&properties, &levels)?,
+        _ => {
+            return Err(format!("Node type {} cannot be analysed", node_type));
+        }
+    };
+
+    Ok(serde_json::to_string(&updates).unwrap())
+}
+
+/**
+ * Loads the audio of some media into a timeline, and adds it to a pipeline, so it can be played through for analysis
+ */
+fn create_audio_pipeline(
+    input: &PipedType,
+) -> Result<(gst::Pipeline, ges::Timeline, ges::UriClipAsset), String> {
+    if input.stream_type.audio < 1 {
+        return Err(String::from("Media has no audio to analyse"));
+    }
+
+    let location = input.get_gst_save_location_with_cache();
+    ges::Asset::needs_reload(ges::UriClip::static_type(), Some(location.as_str()));
+    let asset = ges::UriClipAsset::request_sync(location.as_str());
+    if asset.is_err() {
+        return Err(format!(
+            "Could not load media for analysis: {}",
+            asset.unwrap_err()
+        ));
+    }
+    let asset = asset.unwrap();
+
+    let timeline = PipeableType {
+        video: 0,
+        audio: 1,
+        subtitles: 0,
+    }
+    .create_timeline();
+    let layer = timeline.append_layer();
+    layer
+        .add_asset(&asset, None, None, None, ges::TrackType::AUDIO)
+        .unwrap();
+
+    let pipeline = gst::Pipeline::new(None);
+    pipeline.add(&timeline).unwrap();
+
+    Ok((pipeline, timeline, asset))
+}
+
+/**
+ * Plays through the audio of some media, and gets its level (in dB) for every `AUDIO_ANALYSIS_WINDOW` milliseconds.
+ * All the audio tracks and channels are averaged together
+ */
+pub fn get_audio_levels(input: &PipedType) -> Result<Vec<f64>, String> {
+    let (pipeline, timeline, asset) = create_audio_pipeline(input)?;
+
+    let audioconvert = gst::ElementFactory::make("audioconvert", None).unwrap();
+    let level = gst::ElementFactory::make("level", None).unwrap();
+    let sink = gst::ElementFactory::make("fakesink", None).unwrap();
+
+    level.set_property("interval", AUDIO_ANALYSIS_WINDOW * 1000000);
+    level.set_property("post-messages", true);
+    sink.set_property("sync", false); // analyse as fast as possible, rather than in real time
+
+    pipeline.add(&audioconvert).unwrap();
+    pipeline.add(&level).unwrap();
+    pipeline.add(&sink).unwrap();
+    for pad in timeline.pads() {
+        timeline
+            .link_pads(Some(pad.name().as_str()), &audioconvert, None)
+            .unwrap();
+    }
+    audioconvert.link(&level).unwrap();
+    level.link(&sink).unwrap();
+
+    if pipeline.set_state(gst::State::Playing).is_err() {
+        return Err(String::from("Could not start the analysis pipeline"));
+    }
+
+    let bus = pipeline.bus().unwrap();
+    let mut levels = Vec::new();
+    let mut error = None;
+    for msg in bus.iter_timed(gst::ClockTime::NONE) {
+        use gst::MessageView;
+
+        match msg.view() {
+            MessageView::Eos(..) => break,
+            MessageView::Error(err) => {
+                error = Some(format!("Error during analysis: {}", err.error()));
+                break;
+            }
+            MessageView::Element(_) => {
+                if let Some(structure) = msg.structure() {
+                    if structure.name() == "level" {
+                        if let Ok(rms) = structure.get::<glib::ValueArray>("rms") {
+                            let amplitudes: Vec<f64> = rms
+                                .iter()
+                                .filter_map(|value| value.get::<f64>().ok())
+                                .map(|db| f64::powf(10.0, db / 20.0))
+                                .collect();
+                            if !amplitudes.is_empty() {
+                                let amplitude =
+                                    amplitudes.iter().sum::<f64>() / amplitudes.len() as f64;
+                                levels.push(f64::max(20.0 * f64::log10(amplitude), -200.0));
+                            }
+                        }
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pipeline
+        .set_state(gst::State::Null)
+        .expect("Unable to set the pipeline to the `Null` state");
+    drop(asset);
+
+    if let Some(error) = error {
+        return Err(error);
+    }
+    Ok(levels)
+}
+
+/**
+ * Plays through the audio of some media, drawing it with the visualiser node's chosen scope and encoding the video to a file.
+ * Returns the properties to update on the node
+ */
+fn render_visualisation(
+    node_id: &Uuid,
+    properties: &HashMap<String, Value>,
+    inputs: &Vec<PipedType>,
+) -> Result<HashMap<String, Value>, String> {
+    if inputs.is_empty() {
+        return Err(String::from("No audio to visualise"));
+    }
+    let scope = visualiser_node::get_scope_element(get_number_property(
+        properties,
+        visualiser_node::inputs::STYLE,
+        0.0,
+    ) as i64)?;
+    let (width, height) = visualiser_node::get_resolution(properties);
+    let colour = visualiser_node::get_colour(properties)?;
+
+    let (pipeline, timeline, asset) = create_audio_pipeline(&inputs[0])?;
+
+    let audioconvert = gst::ElementFactory::make("audioconvert", None).unwrap();
+    let scope = gst::ElementFactory::make(scope, None);
+    if scope.is_err() {
+        return Err(String::from(
+            "The visualiser is not installed on the server",
+        ));
+    }
+    let scope = scope.unwrap();
+    let size_filter = gst::ElementFactory::make("capsfilter", None).unwrap();
+    let videoconvert1 = gst::ElementFactory::make("videoconvert", None).unwrap();
+    let format_filter = gst::ElementFactory::make("capsfilter", None).unwrap();
+    let videoconvert2 = gst::ElementFactory::make("videoconvert", None).unwrap();
+    let encoder = gst::ElementFactory::make("x264enc", None).unwrap();
+    let muxer = gst::ElementFactory::make("mp4mux", None).unwrap();
+    let sink = gst::ElementFactory::make("filesink", None).unwrap();
+
+    size_filter.set_property(
+        "caps",
+        gst::Caps::builder("video/x-raw")
+            .field("width", width as i32)
+            .field("height", height as i32)
+            .field(
+                "framerate",
+                gst::Fraction::new(visualiser_node::FRAMERATE as i32, 1),
+            )
+            .build(),
+    );
+    // the frames are tinted in RGBx, where each pixel is 4 bytes
+    format_filter.set_property(
+        "caps",
+        gst::Caps::builder("video/x-raw")
+            .field("format", "RGBx")
+            .build(),
+    );
+    let location = visualiser_node::get_visualisation_location(node_id);
+    std::fs::create_dir_all(intermediate_files_location()).unwrap();
+    sink.set_property("location", location.as_str());
+
+    if let Some(colour) = colour {
+        format_filter.static_pad("src").unwrap().add_probe(
+            gst::PadProbeType::BUFFER,
+            move |_, info| {
+                if let Some(gst::PadProbeData::Buffer(ref mut buffer)) = info.data {
+                    let buffer = buffer.make_mut();
+                    if let Ok(mut map) = buffer.map_writable() {
+                        visualiser_node::tint_frame(map.as_mut_slice(), colour);
+                    }
+                }
+                gst::PadProbeReturn::Ok
+            },
+        );
+    }
+
+    let elements = [
+        &audioconvert,
+        &scope,
+        &size_filter,
+        &videoconvert1,
+        &format_filter,
+        &videoconvert2,
+        &encoder,
+        &muxer,
+        &sink,
+    ];
+    pipeline.add_many(&elements).unwrap();
+    for pad in timeline.pads() {
+        timeline
+            .link_pads(Some(pad.name().as_str()), &audioconvert, None)
+            .unwrap();
+    }
+    gst::Element::link_many(&elements).unwrap();
+
+    if pipeline.set_state(gst::State::Playing).is_err() {
+        return Err(String::from("Could not start the visualisation pipeline"));
+    }
+
+    let bus = pipeline.bus().unwrap();
+    let mut error = None;
+    for msg in bus.iter_timed(gst::ClockTime::NONE) {
+        use gst::MessageView;
+
+        match msg.view() {
+            MessageView::Eos(..) => break,
+            MessageView::Error(err) => {
+                error = Some(format!("Error rendering visualisation: {}", err.error()));
+                break;
+            }
+            _ => (),
+        }
+    }
+
+    pipeline
+        .set_state(gst::State::Null)
+        .expect("Unable to set the pipeline to the `Null` state");
+    drop(asset);
+
+    if let Some(error) = error {
+        return Err(error);
+    }
+    let mut updates = HashMap::new();
+    updates.insert(
+        String::from(visualiser_node::VISUALISATION),
+        Value::String(location),
+    );
+    Ok(updates)
+}
@@ -1,6 +1,13 @@
 use std::collections::HashMap;
 
-use cs310_shared::{cache::Cache, clip::ClipType, store::Store, ID};
+use cs310_shared::{
+    cache::Cache,
+    clip::ClipType,
+    node::Node,
+    nodes::{effect_node::EffectDefinition, NodeRegister},
+    store::Store,
+    ID,
+};
 use uuid::Uuid;
 
 use crate::gst_process::ProcessPool;
@@ -18,6 +25,8 @@ pub struct State {
         HashMap<Uuid, (Option<u64>, Option<String>, Vec<VideoChunkStatus>)>,
     pub gstreamer_processes: ProcessPool,
     pub cache: Cache,
+    pub node_register: NodeRegister,
+    pub node_definitions: Vec<EffectDefinition>,
 }
 
 impl State {
@@ -25,6 +34,10 @@ impl State {
         self.cache.node_modified(&id, &self.store);
     }
 
+    pub fn cache_node_updated(&mut self, node: &Node) {
+        self.cache.node_updated(node, &self.store);
+    }
+
     pub fn cache_clip_modified(&mut self, id: &ID, clip_type: ClipType) {
         self.cache.clip_modified(&id, clip_type, &self.store);
     }
@@ -5,7 +5,9 @@ use serde::Deserialize;
 
 use crate::{
     clip::{ClipIdentifier, ClipType},
-    nodes::media_import_node,
+    node::Node,
+    node_group::expanded_node_id,
+    nodes::{media_import_node, node_group_node, switch_node},
     store::Store,
     ID,
 };
@@ -35,6 +37,8 @@ impl Cache {
 
     /**
      * Updates the cache to purge any affected nodes' cache as a result of a node modification
+     * Changes which only reach an inactive input of a switch node do not affect anything past the switch, unless it is bypassed, since it then passes through an input regardless of which is active
+     * Changes which reach a group node affect all the copies of the nodes in its group
      */
     pub fn node_modified(&mut self, id: &ID, store: &Store) {
         let graph = store.pipeline.get_graph(store);
@@ -50,6 +54,17 @@ impl Cache {
                 }
 
                 self.clear(&node);
+                if let Some(group_node) = store.nodes.get(&node) {
+                    if group_node.node_type == node_group_node::IDENTIFIER {
+                        if let Ok(group) =
+                            node_group_node::get_node_group(&group_node.properties, store)
+                        {
+                            for inner_id in group.nodes.keys() {
+                                self.clear(&expanded_node_id(&node, inner_id));
+                            }
+                        }
+                    }
+                }
 
                 let edges_out = graph.edges_directed(
                     *node_id_to_index.get_by_left(&node).unwrap(),
@@ -61,6 +76,16 @@ impl Cache {
                         .get_by_right(&edge.target())
                         .unwrap()
                         .clone();
+                    if let (Some(to), Some((_, to_property))) =
+                        (store.nodes.get(&to_node), edge.weight())
+                    {
+                        if to.node_type == switch_node::IDENTIFIER
+                            && !to.bypassed
+                            && *to_property != switch_node::get_active_input(&to.properties)
+                        {
+                            continue;
+                        }
+                    }
                     nodes_to_clear.push(to_node);
                 }
 
@@ -68,6 +93,24 @@ impl Cache {
             }
         }
     }
+    /**
+     * Updates the cache before a node is replaced with its updated version, `node`
+     * Switch nodes only affect the nodes after them when the active input changes, though their own cache is always cleared
+     */
+    pub fn node_updated(&mut self, node: &Node, store: &Store) {
+        if let Some(old_node) = store.nodes.get(&node.id) {
+            if node.node_type == switch_node::IDENTIFIER
+                && old_node.node_type == node.node_type
+                && old_node.bypassed == node.bypassed
+                && switch_node::get_active_input(&old_node.properties)
+                    == switch_node::get_active_input(&node.properties)
+            {
+                self.clear(&node.id);
+                return;
+            }
+        }
+        self.node_modified(&node.id, store);
+    }
     /**
      * Updates the cache to purge any affected nodes' cache as a result of a clip modification
      */
@@ -99,10 +142,12 @@ impl Cache {
 mod tests {
     use std::collections::HashMap;
 
+    use serde_json::Value;
+
     use crate::{
         global::uniq_id,
-        node::{Node, Position},
-        nodes::blur_node,
+        node::{Node, Position, INITIAL_TYPE_VERSION},
+        nodes::{blur_node, switch_node},
         pipeline::{Link, LinkEndpoint, Pipeline},
         store::{ClipStore, Store},
     };
@@ -117,6 +162,8 @@ mod tests {
             node_type: blur_node::IDENTIFIER.to_owned(),
             position: Position::new(),
             properties: HashMap::new(),
+            bypassed: false,
+            type_version: String::from(INITIAL_TYPE_VERSION),
         };
 
         let node2 = Node {
@@ -125,6 +172,8 @@ mod tests {
             node_type: blur_node::IDENTIFIER.to_owned(),
             position: Position::new(),
             properties: HashMap::new(),
+            bypassed: false,
+            type_version: String::from(INITIAL_TYPE_VERSION),
         };
 
         let edge = Link {
@@ -149,6 +198,8 @@ mod tests {
             nodes: hm,
             clips: ClipStore::new(),
             pipeline,
+            node_groups: HashMap::new(),
+            solo_node: None,
         };
 
         let mut cache = Cache::new();
@@ -167,6 +218,8 @@ mod tests {
             node_type: blur_node::IDENTIFIER.to_owned(),
             position: Position::new(),
             properties: HashMap::new(),
+            bypassed: false,
+            type_version: String::from(INITIAL_TYPE_VERSION),
         };
 
         let node2 = Node {
@@ -175,6 +228,8 @@ mod tests {
             node_type: blur_node::IDENTIFIER.to_owned(),
             position: Position::new(),
             properties: HashMap::new(),
+            bypassed: false,
+            type_version: String::from(INITIAL_TYPE_VERSION),
         };
 
         let edge = Link {
@@ -199,6 +254,8 @@ mod tests {
             nodes: hm,
             clips: ClipStore::new(),
             pipeline,
+            node_groups: HashMap::new(),
+            solo_node: None,
         };
 
         let mut cache = Cache::new();
@@ -208,4 +265,147 @@ mod tests {
         cache.node_modified(&node2.id, &store);
         assert!(cache.cache_data.get(&node1.id).is_some());
     }
+
+    #[test]
+    fn test_cache_switch() {
+        let mut nodes = Vec::new();
+        for node_type in [
+            blur_node::IDENTIFIER,
+            switch_node::IDENTIFIER,
+            blur_node::IDENTIFIER,
+        ] {
+            nodes.push(Node {
+                id: uniq_id(),
+                group: uniq_id(),
+                node_type: node_type.to_owned(),
+                position: Position::new(),
+                properties: HashMap::new(),
+                bypassed: false,
+                type_version: String::from(INITIAL_TYPE_VERSION),
+            });
+        }
+
+        // the blur node is linked to the second input of the switch, which is inactive by default
+        let mut pipeline = Pipeline::new();
+        pipeline.links.push(Link {
+            from: LinkEndpoint {
+                node_id: nodes[0].id.clone(),
+                property: blur_node::outputs::OUTPUT.to_owned(),
+            },
+            to: LinkEndpoint {
+                node_id: nodes[1].id.clone(),
+                property: switch_node::inputs::media(2),
+            },
+        });
+        pipeline.links.push(Link {
+            from: LinkEndpoint {
+                node_id: nodes[1].id.clone(),
+                property: switch_node::outputs::OUTPUT.to_owned(),
+            },
+            to: LinkEndpoint {
+                node_id: nodes[2].id.clone(),
+                property: blur_node::inputs::MEDIA.to_owned(),
+            },
+        });
+
+        let mut hm = HashMap::new();
+        for node in &nodes {
+            hm.insert(node.id.clone(), node.clone());
+        }
+        let store = Store {
+            nodes: hm,
+            clips: ClipStore::new(),
+            pipeline,
+            node_groups: HashMap::new(),
+            solo_node: None,
+        };
+
+        let mut cache = Cache::new();
+        for node in &nodes {
+            cache.add_to_cache(node.id.clone(), HashMap::new());
+        }
+
+        cache.node_modified(&nodes[0].id, &store);
+        assert!(cache.cache_data.get(&nodes[0].id).is_none());
+        assert!(cache.cache_data.get(&nodes[2].id).is_some());
+
+        let mut switch = nodes[1].clone();
+        switch.properties.insert(
+            switch_node::inputs::NUMBER_OF_INPUTS.to_owned(),
+            Value::from(3),
+        );
+        cache.node_updated(&switch, &store);
+        assert!(cache.cache_data.get(&nodes[1].id).is_none());
+        assert!(cache.cache_data.get(&nodes[2].id).is_some());
+
+        switch
+            .properties
+            .insert(switch_node::inputs::ACTIVE_INPUT.to_owned(), Value::from(2));
+        cache.node_updated(&switch, &store);
+        assert!(cache.cache_data.get(&nodes[2].id).is_none());
+    }
+
+    #[test]
+    fn test_cache_switch_bypassed() {
+        let mut nodes = Vec::new();
+        for node_type in [
+            blur_node::IDENTIFIER,
+            switch_node::IDENTIFIER,
+            blur_node::IDENTIFIER,
+        ] {
+            nodes.push(Node {
+                id: uniq_id(),
+                group: uniq_id(),
+                node_type: node_type.to_owned(),
+                position: Position::new(),
+                properties: HashMap::new(),
+                bypassed: node_type == switch_node::IDENTIFIER,
+                type_version: String::from(INITIAL_TYPE_VERSION),
+            });
+        }
+
+        // the blur node is linked to an inactive input, but the bypassed switch passes it through anyway
+        let mut pipeline = Pipeline::new();
+        pipeline.links.push(Link {
+            from: LinkEndpoint {
+                node_id: nodes[0].id.clone(),
+                property: blur_node::outputs::OUTPUT.to_owned(),
+            },
+            to: LinkEndpoint {
+                node_id: nodes[1].id.clone(),
+                property: switch_node::inputs::media(2),
+            },
+        });
+        pipeline.links.push(Link {
+            from: LinkEndpoint {
+                node_id: nodes[1].id.clone(),
+                property: switch_node::outputs::OUTPUT.to_owned(),
+            },
+            to: LinkEndpoint {
+                node_id: nodes[2].id.clone(),
+                property: blur_node::inputs::MEDIA.to_owned(),
+            },
+        });
+
+        let mut hm = HashMap::new();
+        for node in &nodes {
+            hm.insert(node.id.clone(), node.clone());
+        }
+        let store = Store {
+            nodes: hm,
+            clips: ClipStore::new(),
+            pipeline,
+            node_groups: HashMap::new(),
+            solo_node: None,
+        };
+
+        let mut cache = Cache::new();
+        for node in &nodes {
+            cache.add_to_cache(node.id.clone(), HashMap::new());
+        }
+
+        cache.node_modified(&nodes[0].id, &store);
+        assert!(cache.cache_data.get(&nodes[1].id).is_none());
+        assert!(cache.cache_data.get(&nodes[2].id).is_none());
+    }
 }
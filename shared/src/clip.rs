@@ -6,7 +6,7 @@ use crate::{
         composited_clips_projects_location, is_server, media_output_location,
         source_files_location, CHUNK_FILENAME_NUMBER_LENGTH,
     },
-    node::PipeableType,
+    node::{PipeableType, StreamFormat},
 };
 
 use super::{node::PipeableStreamType, ID};
@@ -32,6 +32,36 @@ pub struct VideoStreamInfo {
     pub height: u32,
     pub framerate: f64,
     pub bitrate: u32,
+    #[serde(default)]
+    pub orientation: VideoOrientation,
+}
+
+/// the orientation of a video stream, as specified by its `image-orientation` tag; the video should be rotated clockwise, and then flipped horizontally if `flip` is set
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct VideoOrientation {
+    pub rotation: u32,
+    pub flip: bool,
+}
+
+impl VideoOrientation {
+    /**
+     * Parses the value of an `image-orientation` tag (e.g. `rotate-90` or `flip-rotate-180`)
+     */
+    pub fn from_tag(tag: &str) -> Option<VideoOrientation> {
+        let (flip, rotation) = match tag.strip_prefix("flip-") {
+            Some(rotation) => (true, rotation),
+            None => (false, tag),
+        };
+        let rotation = rotation.strip_prefix("rotate-")?.parse::<u32>().ok()?;
+        if rotation % 90 != 0 {
+            return None;
+        }
+        Some(VideoOrientation { rotation, flip })
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.rotation % 360 == 0 && !self.flip
+    }
 }
 
 /// metadata about a particular audio stream
@@ -109,6 +139,44 @@ impl SourceClip {
         };
     }
 
+    /**
+     * Gets the orientation of the clip's first video stream, if it has one
+     */
+    pub fn get_orientation(&self) -> VideoOrientation {
+        self.info
+            .as_ref()
+            .and_then(|info| info.video_streams.first())
+            .map(|video_stream| video_stream.orientation)
+            .unwrap_or_default()
+    }
+
+    /**
+     * Gets the format of the clip's streams, based on its length and the metadata of its first video and audio streams
+     */
+    pub fn get_stream_format(&self) -> StreamFormat {
+        let info = self.info.as_ref();
+        let video_stream = info.and_then(|info| info.video_streams.first());
+        let framerate = video_stream
+            .map(|video_stream| video_stream.framerate)
+            .filter(|framerate| *framerate > 0.0); // variable framerate media reports a framerate of 0
+        let resolution = video_stream
+            .map(|video_stream| (video_stream.width, video_stream.height))
+            .filter(|(width, height)| *width > 0 && *height > 0);
+        let duration = info
+            .map(|info| info.duration)
+            .filter(|duration| *duration > 0); // still images have no duration
+        let sample_rate = info
+            .and_then(|info| info.audio_streams.first())
+            .map(|audio_stream| audio_stream.sample_rate)
+            .filter(|sample_rate| *sample_rate > 0);
+        StreamFormat {
+            framerate,
+            resolution,
+            duration,
+            sample_rate,
+        }
+    }
+
     /**
      * Uses GStreamer Discoverer to get metadata about a source clip
      */
@@ -125,7 +193,10 @@ impl SourceClip {
         }
         let info = info.unwrap();
 
-        let duration = info.duration().unwrap().nseconds();
+        let duration = info
+            .duration()
+            .map(|duration| duration.nseconds())
+            .unwrap_or(0); // still images have no duration
         let mut video_streams_vec = Vec::new();
         let video_streams = info.video_streams();
         for video_stream in video_streams {
@@ -138,11 +209,19 @@ impl SourceClip {
                 let fps = fps_num / fps_den;
 
                 let bitrate = video_info.bitrate();
+                let orientation = video_info
+                    .tags()
+                    .and_then(|tags| {
+                        tags.get::<gst::tags::ImageOrientation>()
+                            .and_then(|tag| VideoOrientation::from_tag(tag.get()))
+                    })
+                    .unwrap_or_default();
                 let video_stream = VideoStreamInfo {
                     width,
                     height,
                     bitrate,
                     framerate: fps,
+                    orientation,
                 };
                 video_streams_vec.push(video_stream);
             }
@@ -8,6 +8,7 @@ static IS_SERVER: Lazy<Mutex<Option<bool>>> = Lazy::new(|| Mutex::new(None));
 
 pub const CHUNK_LENGTH: u8 = 1; // Video chunk length, in seconds
 pub const CHUNK_FILENAME_NUMBER_LENGTH: u8 = 6; // The length of the chunk filename template
+pub const AUDIO_ANALYSIS_WINDOW: u64 = 10; // The length of each window when analysing audio levels, in milliseconds
 
 pub fn data_location() -> String {
     DATA_LOCATION.lock().unwrap().as_ref().unwrap().clone()
@@ -21,6 +22,9 @@ pub fn source_files_location() -> String {
 pub fn store_json_location() -> String {
     format!("{}/pipeline.json", data_location())
 }
+pub fn node_definitions_location() -> String {
+    format!("{}/node_definitions", data_location())
+}
 
 pub fn temp_location() -> String {
     format!("{}/temp", data_location())
@@ -58,6 +62,7 @@ pub fn init(data_location: String, is_server: bool) {
 
     fs::create_dir_all(media_output_location()).unwrap();
     fs::create_dir_all(source_files_location()).unwrap();
+    fs::create_dir_all(node_definitions_location()).unwrap();
     fs::create_dir_all(temp_location()).unwrap();
     fs::create_dir_all(projects_location()).unwrap();
     fs::create_dir_all(intermediate_files_location()).unwrap();
@@ -0,0 +1,145 @@
+use crate::ID;
+
+/**
+ * Why the pipeline could not be generated
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum PipelineErrorKind {
+    /// An input which the node needs has nothing piped into it
+    MissingInput,
+    /// A value or piped input is not of the type the node expects
+    TypeMismatch,
+    /// A clip is not given, or does not exist
+    MissingClip,
+    /// GStreamer or GES failed to create or save something
+    Ges,
+    /// A file could not be read or written
+    Io,
+    /// The links between the nodes form a cycle
+    Cycle,
+    Other,
+}
+
+/**
+ * An error found while generating the pipeline, along with the node and property it was found in, where known
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PipelineError {
+    pub node_id: Option<ID>,
+    pub property: Option<String>,
+    pub kind: PipelineErrorKind,
+    pub message: String,
+}
+
+impl PipelineError {
+    pub fn new(kind: PipelineErrorKind, message: String) -> Self {
+        Self {
+            node_id: None,
+            property: None,
+            kind,
+            message,
+        }
+    }
+
+    pub fn missing_input(property: &str) -> Self {
+        Self::new(
+            PipelineErrorKind::MissingInput,
+            format!("Nothing is piped into {}", property),
+        )
+        .with_property(property)
+    }
+
+    pub fn type_mismatch(property: &str, message: String) -> Self {
+        Self::new(PipelineErrorKind::TypeMismatch, message).with_property(property)
+    }
+
+    pub fn missing_clip(property: &str, message: String) -> Self {
+        Self::new(PipelineErrorKind::MissingClip, message).with_property(property)
+    }
+
+    /**
+     * For media piped into `property` which does not have a known length, such as a still image
+     */
+    pub fn unknown_duration(property: &str) -> Self {
+        Self::new(
+            PipelineErrorKind::TypeMismatch,
+            format!(
+                "The media piped into {} does not have a known length",
+                property
+            ),
+        )
+        .with_property(property)
+    }
+
+    pub fn with_property(mut self, property: &str) -> Self {
+        self.property = Some(String::from(property));
+        self
+    }
+
+    /**
+     * Sets the node the error was found in, unless it is already known (e.g. an error in a node inside a group stays with that node)
+     */
+    pub fn for_node(mut self, node_id: ID) -> Self {
+        if self.node_id.is_none() {
+            self.node_id = Some(node_id);
+        }
+        self
+    }
+}
+
+impl std::fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.node_id {
+            Some(node_id) => write!(f, "Node {}: {}", node_id, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl From<String> for PipelineError {
+    fn from(message: String) -> Self {
+        Self::new(PipelineErrorKind::Other, message)
+    }
+}
+
+impl From<std::io::Error> for PipelineError {
+    fn from(error: std::io::Error) -> Self {
+        Self::new(PipelineErrorKind::Io, error.to_string())
+    }
+}
+
+impl From<glib::Error> for PipelineError {
+    fn from(error: glib::Error) -> Self {
+        Self::new(PipelineErrorKind::Ges, error.to_string())
+    }
+}
+
+impl From<glib::BoolError> for PipelineError {
+    fn from(error: glib::BoolError) -> Self {
+        Self::new(PipelineErrorKind::Ges, error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::{PipelineError, PipelineErrorKind};
+
+    #[test]
+    fn test_for_node_keeps_first_node() {
+        let inner = Uuid::new_v4();
+        let outer = Uuid::new_v4();
+        let error = PipelineError::missing_input("media")
+            .for_node(inner)
+            .for_node(outer);
+
+        assert_eq!(error.node_id, Some(inner));
+        assert_eq!(error.property, Some(String::from("media")));
+        assert_eq!(error.kind, PipelineErrorKind::MissingInput);
+        assert_eq!(
+            error.to_string(),
+            format!("Node {}: Nothing is piped into media", inner)
+        );
+    }
+}
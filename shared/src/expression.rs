@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+
+/**
+ * A parsed arithmetic expression, such as `a * 0.5 + sin(t)`
+ * Supports numbers, named variables, `+ - * / % ^`, brackets, and the functions in `FUNCTIONS`
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Number(f64),
+    Variable(String),
+    Negate(Box<Expression>),
+    Binary(char, Box<Expression>, Box<Expression>),
+    Function(String, Vec<Expression>),
+}
+
+/// The functions which can be used in an expression, and how many arguments each takes
+const FUNCTIONS: [(&str, usize); 13] = [
+    ("sin", 1),
+    ("cos", 1),
+    ("tan", 1),
+    ("abs", 1),
+    ("sqrt", 1),
+    ("exp", 1),
+    ("ln", 1),
+    ("floor", 1),
+    ("ceil", 1),
+    ("round", 1),
+    ("min", 2),
+    ("max", 2),
+    ("clamp", 3),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Identifier(String),
+    Operator(char),
+    OpenBracket,
+    CloseBracket,
+    Comma,
+}
+
+impl Expression {
+    /**
+     * Parses an expression, which can only use the given variables (as well as the constants `pi` and `e`)
+     */
+    pub fn parse(text: &str, variables: &[&str]) -> Result<Self, String> {
+        let tokens = tokenise(text)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            variables,
+        };
+        let expression = parser.parse_sum()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("Unexpected {}", describe(token)));
+        }
+        Ok(expression)
+    }
+
+    /**
+     * Evaluates the expression; variables which have not been given a value are 0
+     */
+    pub fn evaluate(&self, values: &HashMap<&str, f64>) -> f64 {
+        match self {
+            Expression::Number(value) => *value,
+            Expression::Variable(name) => match name.as_str() {
+                "pi" => std::f64::consts::PI,
+                "e" => std::f64::consts::E,
+                name => values.get(name).cloned().unwrap_or(0.0),
+            },
+            Expression::Negate(expression) => -expression.evaluate(values),
+            Expression::Binary(operator, left, right) => {
+                let (left, right) = (left.evaluate(values), right.evaluate(values));
+                match operator {
+                    '+' => left + right,
+                    '-' => left - right,
+                    '*' => left * right,
+                    '/' => left / right,
+                    '%' => left % right,
+                    _ => left.powf(right),
+                }
+            }
+            Expression::Function(name, arguments) => {
+                let arguments: Vec<f64> = arguments
+                    .iter()
+                    .map(|argument| argument.evaluate(values))
+                    .collect();
+                match name.as_str() {
+                    "sin" => arguments[0].sin(),
+                    "cos" => arguments[0].cos(),
+                    "tan" => arguments[0].tan(),
+                    "abs" => arguments[0].abs(),
+                    "sqrt" => arguments[0].sqrt(),
+                    "exp" => arguments[0].exp(),
+                    "ln" => arguments[0].ln(),
+                    "floor" => arguments[0].floor(),
+                    "ceil" => arguments[0].ceil(),
+                    "round" => arguments[0].round(),
+                    "min" => f64::min(arguments[0], arguments[1]),
+                    "max" => f64::max(arguments[0], arguments[1]),
+                    _ => f64::min(f64::max(arguments[0], arguments[1]), arguments[2]),
+                }
+            }
+        }
+    }
+
+    /**
+     * Whether the expression refers to a variable anywhere
+     */
+    pub fn uses_variable(&self, variable: &str) -> bool {
+        match self {
+            Expression::Number(_) => false,
+            Expression::Variable(name) => name == variable,
+            Expression::Negate(expression) => expression.uses_variable(variable),
+            Expression::Binary(_, left, right) => {
+                left.uses_variable(variable) || right.uses_variable(variable)
+            }
+            Expression::Function(_, arguments) => arguments
+                .iter()
+                .any(|argument| argument.uses_variable(variable)),
+        }
+    }
+}
+
+fn tokenise(text: &str) -> Result<Vec<Token>, String> {
+    let characters: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < characters.len() {
+        let character = characters[i];
+        if character.is_whitespace() {
+            i += 1;
+        } else if character.is_ascii_digit() || character == '.' {
+            let start = i;
+            while i < characters.len() && (characters[i].is_ascii_digit() || characters[i] == '.') {
+                i += 1;
+            }
+            let number: String = characters[start..i].iter().collect();
+            let value = number.parse::<f64>();
+            if value.is_err() {
+                return Err(format!("Invalid number {}", number));
+            }
+            tokens.push(Token::Number(value.unwrap()));
+        } else if character.is_ascii_alphabetic() || character == '_' {
+            let start = i;
+            while i < characters.len()
+                && (characters[i].is_ascii_alphanumeric() || characters[i] == '_')
+            {
+                i += 1;
+            }
+            tokens.push(Token::Identifier(characters[start..i].iter().collect()));
+        } else {
+            tokens.push(match character {
+                '+' | '-' | '*' | '/' | '%' | '^' => Token::Operator(character),
+                '(' => Token::OpenBracket,
+                ')' => Token::CloseBracket,
+                ',' => Token::Comma,
+                _ => return Err(format!("Unexpected character '{}'", character)),
+            });
+            i += 1;
+        }
+    }
+    Ok(tokens)
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Number(value) => format!("number {}", value),
+        Token::Identifier(name) => format!("name {}", name),
+        Token::Operator(operator) => format!("'{}'", operator),
+        Token::OpenBracket => String::from("'('"),
+        Token::CloseBracket => String::from("')'"),
+        Token::Comma => String::from("','"),
+    }
+}
+
+/**
+ * A recursive descent parser; each method parses one level of operator precedence
+ */
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    variables: &'a [&'a str],
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token.ok_or(String::from("Unexpected end of expression"))
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        let token = self.next()?;
+        if token != expected {
+            return Err(format!(
+                "Expected {} but found {}",
+                describe(&expected),
+                describe(&token)
+            ));
+        }
+        Ok(())
+    }
+
+    // sum := product (('+' | '-') product)*
+    fn parse_sum(&mut self) -> Result<Expression, String> {
+        let mut expression = self.parse_product()?;
+        while let Some(Token::Operator(operator @ ('+' | '-'))) = self.peek().cloned() {
+            self.position += 1;
+            let right = self.parse_product()?;
+            expression = Expression::Binary(operator, Box::new(expression), Box::new(right));
+        }
+        Ok(expression)
+    }
+
+    // product := unary (('*' | '/' | '%') unary)*
+    fn parse_product(&mut self) -> Result<Expression, String> {
+        let mut expression = self.parse_unary()?;
+        while let Some(Token::Operator(operator @ ('*' | '/' | '%'))) = self.peek().cloned() {
+            self.position += 1;
+            let right = self.parse_unary()?;
+            expression = Expression::Binary(operator, Box::new(expression), Box::new(right));
+        }
+        Ok(expression)
+    }
+
+    // unary := '-' unary | power
+    fn parse_unary(&mut self) -> Result<Expression, String> {
+        if let Some(Token::Operator('-')) = self.peek() {
+            self.position += 1;
+            return Ok(Expression::Negate(Box::new(self.parse_unary()?)));
+        }
+        self.parse_power()
+    }
+
+    // power := primary ('^' unary)?, so that powers are right associative
+    fn parse_power(&mut self) -> Result<Expression, String> {
+        let base = self.parse_primary()?;
+        if let Some(Token::Operator('^')) = self.peek() {
+            self.position += 1;
+            let exponent = self.parse_unary()?;
+            return Ok(Expression::Binary('^', Box::new(base), Box::new(exponent)));
+        }
+        Ok(base)
+    }
+
+    // primary := number | name | name '(' arguments ')' | '(' sum ')'
+    fn parse_primary(&mut self) -> Result<Expression, String> {
+        match self.next()? {
+            Token::Number(value) => Ok(Expression::Number(value)),
+            Token::OpenBracket => {
+                let expression = self.parse_sum()?;
+                self.expect(Token::CloseBracket)?;
+                Ok(expression)
+            }
+            Token::Identifier(name) => {
+                if let Some(Token::OpenBracket) = self.peek() {
+                    self.position += 1;
+                    return self.parse_function(name);
+                }
+                if name != "pi" && name != "e" && !self.variables.contains(&name.as_str()) {
+                    return Err(format!("Unknown variable {}", name));
+                }
+                Ok(Expression::Variable(name))
+            }
+            token => Err(format!("Unexpected {}", describe(&token))),
+        }
+    }
+
+    fn parse_function(&mut self, name: String) -> Result<Expression, String> {
+        let function = FUNCTIONS.iter().find(|(function, _)| *function == name);
+        if function.is_none() {
+            return Err(format!("Unknown function {}", name));
+        }
+        let (_, number_of_arguments) = function.unwrap();
+
+        let mut arguments = vec![self.parse_sum()?];
+        while let Some(Token::Comma) = self.peek() {
+            self.position += 1;
+            arguments.push(self.parse_sum()?);
+        }
+        self.expect(Token::CloseBracket)?;
+
+        if arguments.len() != *number_of_arguments {
+            return Err(format!(
+                "{} takes {} argument(s), but was given {}",
+                name,
+                number_of_arguments,
+                arguments.len()
+            ));
+        }
+        Ok(Expression::Function(name, arguments))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::Expression;
+
+    #[test]
+    fn test_expression() {
+        let variables = ["a", "b", "t"];
+        let mut values = HashMap::new();
+        values.insert("a", 4.0);
+        values.insert("b", -1.0);
+        values.insert("t", 0.0);
+
+        let evaluate = |text: &str| {
+            Expression::parse(text, &variables)
+                .unwrap()
+                .evaluate(&values)
+        };
+        assert_eq!(evaluate("a * 0.5 + sin(t)"), 2.0);
+        assert_eq!(evaluate("1 + 2 * 3 - 4 / 2"), 5.0);
+        assert_eq!(evaluate("(1 + 2) * 3"), 9.0);
+        assert_eq!(evaluate("-2 ^ 2"), -4.0);
+        assert_eq!(evaluate("2 ^ 3 ^ 2"), 512.0);
+        assert_eq!(evaluate("clamp(a, b, 2) + max(a, 10) % 3"), 3.0);
+        assert_eq!(evaluate("--b"), -1.0);
+        assert!((evaluate("cos(pi)") + 1.0).abs() < 1e-9);
+
+        assert!(Expression::parse("a +", &variables).is_err());
+        assert!(Expression::parse("a b", &variables).is_err());
+        assert!(Expression::parse("(a", &variables).is_err());
+        assert!(Expression::parse("c * 2", &variables).is_err());
+        assert!(Expression::parse("foo(a)", &variables).is_err());
+        assert!(Expression::parse("min(a)", &variables).is_err());
+        assert!(Expression::parse("a $ 2", &variables).is_err());
+        assert!(Expression::parse("1.2.3", &variables).is_err());
+
+        assert!(Expression::parse("sin(t) * a", &variables)
+            .unwrap()
+            .uses_variable("t"));
+        assert!(!Expression::parse("a * 2", &variables)
+            .unwrap()
+            .uses_variable("t"));
+    }
+}
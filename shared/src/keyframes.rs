@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+
+use ges::traits::TrackElementExt;
+use glib::IsA;
+use gst_controller::prelude::*;
+use serde_json::Value;
+
+/// How many linear segments an eased transition between two keyframes is approximated with
+const EASE_STEPS: u32 = 16;
+/// How long before the next keyframe a held value jumps to the next value, in seconds
+const HOLD_GAP: f64 = 0.001;
+
+/**
+ * How a value changes between a keyframe and the next one
+ */
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    Linear,
+    Hold,
+    Ease,
+}
+
+impl Default for Interpolation {
+    fn default() -> Self {
+        Interpolation::Linear
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe {
+    /// The time of the keyframe, in seconds from the start of the node's output
+    pub time: f64,
+    pub value: f64,
+    /// How the value changes from this keyframe to the next one
+    #[serde(default)]
+    pub interpolation: Interpolation,
+}
+
+/**
+ * The value of a numeric property, which is either a constant, or changes over time between keyframes.
+ * Stored in `Node::properties` as either a number, or an object in the form `{"keyframes": [{"time": 0, "value": 1.2, "interpolation": "linear"}, ...]}`
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum AnimatedNumber {
+    Constant(f64),
+    Keyframed { keyframes: Vec<Keyframe> },
+}
+
+impl AnimatedNumber {
+    /**
+     * Gets the value of a numeric property, using the default value if it has not been set
+     */
+    pub fn from_property(
+        properties: &HashMap<String, Value>,
+        name: &str,
+        default: f64,
+    ) -> Result<Self, String> {
+        match properties.get(name) {
+            Some(value) => Self::from_value(value),
+            None => Ok(AnimatedNumber::Constant(default)),
+        }
+    }
+
+    pub fn from_value(value: &Value) -> Result<Self, String> {
+        let number = serde_json::from_value::<AnimatedNumber>(value.clone());
+        if number.is_err() {
+            return Err(format!("{} is not a number or a list of keyframes", value));
+        }
+        let number = number.unwrap();
+        if let AnimatedNumber::Keyframed { keyframes } = &number {
+            if keyframes.is_empty() {
+                return Err(String::from(
+                    "A keyframed value must have at least one keyframe",
+                ));
+            }
+        }
+        Ok(number)
+    }
+
+    pub fn is_constant(&self) -> bool {
+        matches!(self, AnimatedNumber::Constant(_))
+    }
+
+    /**
+     * Gets the value at the start of the output
+     */
+    pub fn initial_value(&self) -> f64 {
+        self.value_at(0.0)
+    }
+
+    /**
+     * Gets the value at a time (in seconds); before the first keyframe and after the last keyframe, the value is held
+     */
+    pub fn value_at(&self, time: f64) -> f64 {
+        let keyframes = match self {
+            AnimatedNumber::Constant(value) => return *value,
+            AnimatedNumber::Keyframed { keyframes } => sorted(keyframes),
+        };
+        if keyframes.is_empty() {
+            return 0.0;
+        }
+
+        let next = keyframes.iter().position(|keyframe| keyframe.time > time);
+        match next {
+            None => keyframes[keyframes.len() - 1].value,
+            Some(0) => keyframes[0].value,
+            Some(i) => {
+                let (from, to) = (keyframes[i - 1], keyframes[i]);
+                let progress = (time - from.time) / (to.time - from.time);
+                interpolate(&from, &to, progress)
+            }
+        }
+    }
+
+    /**
+     * Gets the points (time in seconds, value) which give the same values as the keyframes when linearly interpolated between, so they can be used with a linear control source
+     * The first point is always at the start of the output, with the value there; points before the start (from keyframes with negative times) are dropped
+     */
+    pub fn get_control_points(&self) -> Vec<(f64, f64)> {
+        let keyframes = match self {
+            AnimatedNumber::Constant(value) => return vec![(0.0, *value)],
+            AnimatedNumber::Keyframed { keyframes } => sorted(keyframes),
+        };
+
+        let mut points = Vec::new();
+        for i in 0..keyframes.len() {
+            let from = keyframes[i];
+            points.push((from.time, from.value));
+            if i + 1 == keyframes.len() {
+                break;
+            }
+            let to = keyframes[i + 1];
+            match from.interpolation {
+                Interpolation::Linear => {}
+                Interpolation::Hold => {
+                    if to.time - from.time > HOLD_GAP {
+                        points.push((to.time - HOLD_GAP, from.value));
+                    }
+                }
+                Interpolation::Ease => {
+                    for step in 1..EASE_STEPS {
+                        let progress = step as f64 / EASE_STEPS as f64;
+                        points.push((
+                            from.time + (to.time - from.time) * progress,
+                            interpolate(&from, &to, progress),
+                        ));
+                    }
+                }
+            }
+        }
+        points.retain(|(time, _)| *time > 0.0);
+        points.insert(0, (0.0, self.initial_value()));
+        points
+    }
+
+    /**
+     * Animates a property of an effect (or other track element) with the keyframes, using a control source. Constant values are left to be set when the element is created
+     */
+    pub fn bind_to_element<E: IsA<ges::TrackElement>>(
+        &self,
+        element: &E,
+        property: &str,
+    ) -> Result<(), String> {
+        if self.is_constant() {
+            return Ok(());
+        }
+        let control_source = gst_controller::InterpolationControlSource::new();
+        control_source.set_mode(gst_controller::InterpolationMode::Linear);
+        for (time, value) in self.get_control_points() {
+            control_source.set(gst::ClockTime::from_nseconds((time * 1e9) as u64), value);
+        }
+        if !element.set_control_source(&control_source, property, "direct-absolute") {
+            return Err(format!("Property {} cannot be keyframed", property));
+        }
+        Ok(())
+    }
+}
+
+fn sorted(keyframes: &Vec<Keyframe>) -> Vec<Keyframe> {
+    let mut keyframes = keyframes.clone();
+    keyframes.sort_by(|a, b| {
+        a.time
+            .partial_cmp(&b.time)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    keyframes
+}
+
+/**
+ * Gets the value part of the way (`progress`, from 0 to 1) between two keyframes
+ */
+fn interpolate(from: &Keyframe, to: &Keyframe, progress: f64) -> f64 {
+    let progress = match from.interpolation {
+        Interpolation::Linear => progress,
+        Interpolation::Hold => 0.0,
+        Interpolation::Ease => progress * progress * (3.0 - 2.0 * progress),
+    };
+    from.value + (to.value - from.value) * progress
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{AnimatedNumber, Interpolation, Keyframe};
+
+    #[test]
+    fn test_value_at() {
+        let number = AnimatedNumber::from_value(&json!({
+            "keyframes": [
+                {"time": 2.0, "value": 10.0, "interpolation": "hold"},
+                {"time": 0.0, "value": 0.0},
+                {"time": 4.0, "value": 20.0, "interpolation": "ease"},
+                {"time": 6.0, "value": 0.0}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(number.initial_value(), 0.0);
+        assert_eq!(number.value_at(1.0), 5.0);
+        assert_eq!(number.value_at(3.9), 10.0);
+        assert_eq!(number.value_at(5.0), 10.0);
+        assert_eq!(number.value_at(100.0), 0.0);
+
+        assert_eq!(
+            AnimatedNumber::from_value(&json!(1.5)).unwrap(),
+            AnimatedNumber::Constant(1.5)
+        );
+        assert!(AnimatedNumber::from_value(&json!("1.5")).is_err());
+        assert!(AnimatedNumber::from_value(&json!({ "keyframes": [] })).is_err());
+    }
+
+    #[test]
+    fn test_control_points() {
+        let number = AnimatedNumber::Keyframed {
+            keyframes: vec![
+                Keyframe {
+                    time: 1.0,
+                    value: 1.0,
+                    interpolation: Interpolation::Hold,
+                },
+                Keyframe {
+                    time: 2.0,
+                    value: 3.0,
+                    interpolation: Interpolation::Linear,
+                },
+            ],
+        };
+        assert_eq!(
+            number.get_control_points(),
+            vec![(0.0, 1.0), (1.0, 1.0), (1.999, 1.0), (2.0, 3.0)]
+        );
+        // the eased points follow the curve when linearly interpolated between
+        let number = AnimatedNumber::Keyframed {
+            keyframes: vec![
+                Keyframe {
+                    time: 0.0,
+                    value: 0.0,
+                    interpolation: Interpolation::Ease,
+                },
+                Keyframe {
+                    time: 1.0,
+                    value: 1.0,
+                    interpolation: Interpolation::Linear,
+                },
+            ],
+        };
+        let points = number.get_control_points();
+        assert_eq!(points.len(), 17);
+        assert_eq!(points[8], (0.5, 0.5));
+        assert!(points[1].1 < 0.0625);
+
+        // keyframes before the start give the value interpolated to the start
+        let number = AnimatedNumber::Keyframed {
+            keyframes: vec![
+                Keyframe {
+                    time: -1.0,
+                    value: 0.0,
+                    interpolation: Interpolation::Linear,
+                },
+                Keyframe {
+                    time: 1.0,
+                    value: 10.0,
+                    interpolation: Interpolation::Linear,
+                },
+            ],
+        };
+        assert_eq!(number.get_control_points(), vec![(0.0, 5.0), (1.0, 10.0)]);
+    }
+}
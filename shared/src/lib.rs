@@ -12,9 +12,14 @@ extern crate serde_json;
 extern crate uuid;
 
 pub mod clip;
+pub mod error;
+pub mod expression;
 pub mod global;
+pub mod keyframes;
+pub mod lint;
 pub mod networking;
 pub mod node;
+pub mod node_group;
 pub mod nodes;
 pub mod pipeline;
 pub mod store;
@@ -22,3 +27,4 @@ pub type ID = Uuid;
 pub mod cache;
 pub mod constants;
 pub mod task;
+pub mod validation;
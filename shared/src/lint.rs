@@ -0,0 +1,628 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::EdgeDirection;
+use uuid::Uuid;
+
+use crate::{
+    cache::Cache,
+    clip::{ClipIdentifier, ClipType},
+    error::{PipelineError, PipelineErrorKind},
+    node::{
+        InputOrOutput, Node, NodeContext, NodeTypeInput, NodeTypeOutput, PipeableType, PipedType,
+        StreamFormat, Type,
+    },
+    nodes::{media_import_node, output_node, NodeRegister},
+    store::Store,
+    ID,
+};
+
+/**
+ * The kind of problem found by linting the pipeline
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum LintKind {
+    /// The links between some nodes form a cycle; the issue's path gives the nodes in the cycle, in order
+    Cycle,
+    /// A node of a type which is not in the node register
+    UnknownNodeType,
+    /// An input which the node needs has nothing linked into it
+    UnconnectedInput,
+    /// An output node with nothing linked into its media input
+    EmptyOutput,
+    /// An output node, or a media import node, whose clip is not given or does not exist
+    MissingClip,
+    /// A composited clip which no output node outputs to
+    UnusedCompositedClip,
+    /// A node whose outputs never reach an output node, so it has no effect
+    OrphanedNode,
+    /// A link to or from a node or property which does not exist
+    DanglingLink,
+    /// A link whose output does not fit the input it is linked into, such as media with too few or too many streams
+    InvalidLink,
+    /// The types in the pipeline could not all be found, so the links which depend on them could not be checked
+    UncheckedLinks,
+}
+
+/**
+ * Whether a problem stops the pipeline being generated, or is only likely to be a mistake
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum LintSeverity {
+    Error,
+    Warning,
+}
+
+/**
+ * A problem with the pipeline, along with the node, property or clip it was found in, where known
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LintIssue {
+    pub kind: LintKind,
+    pub severity: LintSeverity,
+    pub node_id: Option<ID>,
+    pub property: Option<String>,
+    pub clip_id: Option<ID>,
+    /// The nodes involved in the issue, in order (e.g. the nodes around a cycle)
+    pub path: Vec<ID>,
+    pub message: String,
+}
+
+impl LintIssue {
+    fn new(kind: LintKind, message: String) -> Self {
+        let severity = match kind {
+            LintKind::UnusedCompositedClip | LintKind::OrphanedNode => LintSeverity::Warning,
+            _ => LintSeverity::Error,
+        };
+        Self {
+            kind,
+            severity,
+            node_id: None,
+            property: None,
+            clip_id: None,
+            path: Vec::new(),
+            message,
+        }
+    }
+
+    fn for_node(mut self, node_id: ID) -> Self {
+        self.node_id = Some(node_id);
+        self
+    }
+
+    fn with_property(mut self, property: &str) -> Self {
+        self.property = Some(String::from(property));
+        self
+    }
+}
+
+/**
+ * Checks the whole pipeline for problems, without generating it (so without GStreamer), and reports all of them at once
+ * Problems in the nodes' properties are left to validation, and type mismatches between linked nodes are found by `find_invalid_links`, since they need the pipeline's types
+ */
+pub fn lint_pipeline(store: &Store, node_register: &NodeRegister) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+
+    let mut graph: DiGraph<ID, ()> = DiGraph::new();
+    let mut node_indexes = HashMap::new();
+    for id in sorted_node_ids(store) {
+        node_indexes.insert(id, graph.add_node(id));
+    }
+
+    // the composited clip each output node outputs to, and which nodes import each composited clip
+    let mut clip_outputs: HashMap<ID, Vec<ID>> = HashMap::new();
+    let mut clip_imports: HashMap<ID, Vec<ID>> = HashMap::new();
+    for id in sorted_node_ids(store) {
+        let node = &store.nodes[&id];
+        let (clip_property, clips) = match node.node_type.as_str() {
+            output_node::IDENTIFIER => (output_node::inputs::CLIP, &mut clip_outputs),
+            media_import_node::IDENTIFIER => (media_import_node::inputs::CLIP, &mut clip_imports),
+            _ => continue,
+        };
+        match get_clip(node, clip_property, store) {
+            Ok(Some(clip_id)) => clips.entry(clip_id).or_default().push(id),
+            Ok(None) => (),
+            Err(message) => issues.push(
+                LintIssue::new(LintKind::MissingClip, message)
+                    .for_node(id)
+                    .with_property(clip_property),
+            ),
+        }
+    }
+    for (clip_id, output_ids) in &clip_outputs {
+        for import_id in clip_imports.get(clip_id).unwrap_or(&Vec::new()) {
+            for output_id in output_ids {
+                graph.add_edge(node_indexes[output_id], node_indexes[import_id], ());
+            }
+        }
+    }
+
+    let mut linked_inputs: HashMap<ID, HashSet<&str>> = HashMap::new();
+    let mut links_from: HashMap<ID, Vec<&str>> = HashMap::new();
+    for link in &store.pipeline.links {
+        let (from, to) = (
+            node_indexes.get(&link.from.node_id),
+            node_indexes.get(&link.to.node_id),
+        );
+        if from.is_none() || to.is_none() {
+            let missing = if from.is_none() { &link.from } else { &link.to };
+            let mut issue = LintIssue::new(
+                LintKind::DanglingLink,
+                format!(
+                    "A link from {} to {} refers to the node {}, which does not exist",
+                    link.from.get_id(),
+                    link.to.get_id(),
+                    missing.node_id
+                ),
+            );
+            if to.is_some() {
+                issue = issue
+                    .for_node(link.to.node_id)
+                    .with_property(&link.to.property);
+            } else if from.is_some() {
+                issue = issue.for_node(link.from.node_id);
+            }
+            issues.push(issue);
+            continue;
+        }
+        graph.add_edge(*from.unwrap(), *to.unwrap(), ());
+        linked_inputs
+            .entry(link.to.node_id)
+            .or_default()
+            .insert(&link.to.property);
+        links_from
+            .entry(link.from.node_id)
+            .or_default()
+            .push(&link.from.property);
+    }
+
+    issues.extend(find_cycles(&graph));
+
+    for id in sorted_node_ids(store) {
+        let node = &store.nodes[&id];
+        if !node_register.contains_key(&node.node_type) {
+            issues.push(
+                LintIssue::new(
+                    LintKind::UnknownNodeType,
+                    format!("The node type {} does not exist", node.node_type),
+                )
+                .for_node(id),
+            );
+            continue;
+        }
+        let no_links = HashSet::new();
+        let linked = linked_inputs.get(&id).unwrap_or(&no_links);
+
+        if node.node_type == output_node::IDENTIFIER && !linked.contains(output_node::inputs::MEDIA)
+        {
+            issues.push(
+                LintIssue::new(
+                    LintKind::EmptyOutput,
+                    String::from("The output node has no media linked into it"),
+                )
+                .for_node(id)
+                .with_property(output_node::inputs::MEDIA),
+            );
+        }
+
+        let (unconnected, io) = find_unconnected_inputs(node, linked, store, node_register);
+        for input in unconnected {
+            issues.push(
+                LintIssue::new(
+                    LintKind::UnconnectedInput,
+                    format!("Nothing is linked into {}", input),
+                )
+                .for_node(id)
+                .with_property(&input),
+            );
+        }
+
+        // the properties of links can only be checked when the node's inputs and outputs are known
+        if let Some((inputs, outputs)) = io {
+            let mut linked: Vec<&&str> = linked.iter().collect();
+            linked.sort();
+            for property in linked {
+                if !inputs.contains_key(*property) {
+                    issues.push(
+                        LintIssue::new(
+                            LintKind::DanglingLink,
+                            format!(
+                                "A link goes into {}, which the node does not have",
+                                property
+                            ),
+                        )
+                        .for_node(id)
+                        .with_property(property),
+                    );
+                }
+            }
+            for property in links_from.get(&id).unwrap_or(&Vec::new()) {
+                if !outputs.contains_key(*property) {
+                    issues.push(
+                        LintIssue::new(
+                            LintKind::DanglingLink,
+                            format!(
+                                "A link comes from {}, which the node does not have",
+                                property
+                            ),
+                        )
+                        .for_node(id)
+                        .with_property(property),
+                    );
+                }
+            }
+        }
+    }
+
+    let mut clip_ids: Vec<&ID> = store.clips.composited.keys().collect();
+    clip_ids.sort();
+    for clip_id in clip_ids {
+        if !clip_outputs.contains_key(clip_id) {
+            let mut issue = LintIssue::new(
+                LintKind::UnusedCompositedClip,
+                format!(
+                    "The composited clip {} is never output to",
+                    store.clips.composited[clip_id].name
+                ),
+            );
+            issue.clip_id = Some(*clip_id);
+            issues.push(issue);
+        }
+    }
+
+    // nodes which cannot reach an output node, following the links (and composited clips) forwards
+    let mut reaches_output = HashSet::new();
+    let mut queue: VecDeque<NodeIndex> = graph
+        .node_indices()
+        .filter(|index| store.nodes[&graph[*index]].node_type == output_node::IDENTIFIER)
+        .collect();
+    while let Some(index) = queue.pop_front() {
+        if reaches_output.insert(index) {
+            queue.extend(graph.neighbors_directed(index, EdgeDirection::Incoming));
+        }
+    }
+    for index in graph.node_indices() {
+        if !reaches_output.contains(&index) {
+            issues.push(
+                LintIssue::new(
+                    LintKind::OrphanedNode,
+                    String::from("The node is not linked to any output, so has no effect"),
+                )
+                .for_node(graph[index]),
+            );
+        }
+    }
+
+    issues
+}
+
+/**
+ * Finds every link whose output does not fit the input it is linked into, using the types found by generating the pipeline (without its output), so links made invalid by a change further up the pipeline are flagged too
+ * If some of the types cannot be found, the error which stopped them being found is also reported, since the links which depend on them cannot be checked
+ */
+pub fn find_invalid_links(
+    store: &Store,
+    node_register: &NodeRegister,
+    cache: &Cache,
+) -> Vec<LintIssue> {
+    let unchecked = |error: PipelineError| {
+        let mut issue = LintIssue::new(
+            LintKind::UncheckedLinks,
+            format!("Some links could not be checked: {}", error.message),
+        );
+        issue.node_id = error.node_id;
+        issue.property = error.property;
+        issue
+    };
+
+    let types = store
+        .pipeline
+        .generate_pipeline(store, node_register, false, cache);
+    if types.is_err() {
+        return vec![unchecked(types.unwrap_err())];
+    }
+    let (node_type_data, _, error) = types.unwrap();
+
+    let mut issues = Vec::new();
+    for link in &store.pipeline.links {
+        let output = node_type_data
+            .get(&link.from.node_id)
+            .and_then(|(_, _, outputs)| outputs.get(&link.from.property));
+        let input = node_type_data
+            .get(&link.to.node_id)
+            .and_then(|(_, inputs, _)| inputs.get(&link.to.property));
+        if let (Some(output), Some(input)) = (output, input) {
+            let valid = output.check_link_to(input);
+            if valid.is_err() {
+                issues.push(
+                    LintIssue::new(LintKind::InvalidLink, valid.unwrap_err())
+                        .for_node(link.to.node_id)
+                        .with_property(&link.to.property),
+                );
+            }
+        }
+    }
+
+    // invalid links are also returned as the error, but have already been flagged
+    if let Some(error) = error {
+        let flagged = issues
+            .iter()
+            .any(|issue| issue.node_id == error.node_id && issue.property == error.property);
+        if !flagged {
+            issues.push(unchecked(error));
+        }
+    }
+    issues
+}
+
+/**
+ * Gets the node IDs in a fixed order, so the issues are always reported in the same order
+ */
+fn sorted_node_ids(store: &Store) -> Vec<ID> {
+    let mut ids: Vec<ID> = store.nodes.keys().cloned().collect();
+    ids.sort();
+    ids
+}
+
+/**
+ * Gets the composited clip which a clip property refers to; source clips give `None`, since they are not part of the graph
+ */
+fn get_clip(node: &Node, property: &str, store: &Store) -> Result<Option<ID>, String> {
+    let clip = node
+        .properties
+        .get(property)
+        .and_then(|clip| serde_json::from_value::<ClipIdentifier>(clip.clone()).ok());
+    if clip.is_none() {
+        return Err(String::from("No clip has been chosen"));
+    }
+    let clip = clip.unwrap();
+    match clip.clip_type {
+        ClipType::Composited if store.clips.composited.contains_key(&clip.id) => Ok(Some(clip.id)),
+        ClipType::Source if store.clips.source.contains_key(&clip.id) => Ok(None),
+        ClipType::Source if node.node_type == output_node::IDENTIFIER => Err(String::from(
+            "Output nodes must output to a composited clip",
+        )),
+        _ => Err(String::from("The clip does not exist")),
+    }
+}
+
+/**
+ * Finds the inputs which need something linked into them, but have nothing linked into them
+ * The node's inputs can depend on what is piped into it, so stand-in media is piped into each input the node asks for, until all of them are found; the node's inputs and outputs are also returned, if they could all be found
+ * Composited clips are also given stand-in media, since their real types are only known when the pipeline is generated
+ */
+fn find_unconnected_inputs(
+    node: &Node,
+    linked: &HashSet<&str>,
+    store: &Store,
+    node_register: &NodeRegister,
+) -> (
+    Vec<String>,
+    Option<(
+        HashMap<String, NodeTypeInput>,
+        HashMap<String, NodeTypeOutput>,
+    )>,
+) {
+    let node_type = &node_register[&node.node_type];
+    let mut unconnected = Vec::new();
+    let mut piped_inputs = HashMap::new();
+
+    let stand_in = |property: &str| PipedType {
+        stream_type: PipeableType {
+            video: 1,
+            audio: 1,
+            subtitles: 0,
+        },
+        node_id: Uuid::nil(),
+        property_name: String::from(property),
+        io: InputOrOutput::Output,
+        cache_id: None,
+        format: StreamFormat::default(),
+    };
+    let composited_clip_types: HashMap<ID, PipedType> = store
+        .clips
+        .composited
+        .keys()
+        .map(|id| (*id, stand_in(media_import_node::outputs::OUTPUT)))
+        .collect();
+
+    // each pass either finds the inputs and outputs, or one more input that needs media, so there can be at most one pass per property
+    for _ in 0..=(node.properties.len() + node_type.default_properties.len() + linked.len()) {
+        let io = node_type.kind.get_io(&NodeContext {
+            node_id: node.id,
+            properties: &node.properties,
+            piped_inputs: &piped_inputs,
+            composited_clip_types: &composited_clip_types,
+            store,
+            node_register,
+        });
+        match io {
+            Ok((inputs, outputs)) => {
+                let mut required: Vec<&NodeTypeInput> = inputs
+                    .values()
+                    .filter(|input| match &input.property_type {
+                        Type::Pipeable(min, _) => {
+                            min.video > 0 || min.audio > 0 || min.subtitles > 0
+                        }
+                        _ => false,
+                    })
+                    .filter(|input| {
+                        !linked.contains(input.name.as_str())
+                            && !piped_inputs.contains_key(&input.name)
+                    })
+                    .collect();
+                required.sort_by(|a, b| a.name.cmp(&b.name));
+                unconnected.extend(required.iter().map(|input| input.name.clone()));
+                return (unconnected, Some((inputs, outputs)));
+            }
+            Err(error) if error.kind == PipelineErrorKind::MissingInput => {
+                let property = error.property.unwrap_or_default();
+                if piped_inputs.contains_key(&property) {
+                    break;
+                }
+                if !linked.contains(property.as_str()) {
+                    unconnected.push(property.clone());
+                }
+                piped_inputs.insert(property.clone(), stand_in(&property));
+            }
+            Err(_) => break,
+        }
+    }
+    (unconnected, None)
+}
+
+/**
+ * Finds each cycle in the graph, giving the nodes around it in the order they are linked
+ */
+fn find_cycles(graph: &DiGraph<ID, ()>) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let mut components = petgraph::algo::tarjan_scc(graph);
+    for component in &mut components {
+        component.sort_by_key(|index| graph[*index]);
+    }
+    components.sort_by_key(|component| graph[component[0]]);
+
+    for component in components {
+        let start = component[0];
+        let in_component: HashSet<NodeIndex> = component.iter().cloned().collect();
+        if component.len() == 1 && graph.find_edge(start, start).is_none() {
+            continue;
+        }
+
+        // the shortest way back round to the start, only passing through nodes in the cycle
+        let mut previous = HashMap::new();
+        let mut queue = VecDeque::from(vec![start]);
+        while let Some(index) = queue.pop_front() {
+            if index == start && previous.contains_key(&start) {
+                break;
+            }
+            for next in graph.neighbors(index) {
+                if in_component.contains(&next) && !previous.contains_key(&next) {
+                    previous.insert(next, index);
+                    queue.push_back(next);
+                }
+            }
+        }
+        let mut path = vec![graph[start]];
+        let mut index = previous[&start];
+        while index != start {
+            path.push(graph[index]);
+            index = previous[&index];
+        }
+        path.reverse();
+        path.rotate_right(1);
+
+        let mut issue = LintIssue::new(
+            LintKind::Cycle,
+            format!(
+                "The links form a cycle: {}",
+                path.iter()
+                    .chain(path.first())
+                    .map(|id| id.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" -> ")
+            ),
+        )
+        .for_node(graph[start]);
+        issue.path = path;
+        issues.push(issue);
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use uuid::Uuid;
+
+    use super::{lint_pipeline, LintKind};
+    use crate::{
+        node::Node,
+        nodes::{blur_node, media_import_node, output_node},
+        pipeline::{Link, LinkEndpoint},
+        store::Store,
+    };
+
+    fn link(from: &Node, from_property: &str, to: &Node, to_property: &str) -> Link {
+        Link {
+            from: LinkEndpoint {
+                node_id: from.id,
+                property: String::from(from_property),
+            },
+            to: LinkEndpoint {
+                node_id: to.id,
+                property: String::from(to_property),
+            },
+        }
+    }
+
+    #[test]
+    fn test_lint_pipeline() {
+        let mut node_register = HashMap::new();
+        for node_type in vec![
+            media_import_node::media_import_node(),
+            blur_node::blur_node(),
+            output_node::output_node(),
+        ] {
+            node_register.insert(node_type.id.clone(), node_type);
+        }
+        let mut store = Store::new();
+
+        let import = Node::new(String::from(media_import_node::IDENTIFIER), None);
+        let blur1 = Node::new(String::from(blur_node::IDENTIFIER), None);
+        let blur2 = Node::new(String::from(blur_node::IDENTIFIER), None);
+        let blur3 = Node::new(String::from(blur_node::IDENTIFIER), None);
+        let output = Node::new(String::from(output_node::IDENTIFIER), None);
+
+        store.pipeline.links = vec![
+            link(
+                &blur1,
+                blur_node::outputs::OUTPUT,
+                &blur2,
+                blur_node::inputs::MEDIA,
+            ),
+            link(
+                &blur2,
+                blur_node::outputs::OUTPUT,
+                &blur1,
+                blur_node::inputs::MEDIA,
+            ),
+            link(&import, "missing", &blur3, blur_node::inputs::MEDIA),
+            link(&output, "output", &blur3, blur_node::inputs::MEDIA),
+        ];
+        store.pipeline.links[3].from.node_id = Uuid::new_v4();
+        for node in vec![
+            import.clone(),
+            blur1.clone(),
+            blur2.clone(),
+            blur3.clone(),
+            output.clone(),
+        ] {
+            store.nodes.insert(node.id, node);
+        }
+
+        let issues = lint_pipeline(&store, &node_register);
+        let of_kind = |kind| {
+            issues
+                .iter()
+                .filter(|issue| issue.kind == kind)
+                .collect::<Vec<_>>()
+        };
+
+        let cycles = of_kind(LintKind::Cycle);
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].path.len(), 2);
+        assert!(cycles[0].path.contains(&blur1.id) && cycles[0].path.contains(&blur2.id));
+
+        // neither the import node nor the output node has a clip, and the output node has no media
+        assert_eq!(of_kind(LintKind::MissingClip).len(), 2);
+        assert_eq!(of_kind(LintKind::EmptyOutput)[0].node_id, Some(output.id));
+
+        // one link comes from a node which does not exist, and the other from an output which does not exist
+        assert_eq!(of_kind(LintKind::DanglingLink).len(), 2);
+
+        // nothing reaches the output node
+        assert_eq!(of_kind(LintKind::OrphanedNode).len(), 4);
+    }
+}
@@ -38,7 +38,12 @@ enum_from_primitive! {
         DeleteNode,
         CouldNotGeneratePreview,
         CouldNotGetLength,
-        DownloadChunk
+        DownloadChunk,
+        CreateNodeGroup,
+        SoloNode,
+        LintPipeline,
+        InvalidNode,
+        InvalidLink
     }
 }
 
@@ -1,9 +1,13 @@
-use ges::traits::TimelineExt;
-use std::{collections::HashMap, hash::Hash};
+use ges::traits::{GESContainerExt, LayerExt, TimelineExt, TrackExt};
+use std::{collections::HashMap, hash::Hash, sync::Arc};
 
 use serde_json::Value;
 
-use crate::constants::{cache_files_location, intermediate_files_location};
+use crate::{
+    constants::{cache_files_location, intermediate_files_location},
+    error::PipelineError,
+    keyframes::AnimatedNumber,
+};
 
 use super::{global::uniq_id, nodes::NodeRegister, store::Store, ID};
 
@@ -18,6 +22,13 @@ impl Position {
     }
 }
 
+/// The version of node types which do not give one, which is also assumed for nodes saved before nodes recorded the version of their node type
+pub const INITIAL_TYPE_VERSION: &str = "1.0.0";
+
+fn initial_type_version() -> String {
+    String::from(INITIAL_TYPE_VERSION)
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Node {
     pub position: Position,
@@ -25,6 +36,10 @@ pub struct Node {
     pub node_type: String,
     pub properties: HashMap<String, Value>, // A set of key-value pairs - the serde Value allows any type of value to be stored
     pub group: ID,
+    #[serde(default)]
+    pub bypassed: bool, // if set, the node's primary media input is passed straight through to its output
+    #[serde(default = "initial_type_version")]
+    pub type_version: String, // the version of the node type which the properties were saved with
 }
 impl Node {
     pub fn new(node_type: String, group: Option<ID>) -> Self {
@@ -40,6 +55,8 @@ impl Node {
             node_type,
             properties: HashMap::new(),
             group: group_id,
+            bypassed: false,
+            type_version: initial_type_version(),
         }
     }
 
@@ -106,6 +123,50 @@ impl PipeableType {
         timeline
     }
 
+    /**
+     * Checks that there are at least as many of each type of stream as in `min`
+     */
+    pub fn check_meets_min(&self, min: &PipeableType) -> Result<(), String> {
+        for stream_type in [
+            PipeableStreamType::Video,
+            PipeableStreamType::Audio,
+            PipeableStreamType::Subtitles,
+        ] {
+            let (count, min) = (self.of_type(&stream_type), min.of_type(&stream_type));
+            if count < min {
+                return Err(format!(
+                    "has {} {} streams, but at least {} are needed",
+                    count,
+                    stream_type.to_string(),
+                    min
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * Checks that there are no more of each type of stream than in `max`
+     */
+    pub fn check_within_max(&self, max: &PipeableType) -> Result<(), String> {
+        for stream_type in [
+            PipeableStreamType::Video,
+            PipeableStreamType::Audio,
+            PipeableStreamType::Subtitles,
+        ] {
+            let (count, max) = (self.of_type(&stream_type), max.of_type(&stream_type));
+            if count > max {
+                return Err(format!(
+                    "has {} {} streams, but at most {} are allowed",
+                    count,
+                    stream_type.to_string(),
+                    max
+                ));
+            }
+        }
+        Ok(())
+    }
+
     pub fn is_singular_type(&self) -> bool {
         let v = self.video > 0;
         let a = self.audio > 0;
@@ -114,6 +175,95 @@ impl PipeableType {
     }
 }
 
+#[derive(Copy, Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+/// Format information about a stream which is known when the pipeline is generated, so downstream nodes and the output encoding can rely on it
+pub struct StreamFormat {
+    pub framerate: Option<f64>, // `None` if the framerate is unknown, in which case GES's default is used
+    pub resolution: Option<(u32, u32)>, // the width and height of the video, if known
+    pub duration: Option<u64>, // the length of the media in milliseconds, if it can be known without rendering it
+    pub sample_rate: Option<u32>, // the sample rate of the audio, if known
+}
+
+impl StreamFormat {
+    /**
+     * Gets the format of a set of streams that are played together; each value is only kept if all the streams agree on it, and the duration is that of the longest stream (if all the durations are known)
+     */
+    pub fn combine(formats: &Vec<StreamFormat>) -> StreamFormat {
+        let mut framerate = formats.first().and_then(|format| format.framerate);
+        let mut resolution = formats.first().and_then(|format| format.resolution);
+        let mut sample_rate = formats.first().and_then(|format| format.sample_rate);
+        let mut duration = formats.first().and_then(|format| format.duration);
+        for format in formats {
+            if format.framerate != framerate {
+                framerate = None;
+            }
+            if format.resolution != resolution {
+                resolution = None;
+            }
+            if format.sample_rate != sample_rate {
+                sample_rate = None;
+            }
+            duration = match (duration, format.duration) {
+                (Some(duration), Some(other)) => Some(u64::max(duration, other)),
+                _ => None,
+            };
+        }
+        StreamFormat {
+            framerate,
+            resolution,
+            duration,
+            sample_rate,
+        }
+    }
+
+    /**
+     * Gets the format of a set of streams that are played one after another; as with `combine`, except that the duration is the total of the streams' durations
+     */
+    pub fn concatenate(formats: &Vec<StreamFormat>) -> StreamFormat {
+        let mut format = StreamFormat::combine(formats);
+        format.duration = formats
+            .iter()
+            .map(|format| format.duration)
+            .sum::<Option<u64>>();
+        format
+    }
+
+    /**
+     * Gets the format of the streams once rotated clockwise by `rotation` degrees, which swaps the width and height for quarter turns
+     */
+    pub fn rotate(&self, rotation: u32) -> StreamFormat {
+        let mut format = *self;
+        if rotation % 180 == 90 {
+            format.resolution = self.resolution.map(|(width, height)| (height, width));
+        }
+        format
+    }
+
+    /**
+     * Restricts the video tracks of a timeline to this format, so that the timeline is rendered in it
+     */
+    pub fn apply_to_timeline(&self, timeline: &ges::Timeline) {
+        if self.framerate.is_none() && self.resolution.is_none() {
+            return;
+        }
+        let mut caps = gst::Caps::builder("video/x-raw");
+        if let Some(framerate) = self.framerate.and_then(gst::Fraction::approximate_f64) {
+            caps = caps.field("framerate", framerate);
+        }
+        if let Some((width, height)) = self.resolution {
+            caps = caps
+                .field("width", width as i32)
+                .field("height", height as i32);
+        }
+        let caps = caps.build();
+        for track in timeline.tracks() {
+            if track.track_type() == ges::TrackType::VIDEO {
+                track.update_restriction_caps(&caps);
+            }
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum InputOrOutput {
     Input,
@@ -140,9 +290,20 @@ pub struct PipedType {
     pub property_name: String,
     pub io: InputOrOutput,
     pub cache_id: Option<ID>,
+    #[serde(default)]
+    pub format: StreamFormat,
 }
 
 impl PipedType {
+    /**
+     * Creates a timeline with the tracks for this type's streams, rendered in its format
+     */
+    pub fn create_timeline(&self) -> ges::Timeline {
+        let timeline = self.stream_type.create_timeline();
+        self.format.apply_to_timeline(&timeline);
+        timeline
+    }
+
     pub fn get_number_of_streams(&self, stream_type: &PipeableStreamType) -> i32 {
         match stream_type {
             PipeableStreamType::Video => self.stream_type.video,
@@ -199,6 +360,7 @@ pub enum Type {
     Number(Restrictions),
     String(i32),
     Clip,
+    Boolean(bool), // a property which is either on or off, with its default value
 }
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct NodeTypeInput {
@@ -214,17 +376,53 @@ pub struct NodeTypeOutput {
     pub display_name: String,
     pub description: String,
     pub property_type: PipeableType, // The output type is definitive, unlike the input
+    #[serde(default)]
+    pub format: StreamFormat,
+    #[serde(default)]
+    pub number_stream: Option<AnimatedNumber>, // if set, the output is a stream of numbers (with no media streams), which can be linked into number properties of other nodes
 }
 
-/// Shorthand for specifying the `NodeType` functions
-type NodeTypeFunc<T> = fn(
-    node_id: ID,
-    properties: &HashMap<String, Value>,
-    piped_inputs: &HashMap<String, PipedType>,
-    composited_clip_types: &HashMap<ID, PipedType>,
-    store: &Store,
-    node_register: &NodeRegister,
-) -> Result<T, String>;
+impl NodeTypeOutput {
+    /**
+     * Checks whether this output can be linked into an input; number streams can only drive number properties, and media can only be piped into pipeable inputs whose minimum and maximum stream counts it is within
+     */
+    pub fn check_link_to(&self, input: &NodeTypeInput) -> Result<(), String> {
+        match (&self.number_stream, input.property_type) {
+            (Some(_), Type::Number(_)) => Ok(()),
+            (None, Type::Pipeable(min, max)) => {
+                let mut within = self.property_type.check_meets_min(&min);
+                if within.is_ok() {
+                    within = self.property_type.check_within_max(&max);
+                }
+                if within.is_err() {
+                    return Err(format!(
+                        "{} cannot be linked into {}; it {}",
+                        self.display_name,
+                        input.display_name,
+                        within.unwrap_err()
+                    ));
+                }
+                Ok(())
+            }
+            (Some(_), _) => Err(format!(
+                "Number stream {} cannot be linked into {}, which is not a number property",
+                self.display_name, input.display_name
+            )),
+            (None, _) => Err(format!(
+                "Media output {} cannot be linked into {}, which does not accept media",
+                self.display_name, input.display_name
+            )),
+        }
+    }
+}
+
+/// The inputs and outputs of a node
+pub type NodeIo = (
+    HashMap<String, NodeTypeInput>,
+    HashMap<String, NodeTypeOutput>,
+);
+/// The GES timelines for each output of a node, along with anything which must be kept alive until they are saved
+pub type NodeTimelines = (HashMap<String, ges::Timeline>, Vec<MemorySafetyWrapper>);
 
 pub enum MemorySafetyWrapper {
     UriClip(ges::UriClip),
@@ -232,19 +430,413 @@ pub enum MemorySafetyWrapper {
     Effect(ges::Effect),
 }
 
+/**
+ * Everything a node is given when finding its inputs and outputs, or generating its output
+ */
+#[derive(Clone, Copy)]
+pub struct NodeContext<'a> {
+    pub node_id: ID,
+    pub properties: &'a HashMap<String, Value>,
+    pub piped_inputs: &'a HashMap<String, PipedType>,
+    pub composited_clip_types: &'a HashMap<ID, PipedType>,
+    pub store: &'a Store,
+    pub node_register: &'a NodeRegister,
+}
+
+/**
+ * The behaviour of a type of node; implementors can hold any configuration or state the node type needs
+ */
+pub trait NodeKind: Send + Sync {
+    fn default_properties(&self) -> HashMap<String, NodeTypeInput>;
+
+    /**
+     * Gets the inputs and outputs of a node, given its current piped inputs and properties
+     */
+    fn get_io(&self, context: &NodeContext) -> Result<NodeIo, PipelineError>;
+
+    /**
+     * Gets the GES timeline for each of the node's outputs
+     */
+    fn get_output(&self, context: &NodeContext) -> Result<NodeTimelines, PipelineError>;
+
+    /**
+     * Gets the inputs and outputs of a node with one media input, and one output with the same streams and format as the input (or `default_type`, if nothing is piped in yet)
+     */
+    fn single_media_io(
+        &self,
+        context: &NodeContext,
+        input: &str,
+        output: &str,
+        description: &str,
+        default_type: PipeableType,
+    ) -> Result<NodeIo, PipelineError> {
+        let inputs = self.default_properties();
+        let mut outputs = HashMap::new();
+        let (stream_type, format) = match context.piped_inputs.get(input) {
+            Some(piped_input) => (piped_input.stream_type, piped_input.format),
+            None => (default_type, StreamFormat::default()),
+        };
+        outputs.insert(
+            String::from(output),
+            NodeTypeOutput {
+                name: String::from(output),
+                display_name: String::from("Output"),
+                description: String::from(description),
+                property_type: stream_type,
+                format,
+                number_stream: None,
+            },
+        );
+        Ok((inputs, outputs))
+    }
+
+    /**
+     * Gets the output of a node with one media input, which applies `effect` to the input to produce one output
+     */
+    fn single_effect_output(
+        &self,
+        context: &NodeContext,
+        input: &str,
+        output: &str,
+        effect: &ges::Effect,
+    ) -> Result<NodeTimelines, PipelineError> {
+        let (_, outputs) = self.get_io(context)?;
+
+        let media = context.piped_inputs.get(input);
+        if media.is_none() {
+            return Err(PipelineError::missing_input(input));
+        }
+        let media = media.unwrap();
+
+        let output_type = outputs.get(output).unwrap();
+        let output_type = PipedType {
+            stream_type: output_type.property_type,
+            node_id: context.node_id,
+            property_name: String::from(output),
+            io: InputOrOutput::Output,
+            cache_id: None,
+            format: output_type.format,
+        };
+        let timeline = output_type.create_timeline();
+
+        let layer = timeline.append_layer();
+        let clip = ges::UriClip::new(media.get_gst_save_location_with_cache().as_str())?;
+        clip.add(effect)?;
+        layer.add_clip(&clip)?;
+
+        let mut hm = HashMap::new();
+        hm.insert(String::from(output), timeline);
+        Ok((
+            hm,
+            vec![
+                MemorySafetyWrapper::UriClip(clip),
+                MemorySafetyWrapper::Effect(effect.clone()),
+            ],
+        ))
+    }
+}
+
+/**
+ * The section of the node palette a node type is listed in
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeCategory {
+    /// Nodes which change the video of their input
+    Video,
+    /// Nodes which change the audio of their input
+    Audio,
+    /// Nodes which create media or numbers, rather than changing their input
+    Generator,
+    /// Nodes which route, combine or rearrange media
+    Utility,
+}
+
+impl NodeCategory {
+    /// Every category, in the order they are shown in the palette
+    pub const ALL: [NodeCategory; 4] = [
+        NodeCategory::Video,
+        NodeCategory::Audio,
+        NodeCategory::Generator,
+        NodeCategory::Utility,
+    ];
+}
+
+/**
+ * Extra information about a property, shown alongside it in the properties panel
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PropertyHelp {
+    /// A longer explanation than the property's description
+    pub help: Option<String>,
+    /// The units the value is given in (e.g. "s" or "dB")
+    pub units: Option<String>,
+}
+
+/**
+ * Updates the properties of a node saved with one version of its node type to suit the next version (e.g. renaming a property)
+ */
+#[derive(Clone)]
+pub struct NodeMigration {
+    pub from_version: String,
+    pub to_version: String,
+    pub migrate: fn(&mut HashMap<String, Value>),
+}
+
 #[derive(Serialize, Clone)]
 pub struct NodeType {
     pub id: String,
     pub display_name: String,
     pub description: String,
+    pub category: NodeCategory,
+    /// Extra words the node type can be found by when searching the palette
+    pub tags: Vec<String>,
+    /// The semantic version of the node type, which should be increased when its properties change
+    pub version: String,
     pub default_properties: HashMap<String, NodeTypeInput>,
+    /// Help and units for the properties which have them, by property name
+    pub property_help: HashMap<String, PropertyHelp>,
+    /// The number properties which can be keyframed; any other property only uses its value at the start
+    pub animatable: Vec<String>,
+    #[serde(skip_serializing)]
+    pub migrations: Vec<NodeMigration>,
 
-    #[serde(skip_serializing)] // we cannot serialise functions, so do not try to serialise them
-    pub get_io: NodeTypeFunc<(
-        HashMap<String, NodeTypeInput>,
-        HashMap<String, NodeTypeOutput>,
-    )>, // this function returns the inputs, outputs and properties of a node, given its current inputs and properties.
+    #[serde(skip_serializing)] // we cannot serialise behaviour, so do not try to serialise it
+    pub kind: Arc<dyn NodeKind>,
+}
 
-    #[serde(skip_serializing)]
-    pub get_output: NodeTypeFunc<(HashMap<String, ges::Timeline>, Vec<MemorySafetyWrapper>)>, // Gets the GES timeline for that particular node
+impl NodeType {
+    pub fn new<K: NodeKind + 'static>(
+        id: &str,
+        display_name: &str,
+        description: &str,
+        category: NodeCategory,
+        kind: K,
+    ) -> Self {
+        Self {
+            id: String::from(id),
+            display_name: String::from(display_name),
+            description: String::from(description),
+            category,
+            tags: Vec::new(),
+            version: initial_type_version(),
+            default_properties: kind.default_properties(),
+            property_help: HashMap::new(),
+            animatable: Vec::new(),
+            migrations: Vec::new(),
+            kind: Arc::new(kind),
+        }
+    }
+
+    pub fn with_tags(mut self, tags: &[&str]) -> Self {
+        self.tags = tags.iter().map(|tag| String::from(*tag)).collect();
+        self
+    }
+
+    pub fn with_version(mut self, version: &str) -> Self {
+        self.version = String::from(version);
+        self
+    }
+
+    pub fn with_animatable(mut self, properties: &[&str]) -> Self {
+        for property in properties {
+            self.animatable.push(String::from(*property));
+        }
+        self
+    }
+
+    pub fn with_units(mut self, property: &str, units: &str) -> Self {
+        self.property_help
+            .entry(String::from(property))
+            .or_default()
+            .units = Some(String::from(units));
+        self
+    }
+
+    pub fn with_help(mut self, property: &str, help: &str) -> Self {
+        self.property_help
+            .entry(String::from(property))
+            .or_default()
+            .help = Some(String::from(help));
+        self
+    }
+
+    /**
+     * Registers how to update the properties of nodes saved with the `from_version` of the node type; a migration should be added for each version whose properties change
+     */
+    pub fn with_migration(
+        mut self,
+        from_version: &str,
+        to_version: &str,
+        migrate: fn(&mut HashMap<String, Value>),
+    ) -> Self {
+        self.migrations.push(NodeMigration {
+            from_version: String::from(from_version),
+            to_version: String::from(to_version),
+            migrate,
+        });
+        self
+    }
+
+    /**
+     * Brings a node saved with an older version of the node type up to the current version, running each migration in turn
+     * Returns a description of each property which was changed; if there is no migration from the node's version, its properties are kept as they are and this is reported as well
+     */
+    pub fn migrate(&self, node: &mut Node) -> Vec<String> {
+        let mut changes = Vec::new();
+        // bounded, so that migrations which form a cycle cannot loop forever
+        for _ in 0..self.migrations.len() {
+            if node.type_version == self.version {
+                break;
+            }
+            let migration = self
+                .migrations
+                .iter()
+                .find(|migration| migration.from_version == node.type_version);
+            if migration.is_none() {
+                break;
+            }
+            let migration = migration.unwrap();
+
+            let old_properties = node.properties.clone();
+            (migration.migrate)(&mut node.properties);
+            for (property, value) in &old_properties {
+                match node.properties.get(property) {
+                    None => changes.push(format!("removed {}", property)),
+                    Some(new_value) if new_value != value => changes.push(format!(
+                        "changed {} from {} to {}",
+                        property, value, new_value
+                    )),
+                    _ => (),
+                }
+            }
+            for (property, value) in &node.properties {
+                if !old_properties.contains_key(property) {
+                    changes.push(format!("added {} = {}", property, value));
+                }
+            }
+            node.type_version = migration.to_version.clone();
+        }
+        if node.type_version != self.version {
+            changes.push(format!(
+                "no migration from {} to {}, so the properties were kept as they were",
+                node.type_version, self.version
+            ));
+        }
+        node.type_version = self.version.clone();
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{Node, NodeTypeInput, NodeTypeOutput, PipeableType, StreamFormat, Type};
+    use crate::nodes::blur_node::{self, blur_node};
+
+    #[test]
+    fn test_check_link_to_stream_counts() {
+        let output = |video, audio| NodeTypeOutput {
+            name: String::from("output"),
+            display_name: String::from("Output"),
+            description: String::new(),
+            property_type: PipeableType {
+                video,
+                audio,
+                subtitles: 0,
+            },
+            format: StreamFormat::default(),
+            number_stream: None,
+        };
+        let input = NodeTypeInput {
+            name: String::from("media"),
+            display_name: String::from("Media"),
+            description: String::new(),
+            property_type: Type::Pipeable(
+                PipeableType {
+                    video: 1,
+                    audio: 0,
+                    subtitles: 0,
+                },
+                PipeableType {
+                    video: 1,
+                    audio: i32::MAX,
+                    subtitles: i32::MAX,
+                },
+            ),
+        };
+
+        assert!(output(1, 2).check_link_to(&input).is_ok());
+        assert!(output(0, 1).check_link_to(&input).is_err());
+        assert!(output(2, 0).check_link_to(&input).is_err());
+        assert!(output(1, i32::MAX).check_link_to(&input).is_ok());
+    }
+    #[test]
+    fn test_migrate_node() {
+        let node_type = blur_node()
+            .with_version("1.2.0")
+            .with_migration("1.0.0", "1.1.0", |properties| {
+                if let Some(radius) = properties.remove("radius") {
+                    properties.insert(String::from(blur_node::inputs::SIGMA), radius);
+                }
+            })
+            .with_migration("1.1.0", "1.2.0", |properties| {
+                properties.insert(String::from("quality"), json!(1));
+            });
+
+        let mut node = Node::new(String::from(blur_node::IDENTIFIER), None);
+        node.properties.insert(String::from("radius"), json!(3.0));
+        let changes = node_type.migrate(&mut node);
+        assert_eq!(node.type_version, "1.2.0");
+        assert_eq!(
+            node.properties.get(blur_node::inputs::SIGMA),
+            Some(&json!(3.0))
+        );
+        assert_eq!(node.properties.get("quality"), Some(&json!(1)));
+        assert!(!node.properties.contains_key("radius"));
+        assert_eq!(changes.len(), 3);
+
+        // nodes which are up to date are left alone
+        assert!(node_type.migrate(&mut node).is_empty());
+
+        // versions with no migration path are reported, rather than silently updated
+        node.type_version = String::from("0.9.0");
+        let changes = node_type.migrate(&mut node);
+        assert_eq!(node.type_version, "1.2.0");
+        assert_eq!(changes.len(), 1);
+        assert!(changes[0].contains("no migration from 0.9.0"));
+    }
+    #[test]
+    fn test_stream_format_duration() {
+        let format = |duration, sample_rate| StreamFormat {
+            framerate: Some(30.0),
+            resolution: Some((1920, 1080)),
+            duration,
+            sample_rate,
+        };
+        let formats = vec![
+            format(Some(2000), Some(48000)),
+            format(Some(5000), Some(44100)),
+        ];
+        let combined = StreamFormat::combine(&formats);
+        assert_eq!(combined.duration, Some(5000));
+        assert_eq!(combined.sample_rate, None);
+        assert_eq!(combined.resolution, Some((1920, 1080)));
+        assert_eq!(StreamFormat::concatenate(&formats).duration, Some(7000));
+
+        // the length is only known if the lengths of all the streams are
+        let formats = vec![format(Some(2000), None), format(None, None)];
+        assert_eq!(StreamFormat::combine(&formats).duration, None);
+        assert_eq!(StreamFormat::concatenate(&formats).duration, None);
+
+        assert_eq!(
+            format(None, None).rotate(270).resolution,
+            Some((1080, 1920))
+        );
+        assert_eq!(
+            format(None, None).rotate(180).resolution,
+            Some((1920, 1080))
+        );
+    }
 }
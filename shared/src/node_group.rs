@@ -0,0 +1,430 @@
+use std::collections::{HashMap, HashSet};
+
+use uuid::Uuid;
+
+use crate::{
+    error::PipelineError,
+    global::uniq_id,
+    node::Node,
+    nodes::{
+        audio_sync_node, ducking_node, node_group_node, output_node, silence_removal_node,
+        visualiser_node, NodeRegister,
+    },
+    pipeline::{Link, LinkEndpoint},
+    store::Store,
+    ID,
+};
+
+/// Node types which cannot be put in a group; output nodes belong to a composited clip, and the nodes which are analysed on the server store their results on the node itself
+const UNGROUPABLE_NODE_TYPES: [&str; 6] = [
+    output_node::IDENTIFIER,
+    node_group_node::IDENTIFIER,
+    audio_sync_node::IDENTIFIER,
+    silence_removal_node::IDENTIFIER,
+    ducking_node::IDENTIFIER,
+    visualiser_node::IDENTIFIER,
+];
+
+/**
+ * An input, output or property of a group node, which stands for an input, output or property of one of the nodes inside the group
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GroupEndpoint {
+    pub name: String,
+    pub display_name: String,
+    pub endpoint: LinkEndpoint,
+}
+
+/**
+ * A set of linked nodes saved as a reusable node type; each group node (see `node_group_node`) is replaced with a copy of the nodes when the pipeline is generated
+ */
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NodeGroup {
+    pub id: ID,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub nodes: HashMap<ID, Node>,
+    pub links: Vec<Link>,
+    /// Inputs of the nodes inside the group which can be linked into from outside of it
+    pub inputs: Vec<GroupEndpoint>,
+    pub outputs: Vec<GroupEndpoint>,
+    /// Properties of the nodes inside the group which can be set separately on each group node
+    pub properties: Vec<GroupEndpoint>,
+}
+
+impl NodeGroup {
+    /**
+     * Creates a node group from a selection of nodes in the store, keeping the links between them
+     */
+    pub fn from_selection(
+        store: &Store,
+        node_ids: &[ID],
+        name: String,
+        description: String,
+        inputs: Vec<GroupEndpoint>,
+        outputs: Vec<GroupEndpoint>,
+        properties: Vec<GroupEndpoint>,
+    ) -> Result<Self, String> {
+        let mut nodes = HashMap::new();
+        for id in node_ids {
+            let node = store.nodes.get(id);
+            if node.is_none() {
+                return Err(format!("Node {} does not exist", id));
+            }
+            nodes.insert(id.clone(), node.unwrap().clone());
+        }
+        let links = store
+            .pipeline
+            .links
+            .iter()
+            .filter(|link| {
+                nodes.contains_key(&link.from.node_id) && nodes.contains_key(&link.to.node_id)
+            })
+            .cloned()
+            .collect();
+
+        Ok(Self {
+            id: uniq_id(),
+            name,
+            description,
+            nodes,
+            links,
+            inputs,
+            outputs,
+            properties,
+        })
+    }
+
+    /**
+     * Checks that the group can be used as a node type; every endpoint must belong to a node inside the group, and the names of the group node's inputs and properties must be unique
+     */
+    pub fn validate(&self, node_register: &NodeRegister) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err(String::from("The group must have a name"));
+        }
+        if self.nodes.is_empty() {
+            return Err(String::from("The group must contain at least one node"));
+        }
+        for node in self.nodes.values() {
+            if !node_register.contains_key(&node.node_type) {
+                return Err(format!("Unknown node type {}", node.node_type));
+            }
+            if UNGROUPABLE_NODE_TYPES.contains(&node.node_type.as_str()) {
+                return Err(format!(
+                    "Nodes of type {} cannot be put in a group",
+                    node.node_type
+                ));
+            }
+        }
+        for link in &self.links {
+            if !self.nodes.contains_key(&link.from.node_id)
+                || !self.nodes.contains_key(&link.to.node_id)
+            {
+                return Err(String::from(
+                    "Link contains reference to a node outside of the group",
+                ));
+            }
+        }
+
+        // the group node's own property chooses the group, so cannot be used by an input
+        let mut input_names = HashSet::new();
+        input_names.insert(node_group_node::inputs::NODE_GROUP);
+        for input in self.inputs.iter().chain(&self.properties) {
+            if !self.nodes.contains_key(&input.endpoint.node_id) {
+                return Err(format!(
+                    "Input {} is not of a node in the group",
+                    input.name
+                ));
+            }
+            if !input_names.insert(input.name.as_str()) {
+                return Err(format!("There is more than one input named {}", input.name));
+            }
+        }
+        for input in &self.inputs {
+            if self.links.iter().any(|link| link.to == input.endpoint) {
+                return Err(format!(
+                    "Input {} is already linked inside the group",
+                    input.name
+                ));
+            }
+        }
+        let mut output_names = HashSet::new();
+        for output in &self.outputs {
+            if !self.nodes.contains_key(&output.endpoint.node_id) {
+                return Err(format!(
+                    "Output {} is not of a node in the group",
+                    output.name
+                ));
+            }
+            if !output_names.insert(output.name.as_str()) {
+                return Err(format!(
+                    "There is more than one output named {}",
+                    output.name
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/**
+ * The inputs and outputs of a group node after it has been expanded, with the endpoints of the copies of the nodes inside it which they stand for
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupExpansion {
+    /// Both the inputs and the properties of the group node
+    pub inputs: Vec<GroupEndpoint>,
+    pub outputs: Vec<GroupEndpoint>,
+}
+
+impl GroupExpansion {
+    fn input(&self, name: &str) -> Option<&LinkEndpoint> {
+        self.inputs
+            .iter()
+            .find(|input| input.name == name)
+            .map(|input| &input.endpoint)
+    }
+
+    fn output(&self, name: &str) -> Option<&LinkEndpoint> {
+        self.outputs
+            .iter()
+            .find(|output| output.name == name)
+            .map(|output| &output.endpoint)
+    }
+}
+
+/**
+ * Gets the ID of the copy of a node inside a group, for a particular group node; it is the same every time, so the copy's files are reused between generations
+ */
+pub fn expanded_node_id(group_node_id: &ID, node_id: &ID) -> ID {
+    Uuid::from_u128(group_node_id.as_u128() ^ node_id.as_u128())
+}
+
+/**
+ * Replaces every group node in the store with a copy of the nodes in its group, so that the pipeline can be generated as normal
+ * Links into and out of group nodes are moved to the copies of the nodes they stand for, and the group node's properties are copied onto them
+ * Also returns how each group node was expanded, by the group node's ID
+ */
+pub fn expand_node_groups(
+    store: &Store,
+) -> Result<(Store, HashMap<ID, GroupExpansion>), PipelineError> {
+    let mut expanded = store.clone();
+    let mut expansions = HashMap::new();
+    let mut group_links = Vec::new();
+
+    for (id, node) in &store.nodes {
+        if node.node_type != node_group_node::IDENTIFIER {
+            continue;
+        }
+        let group = node_group_node::get_node_group(&node.properties, store);
+        if group.is_err() {
+            return Err(PipelineError::from(group.unwrap_err())
+                .with_property(node_group_node::inputs::NODE_GROUP)
+                .for_node(id.clone()));
+        }
+        let group = group.unwrap();
+
+        expanded.nodes.remove(id);
+        for (inner_id, inner_node) in &group.nodes {
+            let mut inner_node = inner_node.clone();
+            inner_node.id = expanded_node_id(id, inner_id);
+            inner_node.group = node.group.clone();
+            expanded.nodes.insert(inner_node.id.clone(), inner_node);
+        }
+        let expand_endpoint = |endpoint: &GroupEndpoint| GroupEndpoint {
+            name: endpoint.name.clone(),
+            display_name: endpoint.display_name.clone(),
+            endpoint: LinkEndpoint {
+                node_id: expanded_node_id(id, &endpoint.endpoint.node_id),
+                property: endpoint.endpoint.property.clone(),
+            },
+        };
+        let expansion = GroupExpansion {
+            inputs: group
+                .inputs
+                .iter()
+                .chain(&group.properties)
+                .map(expand_endpoint)
+                .collect(),
+            outputs: group.outputs.iter().map(expand_endpoint).collect(),
+        };
+
+        // the values set on the group node replace the values of the properties they stand for
+        for input in &expansion.inputs {
+            if let Some(value) = node.properties.get(&input.name) {
+                let inner_node = expanded.nodes.get_mut(&input.endpoint.node_id);
+                if inner_node.is_none() {
+                    return Err(PipelineError::from(String::from("The group is invalid"))
+                        .for_node(id.clone()));
+                }
+                inner_node
+                    .unwrap()
+                    .properties
+                    .insert(input.endpoint.property.clone(), value.clone());
+            }
+        }
+        for link in &group.links {
+            group_links.push(Link {
+                from: LinkEndpoint {
+                    node_id: expanded_node_id(id, &link.from.node_id),
+                    property: link.from.property.clone(),
+                },
+                to: LinkEndpoint {
+                    node_id: expanded_node_id(id, &link.to.node_id),
+                    property: link.to.property.clone(),
+                },
+            });
+        }
+        expansions.insert(id.clone(), expansion);
+    }
+
+    let mut links = Vec::new();
+    for link in &store.pipeline.links {
+        let mut link = link.clone();
+        if let Some(expansion) = expansions.get(&link.from.node_id) {
+            let output = expansion.output(&link.from.property);
+            if output.is_none() {
+                return Err(PipelineError::from(format!(
+                    "Group node has no output {}",
+                    link.from.property
+                ))
+                .with_property(&link.from.property)
+                .for_node(link.from.node_id.clone()));
+            }
+            link.from = output.unwrap().clone();
+        }
+        if let Some(expansion) = expansions.get(&link.to.node_id) {
+            let input = expansion.input(&link.to.property);
+            if input.is_none() {
+                return Err(PipelineError::from(format!(
+                    "Group node has no input {}",
+                    link.to.property
+                ))
+                .with_property(&link.to.property)
+                .for_node(link.to.node_id.clone()));
+            }
+            link.to = input.unwrap().clone();
+        }
+        links.push(link);
+    }
+    links.append(&mut group_links);
+    expanded.pipeline.links = links;
+
+    Ok((expanded, expansions))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        node::Node,
+        nodes::node_group_node,
+        pipeline::{Link, LinkEndpoint},
+        store::Store,
+    };
+
+    use super::{expand_node_groups, expanded_node_id, GroupEndpoint, NodeGroup};
+
+    #[test]
+    fn test_expand_node_groups() {
+        let endpoint = |node: &Node, property: &str| LinkEndpoint {
+            node_id: node.id.clone(),
+            property: String::from(property),
+        };
+        let group_endpoint = |name: &str, endpoint: LinkEndpoint| GroupEndpoint {
+            name: String::from(name),
+            display_name: String::from(name),
+            endpoint,
+        };
+
+        // a group of a blur followed by a volume node
+        let mut store = Store::new();
+        let blur = Node::new(String::from("blur"), None);
+        let mut volume = Node::new(String::from("audio_gain"), None);
+        volume.properties.insert(String::from("gain"), json!(1.0));
+        store.nodes.insert(blur.id.clone(), blur.clone());
+        store.nodes.insert(volume.id.clone(), volume.clone());
+        store.pipeline.links.push(Link {
+            from: endpoint(&blur, "output"),
+            to: endpoint(&volume, "media"),
+        });
+        let group = NodeGroup::from_selection(
+            &store,
+            &[blur.id.clone(), volume.id.clone()],
+            String::from("Blur and gain"),
+            String::new(),
+            vec![group_endpoint("media", endpoint(&blur, "media"))],
+            vec![group_endpoint("output", endpoint(&volume, "output"))],
+            vec![group_endpoint("loudness", endpoint(&volume, "gain"))],
+        )
+        .unwrap();
+        assert_eq!(group.links.len(), 1);
+
+        // a clip imported into a group node, which is then output
+        let mut store = Store::new();
+        let import = Node::new(String::from("clip_import"), None);
+        let output = Node::new(String::from("output"), Some(import.group.clone()));
+        let mut group_node = Node::new(
+            String::from(node_group_node::IDENTIFIER),
+            Some(import.group.clone()),
+        );
+        group_node.properties.insert(
+            String::from(node_group_node::inputs::NODE_GROUP),
+            json!(group.id),
+        );
+        group_node
+            .properties
+            .insert(String::from("loudness"), json!(2.5));
+        store.node_groups.insert(group.id.clone(), group);
+        for node in [&import, &output, &group_node] {
+            store.nodes.insert(node.id.clone(), node.clone());
+        }
+        store.pipeline.links.push(Link {
+            from: endpoint(&import, "output"),
+            to: endpoint(&group_node, "media"),
+        });
+        store.pipeline.links.push(Link {
+            from: endpoint(&group_node, "output"),
+            to: endpoint(&output, "media"),
+        });
+
+        let (expanded, expansions) = expand_node_groups(&store).unwrap();
+        let (inner_blur, inner_volume) = (
+            expanded_node_id(&group_node.id, &blur.id),
+            expanded_node_id(&group_node.id, &volume.id),
+        );
+        assert_eq!(expanded.nodes.len(), 4);
+        assert!(!expanded.nodes.contains_key(&group_node.id));
+        assert_eq!(expanded.nodes[&inner_volume].group, import.group);
+        assert_eq!(
+            expanded.nodes[&inner_volume].properties.get("gain"),
+            Some(&json!(2.5))
+        );
+
+        let links: Vec<(LinkEndpoint, LinkEndpoint)> = expanded
+            .pipeline
+            .links
+            .iter()
+            .map(|link| (link.from.clone(), link.to.clone()))
+            .collect();
+        let inner = |id, property: &str| LinkEndpoint {
+            node_id: id,
+            property: String::from(property),
+        };
+        assert_eq!(
+            links,
+            vec![
+                (endpoint(&import, "output"), inner(inner_blur, "media")),
+                (inner(inner_volume, "output"), endpoint(&output, "media")),
+                (inner(inner_blur, "output"), inner(inner_volume, "media")),
+            ]
+        );
+        assert_eq!(expansions[&group_node.id].inputs.len(), 2);
+
+        // links to inputs the group does not have are rejected
+        store.pipeline.links[0].to.property = String::from("missing");
+        assert!(expand_node_groups(&store).is_err());
+    }
+}
@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+
+use ges::{
+    traits::{LayerExt, TimelineExt},
+    TrackType,
+};
+use glib::StaticType;
+use serde_json::Value;
+
+use crate::{
+    constants::AUDIO_ANALYSIS_WINDOW,
+    error::PipelineError,
+    node::{
+        InputOrOutput, MemorySafetyWrapper, Node, NodeCategory, NodeContext, NodeIo, NodeKind,
+        NodeTimelines, NodeType, NodeTypeInput, NodeTypeOutput, PipeableType, PipedType,
+        Restrictions, StreamFormat, Type,
+    },
+    store::Store,
+};
+
+use super::{get_input_key, ANALYSIS_KEY};
+
+pub const IDENTIFIER: &str = "audio_sync";
+pub mod inputs {
+    pub const NUMBER_OF_INPUTS: &str = "number_of_inputs";
+    pub const OFFSETS: &str = "offsets";
+
+    pub fn media(i: i32) -> String {
+        format!("media{}", i)
+    }
+}
+pub mod outputs {
+    pub fn output(i: i32) -> String {
+        format!("output{}", i)
+    }
+}
+
+const MIN_INPUTS: i32 = 2;
+const MAX_INPUTS: i32 = 8;
+const MAX_OFFSET: u64 = 60000; // The furthest apart two inputs can be and still be synchronised, in milliseconds
+
+fn media_input(i: i32) -> NodeTypeInput {
+    NodeTypeInput {
+        name: inputs::media(i),
+        display_name: format!("Media {}", i),
+        description: if i == 1 {
+            String::from("The reference media; all other media will be aligned to this")
+        } else {
+            String::from("The media to align to the reference media")
+        },
+        property_type: Type::Pipeable(
+            PipeableType {
+                video: 0,
+                audio: 1,
+                subtitles: 0,
+            },
+            PipeableType {
+                video: i32::MAX,
+                audio: i32::MAX,
+                subtitles: i32::MAX,
+            },
+        ),
+    }
+}
+
+/**
+ * Gets the number of media inputs the node currently has
+ */
+fn get_number_of_inputs(properties: &HashMap<String, Value>) -> i32 {
+    super::get_number_of_inputs(
+        properties,
+        inputs::NUMBER_OF_INPUTS,
+        MIN_INPUTS,
+        MIN_INPUTS,
+        MAX_INPUTS,
+    )
+}
+
+/**
+ * Gets the stored offsets for each of the inputs, in milliseconds. Returns `None` if the offsets have not been computed for the current set of inputs
+ */
+pub fn get_offsets(properties: &HashMap<String, Value>) -> Option<Vec<i64>> {
+    let offsets = properties.get(inputs::OFFSETS);
+    if offsets.is_none() {
+        return None;
+    }
+    let offsets = offsets.unwrap().as_str();
+    if offsets.is_none() {
+        return None;
+    }
+    let offsets: Result<Vec<i64>, _> = offsets
+        .unwrap()
+        .split(",")
+        .map(|offset| offset.trim().parse::<i64>())
+        .collect();
+    if offsets.is_err() {
+        return None;
+    }
+    let offsets = offsets.unwrap();
+    if offsets.len() as i32 != get_number_of_inputs(properties) {
+        return None;
+    }
+    Some(offsets)
+}
+
+pub struct AudioSyncNode;
+
+impl NodeKind for AudioSyncNode {
+    fn default_properties(&self) -> HashMap<String, NodeTypeInput> {
+        let mut default_properties = HashMap::new();
+
+        default_properties.insert(
+            String::from(inputs::NUMBER_OF_INPUTS),
+            NodeTypeInput {
+                name: String::from(inputs::NUMBER_OF_INPUTS),
+                display_name: String::from("Number of Inputs"),
+                description: String::from("The number of media sources to synchronise"),
+                property_type: Type::Number(Restrictions {
+                    min: MIN_INPUTS as f64,
+                    max: MAX_INPUTS as f64,
+                    step: 1.0,
+                    default: MIN_INPUTS as f64,
+                }),
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::OFFSETS),
+            NodeTypeInput {
+                name: String::from(inputs::OFFSETS),
+                display_name: String::from("Offsets"),
+                description: String::from(
+                    "The computed offset of each input relative to the first, in milliseconds. Clear this to resynchronise the inputs",
+                ),
+                property_type: Type::String(256),
+            },
+        );
+        for i in 1..(MIN_INPUTS + 1) {
+            default_properties.insert(inputs::media(i), media_input(i));
+        }
+
+        default_properties
+    }
+
+    fn get_io(&self, context: &NodeContext) -> Result<NodeIo, PipelineError> {
+        let NodeContext {
+            properties,
+            piped_inputs,
+            ..
+        } = *context;
+        let mut inputs = self.default_properties();
+        let mut outputs = HashMap::new();
+
+        let number_of_inputs = get_number_of_inputs(properties);
+        let offsets = get_offsets(properties).unwrap_or(vec![0; number_of_inputs as usize]);
+        let earliest_offset = offsets.iter().min().copied().unwrap_or(0);
+        for i in 1..(number_of_inputs + 1) {
+            if !inputs.contains_key(&inputs::media(i)) {
+                inputs.insert(inputs::media(i), media_input(i));
+            }
+
+            let mut stream_type = PipeableType {
+                video: i32::MAX,
+                audio: i32::MAX,
+                subtitles: i32::MAX,
+            };
+            let mut format = StreamFormat::default();
+            if let Some(piped_input) = piped_inputs.get(&inputs::media(i)) {
+                stream_type = piped_input.stream_type;
+                format = piped_input.format;
+                // each input is delayed so that it starts in line with the earliest input
+                let start = (offsets[(i - 1) as usize] - earliest_offset) as u64;
+                format.duration = format.duration.map(|duration| duration + start);
+            }
+            outputs.insert(
+                outputs::output(i),
+                NodeTypeOutput {
+                    name: outputs::output(i),
+                    display_name: format!("Output {}", i),
+                    description: format!("Media {}, aligned to the common timeline", i),
+                    property_type: stream_type,
+                    format,
+                    number_stream: None,
+                },
+            );
+        }
+
+        return Ok((inputs, outputs));
+    }
+
+    fn get_output(&self, context: &NodeContext) -> Result<NodeTimelines, PipelineError> {
+        let NodeContext {
+            node_id,
+            properties,
+            piped_inputs,
+            ..
+        } = *context;
+        let io = self.get_io(context);
+        if io.is_err() {
+            return Err(io.unwrap_err());
+        }
+        let (_, outputs) = io.unwrap();
+
+        let number_of_inputs = get_number_of_inputs(properties);
+
+        // Until the offsets have been computed by the server, the inputs are left unaligned
+        let offsets = get_offsets(properties).unwrap_or(vec![0; number_of_inputs as usize]);
+        let earliest_offset = *offsets.iter().min().unwrap();
+
+        let mut hm = HashMap::new();
+        let mut memory_safety_vars = Vec::new();
+        for i in 1..(number_of_inputs + 1) {
+            let media = piped_inputs.get(&inputs::media(i));
+            if media.is_none() {
+                return Err(PipelineError::missing_input(&inputs::media(i)));
+            }
+            let media = media.unwrap();
+
+            let output = outputs.get(&outputs::output(i)).unwrap();
+            let output = PipedType {
+                stream_type: output.property_type,
+                node_id,
+                property_name: outputs::output(i),
+                io: InputOrOutput::Output,
+                cache_id: None,
+                format: output.format,
+            };
+
+            let timeline = output.create_timeline();
+            let layer = timeline.append_layer();
+
+            let location = media.get_gst_save_location_with_cache();
+            ges::Asset::needs_reload(ges::UriClip::static_type(), Some(location.as_str()));
+            let clip = ges::UriClipAsset::request_sync(location.as_str())?;
+
+            let start = (offsets[(i - 1) as usize] - earliest_offset) as u64;
+            layer.add_asset(
+                &clip,
+                Some(gst::ClockTime::from_mseconds(start)),
+                None,
+                None,
+                TrackType::UNKNOWN,
+            )?;
+
+            hm.insert(outputs::output(i), timeline);
+            memory_safety_vars.push(MemorySafetyWrapper::UriClipAsset(clip));
+        }
+
+        Ok((hm, memory_safety_vars))
+    }
+}
+
+pub fn audio_sync_node() -> NodeType {
+    NodeType::new(
+        IDENTIFIER,
+        "Audio Sync",
+        "Align multiple media sources to a common timeline by matching their audio",
+        NodeCategory::Audio,
+        AudioSyncNode,
+    )
+    .with_tags(&["sync", "align", "multicam", "offset"])
+    .with_units(inputs::OFFSETS, "ms")
+    .with_help(
+        inputs::OFFSETS,
+        "Offsets are computed by cross-correlating the audio of each input with the first input. Edit them to nudge an input by hand, or clear them to compute them again",
+    )
+}
+
+/**
+ * Gets the media that needs its audio analysing, if the offsets are out of date. The returned key should be stored alongside the results of the analysis
+ *
+ * Every input and everything upstream of it affects the offsets, so they are recomputed whenever any of these change, or when the user clears them
+ */
+pub fn get_analysis_request(
+    node: &Node,
+    piped_inputs: &HashMap<String, PipedType>,
+    store: &Store,
+) -> Option<(String, Vec<PipedType>)> {
+    let number_of_inputs = get_number_of_inputs(&node.properties);
+
+    let mut key = Vec::new();
+    let mut media = Vec::new();
+    for i in 1..(number_of_inputs + 1) {
+        let piped_input = piped_inputs.get(&inputs::media(i));
+        let input_key = get_input_key(node, &inputs::media(i), store);
+        if piped_input.is_none() || input_key.is_none() {
+            return None; // can't align the inputs until they're all connected
+        }
+        media.push(piped_input.unwrap().clone());
+        key.push(input_key.unwrap());
+    }
+    let key = key.join(",");
+
+    let existing_key = node.properties.get(ANALYSIS_KEY);
+    let up_to_date = match existing_key {
+        Some(Value::String(existing_key)) => *existing_key == key,
+        _ => false,
+    };
+    if up_to_date && get_offsets(&node.properties).is_some() {
+        return None;
+    }
+    Some((key, media))
+}
+
+/**
+ * Computes the offsets of each input from their audio levels (one level per `AUDIO_ANALYSIS_WINDOW`, in dB), returning the properties to update
+ */
+pub fn analyse(
+    _properties: &HashMap<String, Value>,
+    levels: &Vec<Vec<f64>>,
+) -> Result<HashMap<String, Value>, String> {
+    if levels.is_empty() {
+        return Err(String::from("No audio to synchronise"));
+    }
+    let envelopes: Vec<Vec<f64>> = levels.iter().map(|l| get_envelope(l)).collect();
+    let reference = &envelopes[0];
+    let max_lag = (MAX_OFFSET / AUDIO_ANALYSIS_WINDOW) as usize;
+
+    let offsets: Vec<String> = envelopes
+        .iter()
+        .map(|envelope| {
+            let lag = find_offset(reference, envelope, max_lag);
+            (lag * AUDIO_ANALYSIS_WINDOW as i64).to_string()
+        })
+        .collect();
+
+    let mut properties = HashMap::new();
+    properties.insert(
+        String::from(inputs::OFFSETS),
+        Value::String(offsets.join(",")),
+    );
+    Ok(properties)
+}
+
+/**
+ * Converts a set of dB levels into linear amplitudes, with the mean removed so that the correlation is not dominated by the overall loudness
+ */
+fn get_envelope(levels: &Vec<f64>) -> Vec<f64> {
+    let amplitudes: Vec<f64> = levels.iter().map(|db| f64::powf(10.0, db / 20.0)).collect();
+    if amplitudes.is_empty() {
+        return amplitudes;
+    }
+    let mean = amplitudes.iter().sum::<f64>() / amplitudes.len() as f64;
+    amplitudes.iter().map(|a| a - mean).collect()
+}
+
+/**
+ * Finds the lag (in windows) at which `other` best lines up with `reference`, via cross-correlation.
+ * A positive lag means that `other` starts that many windows after `reference`
+ */
+pub fn find_offset(reference: &Vec<f64>, other: &Vec<f64>, max_lag: usize) -> i64 {
+    let max_lag = max_lag as i64;
+    let min_lag = i64::max(-max_lag, -(other.len() as i64) + 1);
+    let max_lag = i64::min(max_lag, reference.len() as i64 - 1);
+
+    let mut best_lag = 0;
+    let mut best_correlation = f64::MIN;
+    for lag in min_lag..(max_lag + 1) {
+        // reference[n] lines up with other[n - lag]
+        let start = i64::max(0, lag) as usize;
+        let end = i64::min(reference.len() as i64, other.len() as i64 + lag) as usize;
+
+        let mut correlation = 0.0;
+        for n in start..end {
+            correlation += reference[n] * other[(n as i64 - lag) as usize];
+        }
+        if correlation > best_correlation {
+            best_correlation = correlation;
+            best_lag = lag;
+        }
+    }
+    best_lag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_offset;
+
+    fn signal(length: usize) -> Vec<f64> {
+        (0..length)
+            .map(|i| f64::sin(i as f64 * 0.37) * f64::cos(i as f64 * 0.11) + (i % 7) as f64 * 0.1)
+            .collect()
+    }
+
+    #[test]
+    fn test_find_offset_later() {
+        let reference = signal(500);
+        let other = reference[40..].to_vec(); // other starts 40 windows into the reference
+        assert_eq!(find_offset(&other, &reference, 100), -40);
+        assert_eq!(find_offset(&reference, &other, 100), 40);
+    }
+
+    #[test]
+    fn test_find_offset_same() {
+        let reference = signal(300);
+        assert_eq!(find_offset(&reference, &reference.clone(), 100), 0);
+    }
+}
@@ -0,0 +1,491 @@
+use std::collections::HashMap;
+
+use ges::traits::{GESContainerExt, LayerExt, TimelineExt, TrackElementExt};
+use gst_controller::prelude::*;
+use serde_json::Value;
+
+use crate::{
+    constants::AUDIO_ANALYSIS_WINDOW,
+    error::{PipelineError, PipelineErrorKind},
+    node::{
+        InputOrOutput, MemorySafetyWrapper, Node, NodeCategory, NodeContext, NodeIo, NodeKind,
+        NodeTimelines, NodeType, NodeTypeInput, NodeTypeOutput, PipeableType, PipedType,
+        Restrictions, StreamFormat, Type,
+    },
+    store::Store,
+};
+
+use super::{
+    get_input_key, get_number_property, number_input,
+    silence_removal_node::{format_cuts, parse_cuts, MAX_CUTS_LENGTH},
+    ANALYSIS_KEY,
+};
+
+pub const IDENTIFIER: &str = "ducking";
+pub mod inputs {
+    pub const MAIN: &str = "main";
+    pub const SIDECHAIN: &str = "sidechain";
+    pub const THRESHOLD: &str = "threshold";
+    pub const DEPTH: &str = "depth";
+    pub const ATTACK: &str = "attack";
+    pub const RELEASE: &str = "release";
+    pub const DUCKED_RANGES: &str = "ducked_ranges";
+}
+pub mod outputs {
+    pub const OUTPUT: &str = "output";
+}
+
+const DEFAULT_THRESHOLD: f64 = -30.0;
+const DEFAULT_DEPTH: f64 = 12.0;
+const DEFAULT_ATTACK: f64 = 0.1;
+const DEFAULT_RELEASE: f64 = 0.5;
+const MIN_GAP: u64 = 500; // Loud ranges closer together than this are merged, so pauses between words don't each get ducked separately, in milliseconds
+
+pub struct DuckingNode;
+
+impl NodeKind for DuckingNode {
+    fn default_properties(&self) -> HashMap<String, NodeTypeInput> {
+        let mut default_properties = HashMap::new();
+
+        default_properties.insert(
+            String::from(inputs::MAIN),
+            NodeTypeInput {
+                name: String::from(inputs::MAIN),
+                display_name: String::from("Main"),
+                description: String::from("The media whose volume is lowered, such as music"),
+                property_type: Type::Pipeable(
+                    PipeableType {
+                        video: 0,
+                        audio: 1,
+                        subtitles: 0,
+                    },
+                    PipeableType {
+                        video: i32::MAX,
+                        audio: i32::MAX,
+                        subtitles: i32::MAX,
+                    },
+                ),
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::SIDECHAIN),
+            NodeTypeInput {
+                name: String::from(inputs::SIDECHAIN),
+                display_name: String::from("Sidechain"),
+                description: String::from(
+                    "The media which triggers the ducking when it is loud, such as narration",
+                ),
+                property_type: Type::Pipeable(
+                    PipeableType {
+                        video: 0,
+                        audio: 1,
+                        subtitles: 0,
+                    },
+                    PipeableType {
+                        video: i32::MAX,
+                        audio: i32::MAX,
+                        subtitles: i32::MAX,
+                    },
+                ),
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::THRESHOLD),
+            number_input(
+                inputs::THRESHOLD,
+                "Threshold",
+                "The main input is lowered while the sidechain is louder than this level",
+                Restrictions {
+                    min: -100.0,
+                    max: 0.0,
+                    step: 1.0,
+                    default: DEFAULT_THRESHOLD,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::DEPTH),
+            number_input(
+                inputs::DEPTH,
+                "Depth",
+                "How much the main input is lowered by",
+                Restrictions {
+                    min: 0.0,
+                    max: 60.0,
+                    step: 1.0,
+                    default: DEFAULT_DEPTH,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::ATTACK),
+            number_input(
+                inputs::ATTACK,
+                "Attack",
+                "How long the main input takes to fade down, in seconds",
+                Restrictions {
+                    min: 0.0,
+                    max: 5.0,
+                    step: 0.01,
+                    default: DEFAULT_ATTACK,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::RELEASE),
+            number_input(
+                inputs::RELEASE,
+                "Release",
+                "How long the main input takes to fade back up, in seconds",
+                Restrictions {
+                    min: 0.0,
+                    max: 10.0,
+                    step: 0.01,
+                    default: DEFAULT_RELEASE,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::DUCKED_RANGES),
+            NodeTypeInput {
+                name: String::from(inputs::DUCKED_RANGES),
+                display_name: String::from("Ducked Ranges"),
+                description: String::from(
+                    "The ranges where the main input is lowered, in seconds (e.g. 1.2-2.5, 5-6.1). These are detected from the sidechain automatically, but can be edited",
+                ),
+                property_type: Type::String(MAX_CUTS_LENGTH),
+            },
+        );
+
+        default_properties
+    }
+
+    fn get_io(&self, context: &NodeContext) -> Result<NodeIo, PipelineError> {
+        let NodeContext { piped_inputs, .. } = *context;
+        let inputs = self.default_properties();
+        let mut outputs = HashMap::new();
+        let mut stream_type = PipeableType {
+            video: i32::MAX,
+            audio: i32::MAX,
+            subtitles: i32::MAX,
+        };
+        let mut format = StreamFormat::default();
+        if let Some(piped_input) = piped_inputs.get(inputs::MAIN) {
+            stream_type = piped_input.stream_type;
+            format = piped_input.format;
+        }
+        outputs.insert(
+            outputs::OUTPUT.to_string(),
+            NodeTypeOutput {
+                name: outputs::OUTPUT.to_string(),
+                description: "The main input, lowered while the sidechain is active".to_string(),
+                display_name: "Output".to_string(),
+                property_type: stream_type,
+                format,
+                number_stream: None,
+            },
+        );
+
+        return Ok((inputs, outputs));
+    }
+
+    fn get_output(&self, context: &NodeContext) -> Result<NodeTimelines, PipelineError> {
+        let NodeContext {
+            node_id,
+            properties,
+            piped_inputs,
+            ..
+        } = *context;
+        let io = self.get_io(context);
+        if io.is_err() {
+            return Err(io.unwrap_err());
+        }
+        let (_, outputs) = io.unwrap();
+
+        let main = piped_inputs.get(inputs::MAIN);
+        if main.is_none() {
+            return Err(PipelineError::missing_input(inputs::MAIN));
+        }
+        let main = main.unwrap();
+        if piped_inputs.get(inputs::SIDECHAIN).is_none() {
+            return Err(PipelineError::missing_input(inputs::SIDECHAIN));
+        }
+
+        // Until the server has analysed the sidechain, nothing is ducked
+        let ducked_ranges = match properties.get(inputs::DUCKED_RANGES) {
+            Some(Value::String(ranges)) => parse_cuts(ranges)?,
+            _ => Vec::new(),
+        };
+        let gain_points = get_gain_points(
+            &ducked_ranges,
+            get_number_property(properties, inputs::DEPTH, DEFAULT_DEPTH),
+            (get_number_property(properties, inputs::ATTACK, DEFAULT_ATTACK) * 1000.0) as u64,
+            (get_number_property(properties, inputs::RELEASE, DEFAULT_RELEASE) * 1000.0) as u64,
+        );
+
+        let output = outputs.get(outputs::OUTPUT).unwrap();
+        let output = PipedType {
+            stream_type: output.property_type,
+            node_id,
+            property_name: String::from(outputs::OUTPUT),
+            io: InputOrOutput::Output,
+            cache_id: None,
+            format: output.format,
+        };
+
+        let effect = ges::Effect::new("volume")?;
+        let timeline = output.create_timeline();
+        let layer = timeline.append_layer();
+        let clip = ges::UriClip::new(main.get_gst_save_location_with_cache().as_str())?;
+
+        clip.add(&effect)?;
+        layer.add_clip(&clip)?;
+
+        let control_source = gst_controller::InterpolationControlSource::new();
+        control_source.set_mode(gst_controller::InterpolationMode::Linear);
+        for (time, gain) in gain_points {
+            control_source.set(gst::ClockTime::from_mseconds(time), gain);
+        }
+        if !effect.set_control_source(&control_source, "volume", "direct-absolute") {
+            return Err(PipelineError::new(
+                PipelineErrorKind::Ges,
+                String::from("Could not control the volume of the main input"),
+            ));
+        }
+
+        let mut hm = HashMap::new();
+        hm.insert(outputs::OUTPUT.to_string(), timeline);
+        Ok((
+            hm,
+            vec![
+                MemorySafetyWrapper::UriClip(clip),
+                MemorySafetyWrapper::Effect(effect),
+            ],
+        ))
+    }
+}
+
+pub fn ducking_node() -> NodeType {
+    NodeType::new(
+        IDENTIFIER,
+        "Ducking",
+        "Automatically lower the volume of one input (e.g. music) while another (e.g. narration) is loud",
+        NodeCategory::Audio,
+        DuckingNode,
+    )
+    .with_tags(&["sidechain", "compressor", "music", "voice"])
+    .with_units(inputs::THRESHOLD, "dB")
+    .with_units(inputs::DEPTH, "dB")
+    .with_units(inputs::ATTACK, "s")
+    .with_units(inputs::RELEASE, "s")
+}
+
+/**
+ * Gets the sidechain media that needs its audio analysing, if the ducked ranges are out of date. The returned key should be stored alongside the results of the analysis
+ *
+ * Only the sidechain and the threshold affect the ducked ranges, so the depth, attack and release can be changed without analysing the sidechain again
+ */
+pub fn get_analysis_request(
+    node: &Node,
+    piped_inputs: &HashMap<String, PipedType>,
+    store: &Store,
+) -> Option<(String, Vec<PipedType>)> {
+    let piped_input = piped_inputs.get(inputs::SIDECHAIN);
+    let input_key = get_input_key(node, inputs::SIDECHAIN, store);
+    if piped_input.is_none() || input_key.is_none() {
+        return None;
+    }
+
+    let key = format!(
+        "{}:{}",
+        input_key.unwrap(),
+        get_number_property(&node.properties, inputs::THRESHOLD, DEFAULT_THRESHOLD)
+    );
+
+    let existing_key = node.properties.get(ANALYSIS_KEY);
+    let up_to_date = match existing_key {
+        Some(Value::String(existing_key)) => *existing_key == key,
+        _ => false,
+    };
+    if up_to_date && node.properties.contains_key(inputs::DUCKED_RANGES) {
+        return None;
+    }
+    Some((key, vec![piped_input.unwrap().clone()]))
+}
+
+/**
+ * Finds where the sidechain is loud from its audio levels (one level per `AUDIO_ANALYSIS_WINDOW`, in dB), returning the properties to update
+ */
+pub fn analyse(
+    properties: &HashMap<String, Value>,
+    levels: &Vec<Vec<f64>>,
+) -> Result<HashMap<String, Value>, String> {
+    if levels.is_empty() {
+        return Err(String::from("No audio to analyse"));
+    }
+    let threshold = get_number_property(properties, inputs::THRESHOLD, DEFAULT_THRESHOLD);
+
+    let ranges = detect_loud_ranges(&levels[0], threshold);
+    let formatted_ranges = format_cuts(&ranges);
+    if formatted_ranges.chars().count() > MAX_CUTS_LENGTH as usize {
+        return Err(format!(
+            "Found too many loud ranges to store ({}); try a higher threshold",
+            ranges.len()
+        ));
+    }
+
+    let mut properties = HashMap::new();
+    properties.insert(
+        String::from(inputs::DUCKED_RANGES),
+        Value::String(formatted_ranges),
+    );
+    Ok(properties)
+}
+
+/**
+ * Finds the ranges (in milliseconds) where the level is at or above the threshold; ranges separated by less than `MIN_GAP` are merged into one
+ */
+pub fn detect_loud_ranges(levels: &Vec<f64>, threshold: f64) -> Vec<(u64, u64)> {
+    let mut ranges: Vec<(u64, u64)> = Vec::new();
+    let mut range_start = None;
+    for i in 0..(levels.len() + 1) {
+        let is_loud = i < levels.len() && levels[i] >= threshold;
+        match (is_loud, range_start) {
+            (true, None) => range_start = Some(i),
+            (false, Some(start)) => {
+                let start = start as u64 * AUDIO_ANALYSIS_WINDOW;
+                let end = i as u64 * AUDIO_ANALYSIS_WINDOW;
+                match ranges.last_mut() {
+                    Some(last) if start - last.1 < MIN_GAP => last.1 = end,
+                    _ => ranges.push((start, end)),
+                }
+                range_start = None;
+            }
+            _ => {}
+        }
+    }
+    ranges
+}
+
+/**
+ * Gets the volume envelope of the main input as a list of (time in milliseconds, gain multiplier) points to interpolate between.
+ * The volume starts fading down `attack` before each ducked range, and fades back up over `release` after it; ranges which are too close together for the volume to recover in between are merged
+ */
+pub fn get_gain_points(
+    ducked_ranges: &Vec<(u64, u64)>,
+    depth: f64,
+    attack: u64,
+    release: u64,
+) -> Vec<(u64, f64)> {
+    let mut ducked_ranges = ducked_ranges.clone();
+    ducked_ranges.sort();
+
+    let mut merged: Vec<(u64, u64)> = Vec::new();
+    for (start, end) in ducked_ranges {
+        match merged.last_mut() {
+            Some(last) if start.saturating_sub(attack) <= last.1 + release => {
+                last.1 = u64::max(last.1, end);
+            }
+            _ => merged.push((start, end)),
+        }
+    }
+
+    let gain = f64::powf(10.0, -depth / 20.0);
+    let mut points: Vec<(u64, f64)> = vec![(0, 1.0)];
+    let mut add_point = |time: u64, value: f64| match points.last_mut() {
+        Some(last) if last.0 == time => last.1 = value,
+        _ => points.push((time, value)),
+    };
+    for (start, end) in merged {
+        add_point(start.saturating_sub(attack), 1.0);
+        add_point(start, gain);
+        add_point(end, gain);
+        add_point(end + release, 1.0);
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{analyse, detect_loud_ranges, get_gain_points, MIN_GAP};
+    use crate::constants::AUDIO_ANALYSIS_WINDOW;
+
+    #[test]
+    fn test_detect_loud_ranges() {
+        let mut levels = vec![-60.0; 200];
+        for i in 0..10 {
+            levels[i] = -10.0;
+        }
+        for i in 100..200 {
+            levels[i] = -20.0;
+        }
+        assert_eq!(
+            detect_loud_ranges(&levels, -30.0),
+            vec![(0, 100), (1000, 2000)]
+        );
+
+        // short dips below the threshold are bridged
+        levels[150] = -60.0;
+        levels[151] = -60.0;
+        assert_eq!(
+            detect_loud_ranges(&levels, -30.0),
+            vec![(0, 100), (1000, 2000)]
+        );
+    }
+
+    #[test]
+    fn test_noisy_envelope() {
+        // an hour of audio hovering around the threshold, crossing it every other window
+        let levels: Vec<f64> = (0..360000)
+            .map(|i| if i % 2 == 0 { -29.0 } else { -31.0 })
+            .collect();
+        let ranges = detect_loud_ranges(&levels, -30.0);
+        assert_eq!(ranges, vec![(0, 360000 * AUDIO_ANALYSIS_WINDOW - 10)]);
+        assert!(analyse(&HashMap::new(), &vec![levels]).is_ok());
+
+        // isolated spikes further apart than the gap are kept separate, until there are too many to store
+        let spacing = (MIN_GAP / AUDIO_ANALYSIS_WINDOW) as usize + 1;
+        let levels: Vec<f64> = (0..360000)
+            .map(|i| if i % spacing == 0 { -10.0 } else { -60.0 })
+            .collect();
+        assert_eq!(
+            detect_loud_ranges(&levels, -30.0).len(),
+            (360000 + spacing - 1) / spacing
+        );
+        assert!(analyse(&HashMap::new(), &vec![levels]).is_err());
+    }
+
+    #[test]
+    fn test_gain_points() {
+        // -20dB is a gain of 0.1; the last two ranges are close enough to be merged
+        let points = get_gain_points(
+            &vec![(3000, 4000), (1000, 2000), (4100, 5000)],
+            20.0,
+            200,
+            500,
+        );
+        let expected = vec![
+            (0, 1.0),
+            (800, 1.0),
+            (1000, 0.1),
+            (2000, 0.1),
+            (2500, 1.0),
+            (2800, 1.0),
+            (3000, 0.1),
+            (5000, 0.1),
+            (5500, 1.0),
+        ];
+        assert_eq!(points.len(), expected.len());
+        for ((time, gain), (expected_time, expected_gain)) in points.iter().zip(expected) {
+            assert_eq!(*time, expected_time);
+            assert!((gain - expected_gain).abs() < 1e-9);
+        }
+
+        // a range at the very start is ducked immediately
+        let points = get_gain_points(&vec![(0, 1000)], 20.0, 200, 500);
+        assert_eq!(points[0].0, 0);
+        assert!((points[0].1 - 0.1).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,391 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+};
+
+use crate::{
+    error::{PipelineError, PipelineErrorKind},
+    keyframes::AnimatedNumber,
+    node::{
+        NodeCategory, NodeContext, NodeIo, NodeKind, NodeTimelines, NodeType, NodeTypeInput,
+        PipeableType, Restrictions, Type,
+    },
+};
+
+use super::number_input;
+
+pub mod inputs {
+    pub const MEDIA: &str = "media";
+}
+pub mod outputs {
+    pub const OUTPUT: &str = "output";
+}
+
+/**
+ * A kind of stream an effect changes; streams of other kinds pass through unchanged
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EffectTrackType {
+    Video,
+    Audio,
+}
+
+/**
+ * A numeric property of an effect node, which sets a property of the effect's element
+ */
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EffectProperty {
+    pub name: String,
+    pub display_name: String,
+    #[serde(default)]
+    pub description: String,
+    /// The name of the property on the GStreamer element
+    pub element_property: String,
+    pub restrictions: Restrictions,
+    /// Whether the element property only takes whole numbers, so values are rounded before being set
+    #[serde(default)]
+    pub integer: bool,
+    #[serde(default)]
+    pub units: Option<String>,
+    /// A longer explanation of the property than its description
+    #[serde(default)]
+    pub help: Option<String>,
+}
+
+/**
+ * The definition of a node type which applies one GStreamer element to its media input, loaded from a JSON file such as:
+ * `{"id": "sepia", "display_name": "Sepia", "element": "coloreffects preset=sepia", "track_types": ["video"], "properties": []}`
+ */
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EffectDefinition {
+    pub id: String,
+    pub display_name: String,
+    #[serde(default)]
+    pub description: String,
+    /// The element, in `gst-launch` syntax; the properties are set on the last element given
+    pub element: String,
+    /// The kind of stream the element is applied to, which the media input must have. GES applies the element to only one kind of stream, so only one may be given; an effect which changes both video and audio needs a definition for each
+    pub track_types: Vec<EffectTrackType>,
+    #[serde(default)]
+    pub properties: Vec<EffectProperty>,
+    /// The palette category; if not given, it is chosen from the track types
+    #[serde(default)]
+    pub category: Option<NodeCategory>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+impl EffectDefinition {
+    /**
+     * Checks that the definition describes a usable node type
+     */
+    pub fn validate(&self) -> Result<(), String> {
+        if self.id.trim().is_empty() {
+            return Err(String::from("The id must not be empty"));
+        }
+        if self.element.trim().is_empty() {
+            return Err(String::from("The element must not be empty"));
+        }
+        if self.track_types.is_empty() {
+            return Err(String::from("At least one track type must be given"));
+        }
+        if self.track_types.contains(&EffectTrackType::Video)
+            && self.track_types.contains(&EffectTrackType::Audio)
+        {
+            return Err(String::from(
+                "The element can only be applied to one kind of stream, so video and audio need separate definitions",
+            ));
+        }
+
+        let mut names = HashSet::new();
+        for property in &self.properties {
+            if property.name == inputs::MEDIA {
+                return Err(format!("The property name {} is reserved", inputs::MEDIA));
+            }
+            if !names.insert(property.name.as_str()) {
+                return Err(format!("The property {} is defined twice", property.name));
+            }
+            let restrictions = property.restrictions;
+            if restrictions.min > restrictions.max
+                || restrictions.default < restrictions.min
+                || restrictions.default > restrictions.max
+                || restrictions.step <= 0.0
+            {
+                return Err(format!(
+                    "The restrictions of property {} are invalid",
+                    property.name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * Gets the streams the media input must have, which is at least one of each kind of stream the effect changes
+     */
+    pub fn input_type(&self) -> PipeableType {
+        PipeableType {
+            video: self.track_types.contains(&EffectTrackType::Video) as i32,
+            audio: self.track_types.contains(&EffectTrackType::Audio) as i32,
+            subtitles: 0,
+        }
+    }
+
+    /**
+     * Gets the palette category of the node type; unless one is given, effects are listed under the kind of stream they change
+     */
+    pub fn category(&self) -> NodeCategory {
+        match self.category {
+            Some(category) => category,
+            None if self.track_types.contains(&EffectTrackType::Audio) => NodeCategory::Audio,
+            None => NodeCategory::Video,
+        }
+    }
+
+    /**
+     * Gets the description of the element with each property set to its value, or its default if it has no value
+     */
+    pub fn get_element_description(&self, values: &HashMap<String, f64>) -> String {
+        let mut description = self.element.trim().to_string();
+        for property in &self.properties {
+            let value = values
+                .get(&property.name)
+                .cloned()
+                .unwrap_or(property.restrictions.default);
+            let value = if property.integer {
+                (value.round() as i64).to_string()
+            } else {
+                value.to_string()
+            };
+            description = format!("{} {}={}", description, property.element_property, value);
+        }
+        description
+    }
+}
+
+pub struct EffectNode {
+    definition: EffectDefinition,
+}
+
+impl EffectNode {
+    pub fn new(definition: EffectDefinition) -> Result<Self, String> {
+        definition.validate()?;
+        Ok(Self { definition })
+    }
+}
+
+impl NodeKind for EffectNode {
+    fn default_properties(&self) -> HashMap<String, NodeTypeInput> {
+        let mut default_properties = HashMap::new();
+        default_properties.insert(
+            String::from(inputs::MEDIA),
+            NodeTypeInput {
+                name: String::from(inputs::MEDIA),
+                display_name: String::from("Media"),
+                description: String::from("The media to apply the effect to"),
+                property_type: Type::Pipeable(
+                    self.definition.input_type(),
+                    PipeableType {
+                        video: i32::MAX,
+                        audio: i32::MAX,
+                        subtitles: i32::MAX,
+                    },
+                ),
+            },
+        );
+        for property in &self.definition.properties {
+            default_properties.insert(
+                property.name.clone(),
+                number_input(
+                    &property.name,
+                    &property.display_name,
+                    &property.description,
+                    property.restrictions,
+                ),
+            );
+        }
+        default_properties
+    }
+
+    fn get_io(&self, context: &NodeContext) -> Result<NodeIo, PipelineError> {
+        self.single_media_io(
+            context,
+            inputs::MEDIA,
+            outputs::OUTPUT,
+            "The media with the effect applied",
+            self.definition.input_type(),
+        )
+    }
+
+    fn get_output(&self, context: &NodeContext) -> Result<NodeTimelines, PipelineError> {
+        let mut values = HashMap::new();
+        for property in &self.definition.properties {
+            let value = AnimatedNumber::from_property(
+                context.properties,
+                &property.name,
+                property.restrictions.default,
+            )?;
+            values.insert(property.name.clone(), value);
+        }
+        let initial_values: HashMap<String, f64> = values
+            .iter()
+            .map(|(name, value)| (name.clone(), value.initial_value()))
+            .collect();
+
+        let effect = ges::Effect::new(
+            self.definition
+                .get_element_description(&initial_values)
+                .as_str(),
+        );
+        if effect.is_err() {
+            return Err(PipelineError::new(
+                PipelineErrorKind::Ges,
+                format!(
+                    "Could not create the element {}: {}",
+                    self.definition.element,
+                    effect.unwrap_err()
+                ),
+            ));
+        }
+        let effect = effect.unwrap();
+
+        let output = self.single_effect_output(context, inputs::MEDIA, outputs::OUTPUT, &effect)?;
+        for property in &self.definition.properties {
+            values[&property.name].bind_to_element(&effect, &property.element_property)?;
+        }
+        Ok(output)
+    }
+}
+
+pub fn effect_node(definition: EffectDefinition) -> Result<NodeType, String> {
+    let id = definition.id.clone();
+    let display_name = definition.display_name.clone();
+    let description = definition.description.clone();
+    let category = definition.category();
+    let tags: Vec<&str> = definition.tags.iter().map(|tag| tag.as_str()).collect();
+    let mut node_type = NodeType::new(
+        &id,
+        &display_name,
+        &description,
+        category,
+        EffectNode::new(definition.clone())?,
+    )
+    .with_tags(&tags);
+    if let Some(version) = &definition.version {
+        node_type = node_type.with_version(version);
+    }
+    for property in &definition.properties {
+        // every property of an effect is given to its element through a control source
+        node_type = node_type.with_animatable(&[&property.name]);
+        if let Some(units) = &property.units {
+            node_type = node_type.with_units(&property.name, units);
+        }
+        if let Some(help) = &property.help {
+            node_type = node_type.with_help(&property.name, help);
+        }
+    }
+    Ok(node_type)
+}
+
+/**
+ * Loads an effect definition from each `.json` file in a directory, in order of file name; the errors for files which cannot be loaded include the file's path
+ * If the directory does not exist, there are no definitions to load
+ */
+pub fn load_effect_definitions(directory: &str) -> Vec<Result<EffectDefinition, String>> {
+    let entries = fs::read_dir(directory);
+    if entries.is_err() {
+        return Vec::new();
+    }
+    let mut paths: Vec<PathBuf> = entries
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .map_or(false, |extension| extension == "json")
+        })
+        .collect();
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let definition = fs::read_to_string(path)
+                .map_err(|error| error.to_string())
+                .and_then(|text| {
+                    serde_json::from_str::<EffectDefinition>(&text)
+                        .map_err(|error| error.to_string())
+                })
+                .and_then(|definition| definition.validate().map(|_| definition));
+            definition.map_err(|error| format!("{}: {}", path.display(), error))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::{EffectDefinition, EffectTrackType};
+    use crate::node::NodeCategory;
+
+    #[test]
+    fn test_effect_definition() {
+        let definition = serde_json::from_value::<EffectDefinition>(json!({
+            "id": "pixelate",
+            "display_name": "Pixelate",
+            "element": "videoscale method=0 ",
+            "track_types": ["video"],
+            "properties": [
+                {
+                    "name": "size",
+                    "display_name": "Size",
+                    "element_property": "sharpness",
+                    "restrictions": {"min": 0.5, "max": 1.5, "step": 0.1, "default": 1.0}
+                },
+                {
+                    "name": "taps",
+                    "display_name": "Taps",
+                    "element_property": "envelope",
+                    "restrictions": {"min": 1.0, "max": 5.0, "step": 1.0, "default": 2.0},
+                    "integer": true
+                }
+            ]
+        }))
+        .unwrap();
+        assert!(definition.validate().is_ok());
+        assert_eq!(definition.category(), NodeCategory::Video);
+        assert_eq!(definition.input_type().video, 1);
+        assert_eq!(definition.input_type().audio, 0);
+
+        let mut values = HashMap::new();
+        values.insert(String::from("taps"), 3.4);
+        assert_eq!(
+            definition.get_element_description(&values),
+            "videoscale method=0 sharpness=1 envelope=3"
+        );
+
+        let mut invalid = definition.clone();
+        invalid.properties[1].name = String::from("size");
+        assert!(invalid.validate().is_err());
+        let mut invalid = definition.clone();
+        invalid.properties[0].restrictions.default = 2.0;
+        assert!(invalid.validate().is_err());
+        let mut both = definition.clone();
+        both.track_types.push(EffectTrackType::Audio);
+        assert!(both.validate().is_err());
+        let mut categorised = definition.clone();
+        categorised.category = Some(NodeCategory::Generator);
+        assert_eq!(categorised.category(), NodeCategory::Generator);
+
+        let mut invalid = definition;
+        invalid.track_types.clear();
+        assert!(invalid.validate().is_err());
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::{
+    error::PipelineError,
+    expression::Expression,
+    keyframes::{AnimatedNumber, Interpolation, Keyframe},
+    node::{
+        NodeCategory, NodeContext, NodeIo, NodeKind, NodeTimelines, NodeType, NodeTypeInput,
+        NodeTypeOutput, PipeableType, Restrictions, StreamFormat, Type,
+    },
+};
+
+use super::{get_number_property, number_input};
+
+pub const IDENTIFIER: &str = "expression";
+pub mod inputs {
+    pub const A: &str = "a";
+    pub const B: &str = "b";
+    pub const C: &str = "c";
+    pub const EXPRESSION: &str = "expression";
+    pub const DURATION: &str = "duration";
+}
+pub mod outputs {
+    pub const VALUE: &str = "value";
+}
+
+/// The variable holding the time, in seconds
+const TIME: &str = "t";
+const DEFAULT_EXPRESSION: &str = "a";
+const DEFAULT_DURATION: f64 = 10.0;
+/// How many times per second the expression is evaluated, if its value changes over time
+const SAMPLE_RATE: f64 = 30.0;
+
+pub struct ExpressionNode;
+
+impl NodeKind for ExpressionNode {
+    fn default_properties(&self) -> HashMap<String, NodeTypeInput> {
+        let mut default_properties = HashMap::new();
+
+        for (name, display_name) in [(inputs::A, "A"), (inputs::B, "B"), (inputs::C, "C")] {
+            default_properties.insert(
+                String::from(name),
+                number_input(
+                    name,
+                    display_name,
+                    &format!(
+                        "The value of {} in the expression; link a number stream to use a changing value",
+                        name
+                    ),
+                    Restrictions {
+                        min: -1000000.0,
+                        max: 1000000.0,
+                        step: 0.01,
+                        default: 0.0,
+                    },
+                ),
+            );
+        }
+        default_properties.insert(
+            String::from(inputs::EXPRESSION),
+            NodeTypeInput {
+                name: String::from(inputs::EXPRESSION),
+                display_name: String::from("Expression"),
+                description: String::from(
+                    "The value to output, in terms of a, b, c and the time in seconds t (e.g. a * 0.5 + sin(t)). Supports + - * / % ^, brackets, pi, e, sin, cos, tan, abs, sqrt, exp, ln, floor, ceil, round, min, max and clamp",
+                ),
+                property_type: Type::String(256),
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::DURATION),
+            number_input(
+                inputs::DURATION,
+                "Duration",
+                "How long the expression is evaluated for, in seconds, if its value changes over time; afterwards, the last value is held",
+                Restrictions {
+                    min: 0.1,
+                    max: 600.0,
+                    step: 0.1,
+                    default: DEFAULT_DURATION,
+                },
+            ),
+        );
+
+        default_properties
+    }
+
+    fn get_io(&self, context: &NodeContext) -> Result<NodeIo, PipelineError> {
+        let NodeContext { properties, .. } = *context;
+        let inputs = self.default_properties();
+        let mut outputs = HashMap::new();
+
+        let value = evaluate(properties);
+        if value.is_err() {
+            return Err(PipelineError::from(value.unwrap_err()).with_property(inputs::EXPRESSION));
+        }
+
+        outputs.insert(
+            outputs::VALUE.to_string(),
+            NodeTypeOutput {
+                name: outputs::VALUE.to_string(),
+                description: "The value of the expression over time".to_string(),
+                display_name: "Value".to_string(),
+                property_type: PipeableType {
+                    video: 0,
+                    audio: 0,
+                    subtitles: 0,
+                },
+                format: StreamFormat::default(),
+                number_stream: Some(value.unwrap()),
+            },
+        );
+
+        return Ok((inputs, outputs));
+    }
+
+    fn get_output(&self, context: &NodeContext) -> Result<NodeTimelines, PipelineError> {
+        let io = self.get_io(context);
+        if io.is_err() {
+            return Err(io.unwrap_err());
+        }
+
+        // the number stream is passed on directly through the outputs, so there are no timelines
+        Ok((HashMap::new(), Vec::new()))
+    }
+}
+
+pub fn expression_node() -> NodeType {
+    NodeType::new(
+        IDENTIFIER,
+        "Expression",
+        "Combine numbers (or number streams) using a mathematical expression",
+        NodeCategory::Generator,
+        ExpressionNode,
+    )
+    .with_tags(&["math", "formula", "number", "calculate"])
+    .with_animatable(&[inputs::A, inputs::B, inputs::C])
+    .with_units(inputs::DURATION, "s")
+    .with_help(
+        inputs::EXPRESSION,
+        "Supports + - * / % ^, brackets, the inputs a, b and c, the time t in seconds, and functions such as sin, cos, abs, min and max",
+    )
+}
+
+/**
+ * Parses the node's expression and evaluates it with the node's inputs
+ */
+fn evaluate(properties: &HashMap<String, Value>) -> Result<AnimatedNumber, String> {
+    let text = match properties.get(inputs::EXPRESSION) {
+        Some(Value::String(text)) if !text.trim().is_empty() => text.clone(),
+        _ => String::from(DEFAULT_EXPRESSION),
+    };
+    let expression = Expression::parse(&text, &[inputs::A, inputs::B, inputs::C, TIME]);
+    if expression.is_err() {
+        return Err(format!("Invalid expression: {}", expression.unwrap_err()));
+    }
+
+    let mut variables = HashMap::new();
+    for name in [inputs::A, inputs::B, inputs::C] {
+        variables.insert(name, AnimatedNumber::from_property(properties, name, 0.0)?);
+    }
+    evaluate_expression(
+        &expression.unwrap(),
+        &variables,
+        get_number_property(properties, inputs::DURATION, DEFAULT_DURATION),
+    )
+}
+
+/**
+ * Evaluates an expression with values which may change over time.
+ * If nothing it depends on changes, the result is a constant; otherwise, it is sampled `SAMPLE_RATE` times a second for `duration` seconds
+ */
+pub fn evaluate_expression(
+    expression: &Expression,
+    variables: &HashMap<&str, AnimatedNumber>,
+    duration: f64,
+) -> Result<AnimatedNumber, String> {
+    let is_constant = !expression.uses_variable(TIME)
+        && variables
+            .iter()
+            .all(|(name, value)| value.is_constant() || !expression.uses_variable(name));
+
+    let value_at = |time: f64| {
+        let mut values = HashMap::new();
+        values.insert(TIME, time);
+        for (name, value) in variables {
+            values.insert(*name, value.value_at(time));
+        }
+        let value = expression.evaluate(&values);
+        if !value.is_finite() {
+            return Err(format!(
+                "The expression has no value at {}s (e.g. it divides by 0)",
+                time
+            ));
+        }
+        Ok(value)
+    };
+
+    if is_constant {
+        return Ok(AnimatedNumber::Constant(value_at(0.0)?));
+    }
+
+    let samples = (duration * SAMPLE_RATE).ceil() as u64;
+    let mut keyframes = Vec::new();
+    for i in 0..(samples + 1) {
+        let time = f64::min(i as f64 / SAMPLE_RATE, duration);
+        keyframes.push(Keyframe {
+            time,
+            value: value_at(time)?,
+            interpolation: Interpolation::Linear,
+        });
+    }
+    Ok(AnimatedNumber::Keyframed { keyframes })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        expression::Expression,
+        keyframes::{AnimatedNumber, Interpolation, Keyframe},
+    };
+
+    use super::evaluate_expression;
+
+    #[test]
+    fn test_evaluate_expression() {
+        let mut variables = HashMap::new();
+        variables.insert("a", AnimatedNumber::Constant(2.0));
+        variables.insert(
+            "b",
+            AnimatedNumber::Keyframed {
+                keyframes: vec![
+                    Keyframe {
+                        time: 0.0,
+                        value: 0.0,
+                        interpolation: Interpolation::Linear,
+                    },
+                    Keyframe {
+                        time: 1.0,
+                        value: 3.0,
+                        interpolation: Interpolation::Linear,
+                    },
+                ],
+            },
+        );
+        let parse = |text: &str| Expression::parse(text, &["a", "b", "t"]).unwrap();
+
+        assert_eq!(
+            evaluate_expression(&parse("a * 3"), &variables, 1.0).unwrap(),
+            AnimatedNumber::Constant(6.0)
+        );
+
+        let value = evaluate_expression(&parse("a + b"), &variables, 1.0).unwrap();
+        assert!(!value.is_constant());
+        assert_eq!(value.initial_value(), 2.0);
+        assert_eq!(value.value_at(1.0), 5.0);
+        assert!((value.value_at(0.5) - 3.5).abs() < 1e-9);
+
+        let value = evaluate_expression(&parse("t * 2"), &variables, 0.5).unwrap();
+        assert_eq!(value.value_at(0.5), 1.0);
+        assert_eq!(value.value_at(10.0), 1.0);
+
+        assert!(evaluate_expression(&parse("a / (b - 3)"), &variables, 1.0).is_err());
+    }
+}
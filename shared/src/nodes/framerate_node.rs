@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+
+use ges::traits::{GESContainerExt, LayerExt, TimelineExt};
+use serde_json::Value;
+
+use crate::{
+    error::PipelineError,
+    node::{
+        InputOrOutput, MemorySafetyWrapper, NodeCategory, NodeContext, NodeIo, NodeKind,
+        NodeTimelines, NodeType, NodeTypeInput, NodeTypeOutput, PipeableType, PipedType,
+        Restrictions, StreamFormat, Type,
+    },
+};
+
+use super::{get_bool_property, get_number_property};
+
+pub const IDENTIFIER: &str = "framerate";
+pub mod inputs {
+    pub const MEDIA: &str = "media";
+    pub const FRAMERATE: &str = "framerate";
+    pub const BLEND: &str = "blend";
+    pub const DEINTERLACE: &str = "deinterlace";
+}
+pub mod outputs {
+    pub const OUTPUT: &str = "output";
+}
+
+const DEFAULT_FRAMERATE: f64 = 30.0;
+
+/// The property which chose how frames were converted before it became the `BLEND` boolean (0 dropped or duplicated frames, 1 blended them)
+const OLD_MODE: &str = "mode";
+
+pub struct FramerateNode;
+
+impl NodeKind for FramerateNode {
+    fn default_properties(&self) -> HashMap<String, NodeTypeInput> {
+        let mut default_properties = HashMap::new();
+
+        default_properties.insert(
+            String::from(inputs::MEDIA),
+            NodeTypeInput {
+                name: String::from(inputs::MEDIA),
+                display_name: String::from("Media"),
+                description: String::from("The media to convert"),
+                property_type: Type::Pipeable(
+                    PipeableType {
+                        video: 1,
+                        audio: 0,
+                        subtitles: 0,
+                    },
+                    PipeableType {
+                        video: 1,
+                        audio: i32::MAX,
+                        subtitles: i32::MAX,
+                    },
+                ),
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::FRAMERATE),
+            NodeTypeInput {
+                name: String::from(inputs::FRAMERATE),
+                display_name: String::from("Frame Rate"),
+                description: String::from("The frame rate to convert the video to"),
+                property_type: Type::Number(Restrictions {
+                    min: 1.0,
+                    max: 120.0,
+                    step: 0.01,
+                    default: DEFAULT_FRAMERATE,
+                }),
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::BLEND),
+            NodeTypeInput {
+                name: String::from(inputs::BLEND),
+                display_name: String::from("Blend Frames"),
+                description: String::from(
+                    "Blend neighbouring frames together, rather than dropping or duplicating frames",
+                ),
+                property_type: Type::Boolean(false),
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::DEINTERLACE),
+            NodeTypeInput {
+                name: String::from(inputs::DEINTERLACE),
+                display_name: String::from("Deinterlace"),
+                description: String::from("Deinterlace the video before converting it"),
+                property_type: Type::Boolean(false),
+            },
+        );
+
+        default_properties
+    }
+
+    fn get_io(&self, context: &NodeContext) -> Result<NodeIo, PipelineError> {
+        let NodeContext {
+            properties,
+            piped_inputs,
+            ..
+        } = *context;
+        let inputs = self.default_properties();
+        let mut outputs = HashMap::new();
+        let mut stream_type = PipeableType {
+            video: i32::MAX,
+            audio: i32::MAX,
+            subtitles: i32::MAX,
+        };
+        let mut format = StreamFormat::default();
+        if let Some(piped_input) = piped_inputs.get(inputs::MEDIA) {
+            stream_type = piped_input.stream_type;
+            format = piped_input.format;
+        }
+        format.framerate = Some(get_number_property(
+            properties,
+            inputs::FRAMERATE,
+            DEFAULT_FRAMERATE,
+        ));
+
+        outputs.insert(
+            outputs::OUTPUT.to_string(),
+            NodeTypeOutput {
+                name: outputs::OUTPUT.to_string(),
+                description: "The media at the new frame rate".to_string(),
+                display_name: "Output".to_string(),
+                property_type: stream_type,
+                format,
+                number_stream: None,
+            },
+        );
+
+        return Ok((inputs, outputs));
+    }
+
+    fn get_output(&self, context: &NodeContext) -> Result<NodeTimelines, PipelineError> {
+        let NodeContext {
+            node_id,
+            properties,
+            piped_inputs,
+            ..
+        } = *context;
+        let io = self.get_io(context);
+        if io.is_err() {
+            return Err(io.unwrap_err());
+        }
+        let (_, outputs) = io.unwrap();
+
+        let media = piped_inputs.get(inputs::MEDIA);
+        if media.is_none() {
+            return Err(PipelineError::missing_input(inputs::MEDIA));
+        }
+        let media = media.unwrap();
+
+        let output = outputs.get(outputs::OUTPUT).unwrap();
+        let output = PipedType {
+            stream_type: output.property_type,
+            node_id,
+            property_name: String::from(outputs::OUTPUT),
+            io: InputOrOutput::Output,
+            cache_id: None,
+            format: output.format,
+        };
+
+        let framerate = output.format.framerate.unwrap();
+        let blend = get_bool_property(properties, inputs::BLEND, false);
+        let deinterlace = get_bool_property(properties, inputs::DEINTERLACE, false);
+
+        let description = get_effect_description(framerate, blend, deinterlace);
+        if description.is_err() {
+            return Err(description.unwrap_err().into());
+        }
+        let effect = ges::Effect::new(description.unwrap().as_str())?;
+
+        let timeline = output.create_timeline();
+        let layer = timeline.append_layer();
+        let clip = ges::UriClip::new(media.get_gst_save_location_with_cache().as_str())?;
+
+        clip.add(&effect)?;
+        layer.add_clip(&clip)?;
+
+        let mut hm = HashMap::new();
+        hm.insert(outputs::OUTPUT.to_string(), timeline);
+        Ok((
+            hm,
+            vec![
+                MemorySafetyWrapper::UriClip(clip),
+                MemorySafetyWrapper::Effect(effect),
+            ],
+        ))
+    }
+}
+
+/**
+ * Gets the GStreamer bin description for the effect which converts the video to the given framerate
+ */
+fn get_effect_description(
+    framerate: f64,
+    blend: bool,
+    deinterlace: bool,
+) -> Result<String, String> {
+    let caps_framerate = |framerate: f64| {
+        gst::Fraction::approximate_f64(framerate)
+            .map(|fraction| format!("{}/{}", *fraction.numer(), *fraction.denom()))
+    };
+
+    let mut elements = Vec::new();
+    if deinterlace {
+        elements.push(String::from("deinterlace"));
+    }
+    if blend {
+        // Convert to double the target framerate, then weave each pair of frames into the two fields of one frame, and blend those fields back together
+        let doubled = caps_framerate(framerate * 2.0);
+        if doubled.is_none() {
+            return Err(format!("Invalid frame rate: {}", framerate));
+        }
+        elements.push(format!(
+            "videorate ! video/x-raw,framerate={}",
+            doubled.unwrap()
+        ));
+        elements.push(String::from("interlace field-pattern=1:1"));
+        elements.push(String::from("deinterlace method=linearblend fields=top"));
+    } else {
+        // Drop or duplicate frames to reach the target framerate
+        let target = caps_framerate(framerate);
+        if target.is_none() {
+            return Err(format!("Invalid frame rate: {}", framerate));
+        }
+        elements.push(format!(
+            "videorate ! video/x-raw,framerate={}",
+            target.unwrap()
+        ));
+    }
+
+    Ok(elements.join(" ! "))
+}
+
+pub fn framerate_node() -> NodeType {
+    NodeType::new(
+        IDENTIFIER,
+        "Frame Rate",
+        "Convert the frame rate of a media source, and optionally deinterlace it",
+        NodeCategory::Video,
+        FramerateNode,
+    )
+    .with_tags(&["fps", "deinterlace", "convert"])
+    .with_units(inputs::FRAMERATE, "fps")
+    .with_version("1.1.0")
+    .with_migration("1.0.0", "1.1.0", |properties| {
+        // the mode and deinterlace properties were numbers, where anything other than 0 turned them on
+        let blend = get_number_property(properties, OLD_MODE, 0.0) != 0.0;
+        let deinterlace = get_number_property(properties, inputs::DEINTERLACE, 0.0) != 0.0;
+        properties.remove(OLD_MODE);
+        properties.insert(String::from(inputs::BLEND), Value::Bool(blend));
+        properties.insert(String::from(inputs::DEINTERLACE), Value::Bool(deinterlace));
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{framerate_node, get_effect_description, inputs, OLD_MODE};
+    use crate::node::Node;
+
+    #[test]
+    fn test_effect_description() {
+        assert_eq!(
+            get_effect_description(25.0, false, false).unwrap(),
+            "videorate ! video/x-raw,framerate=25/1"
+        );
+        assert_eq!(
+            get_effect_description(25.0, false, true).unwrap(),
+            "deinterlace ! videorate ! video/x-raw,framerate=25/1"
+        );
+        assert_eq!(
+            get_effect_description(25.0, true, false).unwrap(),
+            "videorate ! video/x-raw,framerate=50/1 ! interlace field-pattern=1:1 ! deinterlace method=linearblend fields=top"
+        );
+        assert_eq!(
+            get_effect_description(30.0, true, true).unwrap(),
+            "deinterlace ! videorate ! video/x-raw,framerate=60/1 ! interlace field-pattern=1:1 ! deinterlace method=linearblend fields=top"
+        );
+    }
+
+    #[test]
+    fn test_migrate_numeric_modes() {
+        let mut node = Node::new(String::from(super::IDENTIFIER), None);
+        node.properties.insert(String::from(OLD_MODE), json!(1.0));
+        node.properties
+            .insert(String::from(inputs::DEINTERLACE), json!(0.0));
+        framerate_node().migrate(&mut node);
+
+        assert!(!node.properties.contains_key(OLD_MODE));
+        assert_eq!(node.properties.get(inputs::BLEND), Some(&Value::Bool(true)));
+        assert_eq!(
+            node.properties.get(inputs::DEINTERLACE),
+            Some(&Value::Bool(false))
+        );
+    }
+}
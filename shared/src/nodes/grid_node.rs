@@ -0,0 +1,473 @@
+use std::collections::HashMap;
+
+use ges::{
+    traits::{LayerExt, TimelineElementExt, TimelineExt, UriClipAssetExt},
+    TrackType,
+};
+use glib::{ObjectExt, StaticType, ToValue};
+use serde_json::Value;
+
+use crate::{
+    error::{PipelineError, PipelineErrorKind},
+    node::{
+        InputOrOutput, MemorySafetyWrapper, NodeCategory, NodeContext, NodeIo, NodeKind,
+        NodeTimelines, NodeType, NodeTypeInput, NodeTypeOutput, PipeableType, PipedType,
+        Restrictions, StreamFormat, Type,
+    },
+};
+
+use super::{get_number_property, number_input};
+
+pub const IDENTIFIER: &str = "grid";
+pub mod inputs {
+    pub const NUMBER_OF_INPUTS: &str = "number_of_inputs";
+    pub const COLUMNS: &str = "columns";
+    pub const ROWS: &str = "rows";
+    pub const WIDTH: &str = "width";
+    pub const HEIGHT: &str = "height";
+    pub const GAP: &str = "gap";
+    pub const BACKGROUND_COLOUR: &str = "background_colour";
+    pub const AUDIO: &str = "audio";
+
+    pub fn media(i: i32) -> String {
+        format!("media{}", i)
+    }
+}
+pub mod outputs {
+    pub const OUTPUT: &str = "output";
+}
+
+const MAX_INPUTS: i32 = 16;
+const DEFAULT_NUMBER_OF_INPUTS: f64 = 4.0;
+const DEFAULT_WIDTH: f64 = 1920.0;
+const DEFAULT_HEIGHT: f64 = 1080.0;
+const DEFAULT_BACKGROUND_COLOUR: &str = "#000000";
+
+fn media_input(i: i32) -> NodeTypeInput {
+    NodeTypeInput {
+        name: inputs::media(i),
+        display_name: format!("Media {}", i),
+        description: format!("The media to place in cell {} of the grid", i),
+        property_type: Type::Pipeable(
+            PipeableType {
+                video: 1,
+                audio: 0,
+                subtitles: 0,
+            },
+            PipeableType {
+                video: 1,
+                audio: i32::MAX,
+                subtitles: i32::MAX,
+            },
+        ),
+    }
+}
+
+fn get_number_of_inputs(properties: &HashMap<String, Value>) -> i32 {
+    super::get_number_of_inputs(
+        properties,
+        inputs::NUMBER_OF_INPUTS,
+        DEFAULT_NUMBER_OF_INPUTS as i32,
+        2,
+        MAX_INPUTS,
+    )
+}
+
+/**
+ * Whether the audio of input `i` is kept in the output
+ */
+fn keeps_audio(properties: &HashMap<String, Value>, i: i32) -> bool {
+    let audio = get_number_property(properties, inputs::AUDIO, 0.0) as i32;
+    audio == 0 || audio == i
+}
+
+pub struct GridNode;
+
+impl NodeKind for GridNode {
+    fn default_properties(&self) -> HashMap<String, NodeTypeInput> {
+        let mut default_properties = HashMap::new();
+
+        default_properties.insert(
+            String::from(inputs::NUMBER_OF_INPUTS),
+            number_input(
+                inputs::NUMBER_OF_INPUTS,
+                "Number of Inputs",
+                "The number of media to place in the grid",
+                Restrictions {
+                    min: 2.0,
+                    max: MAX_INPUTS as f64,
+                    step: 1.0,
+                    default: DEFAULT_NUMBER_OF_INPUTS,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::COLUMNS),
+            number_input(
+                inputs::COLUMNS,
+                "Columns",
+                "The number of columns in the grid; 0 chooses automatically",
+                Restrictions {
+                    min: 0.0,
+                    max: MAX_INPUTS as f64,
+                    step: 1.0,
+                    default: 0.0,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::ROWS),
+            number_input(
+                inputs::ROWS,
+                "Rows",
+                "The number of rows in the grid; 0 chooses automatically",
+                Restrictions {
+                    min: 0.0,
+                    max: MAX_INPUTS as f64,
+                    step: 1.0,
+                    default: 0.0,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::WIDTH),
+            number_input(
+                inputs::WIDTH,
+                "Width",
+                "The width of the output video, in pixels",
+                Restrictions {
+                    min: 16.0,
+                    max: 7680.0,
+                    step: 2.0,
+                    default: DEFAULT_WIDTH,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::HEIGHT),
+            number_input(
+                inputs::HEIGHT,
+                "Height",
+                "The height of the output video, in pixels",
+                Restrictions {
+                    min: 16.0,
+                    max: 4320.0,
+                    step: 2.0,
+                    default: DEFAULT_HEIGHT,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::GAP),
+            number_input(
+                inputs::GAP,
+                "Gap",
+                "The space between and around the cells of the grid, in pixels",
+                Restrictions {
+                    min: 0.0,
+                    max: 500.0,
+                    step: 1.0,
+                    default: 0.0,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::BACKGROUND_COLOUR),
+            NodeTypeInput {
+                name: String::from(inputs::BACKGROUND_COLOUR),
+                display_name: String::from("Background Colour"),
+                description: format!(
+                    "The colour shown behind the grid, as a hex code (default {})",
+                    DEFAULT_BACKGROUND_COLOUR
+                ),
+                property_type: Type::String(7),
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::AUDIO),
+            number_input(
+                inputs::AUDIO,
+                "Audio",
+                "The number of the input whose audio is kept; 0 mixes the audio of all the inputs",
+                Restrictions {
+                    min: 0.0,
+                    max: MAX_INPUTS as f64,
+                    step: 1.0,
+                    default: 0.0,
+                },
+            ),
+        );
+
+        default_properties
+    }
+
+    fn get_io(&self, context: &NodeContext) -> Result<NodeIo, PipelineError> {
+        let NodeContext {
+            properties,
+            piped_inputs,
+            ..
+        } = *context;
+        let mut inputs = self.default_properties();
+        let mut outputs = HashMap::new();
+
+        let mut audio = 0;
+        let mut formats = Vec::new();
+        for i in 1..(get_number_of_inputs(properties) + 1) {
+            inputs.insert(inputs::media(i), media_input(i));
+            if let Some(piped_input) = piped_inputs.get(&inputs::media(i)) {
+                if keeps_audio(properties, i) && piped_input.stream_type.audio > 0 {
+                    audio = 1;
+                }
+                formats.push(piped_input.format);
+            }
+        }
+
+        let mut format = StreamFormat::combine(&formats);
+        format.resolution = Some((
+            get_number_property(properties, inputs::WIDTH, DEFAULT_WIDTH) as u32,
+            get_number_property(properties, inputs::HEIGHT, DEFAULT_HEIGHT) as u32,
+        ));
+
+        outputs.insert(
+            outputs::OUTPUT.to_string(),
+            NodeTypeOutput {
+                name: outputs::OUTPUT.to_string(),
+                description: "The media laid out in a grid".to_string(),
+                display_name: "Output".to_string(),
+                property_type: PipeableType {
+                    video: 1,
+                    audio,
+                    subtitles: 0,
+                },
+                format,
+                number_stream: None,
+            },
+        );
+
+        return Ok((inputs, outputs));
+    }
+
+    fn get_output(&self, context: &NodeContext) -> Result<NodeTimelines, PipelineError> {
+        let NodeContext {
+            node_id,
+            properties,
+            piped_inputs,
+            ..
+        } = *context;
+        let io = self.get_io(context);
+        if io.is_err() {
+            return Err(io.unwrap_err());
+        }
+        let (_, outputs) = io.unwrap();
+
+        let number_of_inputs = get_number_of_inputs(properties);
+        let (width, height) = (
+            get_number_property(properties, inputs::WIDTH, DEFAULT_WIDTH) as u32,
+            get_number_property(properties, inputs::HEIGHT, DEFAULT_HEIGHT) as u32,
+        );
+        let cells = get_grid_cells(
+            number_of_inputs as u32,
+            get_number_property(properties, inputs::COLUMNS, 0.0) as u32,
+            get_number_property(properties, inputs::ROWS, 0.0) as u32,
+            width,
+            height,
+            get_number_property(properties, inputs::GAP, 0.0) as u32,
+        )?;
+        let background_colour = match properties.get(inputs::BACKGROUND_COLOUR) {
+            Some(Value::String(colour)) if !colour.is_empty() => parse_colour(colour)?,
+            _ => parse_colour(DEFAULT_BACKGROUND_COLOUR)?,
+        };
+
+        let output = outputs.get(outputs::OUTPUT).unwrap();
+        let output = PipedType {
+            stream_type: output.property_type,
+            node_id,
+            property_name: String::from(outputs::OUTPUT),
+            io: InputOrOutput::Output,
+            cache_id: None,
+            format: output.format,
+        };
+
+        let timeline = output.create_timeline();
+
+        let mut memory_safety_vars = Vec::new();
+        let mut duration = 0;
+        for i in 1..(number_of_inputs + 1) {
+            let media = piped_inputs.get(&inputs::media(i));
+            if media.is_none() {
+                return Err(PipelineError::missing_input(&inputs::media(i)));
+            }
+            let media = media.unwrap();
+
+            let location = media.get_gst_save_location_with_cache();
+            ges::Asset::needs_reload(ges::UriClip::static_type(), Some(location.as_str()));
+            let asset = ges::UriClipAsset::request_sync(location.as_str())?;
+            let asset_duration = asset.duration();
+            if asset_duration.is_none() {
+                return Err(PipelineError::unknown_duration(&inputs::media(i)));
+            }
+            duration = u64::max(duration, asset_duration.unwrap().mseconds());
+
+            let track_types = if keeps_audio(properties, i) && output.stream_type.audio > 0 {
+                TrackType::VIDEO | TrackType::AUDIO
+            } else {
+                TrackType::VIDEO
+            };
+
+            // each input has its own layer, above the background
+            let layer = timeline.append_layer();
+            let clip = layer.add_asset(&asset, None, None, None, track_types)?;
+
+            let (x, y, cell_width, cell_height) = cells[(i - 1) as usize];
+            let child_properties = [
+                ("posx", x as i32),
+                ("posy", y as i32),
+                ("width", cell_width as i32),
+                ("height", cell_height as i32),
+            ];
+            for (name, value) in child_properties {
+                if clip.set_child_property(name, &value.to_value()).is_err() {
+                    return Err(PipelineError::new(
+                        PipelineErrorKind::Ges,
+                        format!("Could not set the {} of input {}", name, i),
+                    ));
+                }
+            }
+
+            memory_safety_vars.push(MemorySafetyWrapper::UriClipAsset(asset));
+        }
+
+        let background = ges::TestClip::new();
+        if background.is_none() {
+            return Err(PipelineError::new(
+                PipelineErrorKind::Ges,
+                String::from("Could not create the background"),
+            ));
+        }
+        let background = background.unwrap();
+        background.set_property("vpattern", ges::VideoTestPattern::SolidColor);
+        background.set_property("foreground-color", background_colour);
+        background.set_property("mute", true);
+        background.set_duration(gst::ClockTime::from_mseconds(duration));
+        let background_layer = timeline.append_layer();
+        background_layer.add_clip(&background)?;
+
+        let mut hm = HashMap::new();
+        hm.insert(outputs::OUTPUT.to_string(), timeline);
+        Ok((hm, memory_safety_vars))
+    }
+}
+
+pub fn grid_node() -> NodeType {
+    NodeType::new(
+        IDENTIFIER,
+        "Grid",
+        "Lay out several media sources in a grid, for split-screen or mosaic videos",
+        NodeCategory::Video,
+        GridNode,
+    )
+    .with_tags(&["split screen", "mosaic", "layout", "tile"])
+    .with_units(inputs::WIDTH, "px")
+    .with_units(inputs::HEIGHT, "px")
+    .with_units(inputs::GAP, "px")
+}
+
+/**
+ * Calculates the position and size (x, y, width, height) of each cell in the grid, filling each row from left to right.
+ * If the number of columns or rows is 0, it is chosen to fit all the cells, keeping the grid as square as possible
+ */
+pub fn get_grid_cells(
+    number_of_cells: u32,
+    columns: u32,
+    rows: u32,
+    width: u32,
+    height: u32,
+    gap: u32,
+) -> Result<Vec<(u32, u32, u32, u32)>, String> {
+    let divide_up = |a: u32, b: u32| (a + b - 1) / b;
+    let (columns, rows) = match (columns, rows) {
+        (0, 0) => {
+            let columns = f64::ceil(f64::sqrt(number_of_cells as f64)) as u32;
+            (columns, divide_up(number_of_cells, columns))
+        }
+        (0, rows) => (divide_up(number_of_cells, rows), rows),
+        (columns, 0) => (columns, divide_up(number_of_cells, columns)),
+        (columns, rows) => (columns, rows),
+    };
+    if columns * rows < number_of_cells {
+        return Err(format!(
+            "A {}x{} grid cannot fit {} inputs",
+            columns, rows, number_of_cells
+        ));
+    }
+    if gap * (columns + 1) >= width || gap * (rows + 1) >= height {
+        return Err(String::from("The gap is too large for the grid"));
+    }
+
+    let cell_width = (width - gap * (columns + 1)) / columns;
+    let cell_height = (height - gap * (rows + 1)) / rows;
+    Ok((0..number_of_cells)
+        .map(|i| {
+            let (column, row) = (i % columns, i / columns);
+            (
+                gap + column * (cell_width + gap),
+                gap + row * (cell_height + gap),
+                cell_width,
+                cell_height,
+            )
+        })
+        .collect())
+}
+
+/**
+ * Parses a hex colour code (e.g. `#ff8800`) into an opaque ARGB value
+ */
+pub fn parse_colour(colour: &str) -> Result<u32, String> {
+    let hex = colour.trim().trim_start_matches("#");
+    if hex.len() != 6 {
+        return Err(format!("Invalid colour: {}", colour));
+    }
+    match u32::from_str_radix(hex, 16) {
+        Ok(rgb) => Ok(0xff000000 | rgb),
+        Err(_) => Err(format!("Invalid colour: {}", colour)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_grid_cells, parse_colour};
+
+    #[test]
+    fn test_grid_cells() {
+        // 2x2 grid chosen automatically, with a 10px gap
+        let cells = get_grid_cells(4, 0, 0, 1920, 1080, 10).unwrap();
+        assert_eq!(
+            cells,
+            vec![
+                (10, 10, 945, 525),
+                (965, 10, 945, 525),
+                (10, 545, 945, 525),
+                (965, 545, 945, 525)
+            ]
+        );
+
+        // side by side
+        let cells = get_grid_cells(2, 2, 1, 1920, 1080, 0).unwrap();
+        assert_eq!(cells, vec![(0, 0, 960, 1080), (960, 0, 960, 1080)]);
+
+        // 5 inputs need a 3x2 grid
+        assert_eq!(
+            get_grid_cells(5, 0, 0, 1920, 1080, 0).unwrap()[4],
+            (640, 540, 640, 540)
+        );
+        assert!(get_grid_cells(5, 2, 2, 1920, 1080, 0).is_err());
+    }
+
+    #[test]
+    fn test_parse_colour() {
+        assert_eq!(parse_colour("#ff8800").unwrap(), 0xffff8800);
+        assert_eq!(parse_colour("000000").unwrap(), 0xff000000);
+        assert!(parse_colour("#fff").is_err());
+        assert!(parse_colour("#gggggg").is_err());
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::{
+    error::PipelineError,
+    keyframes::{AnimatedNumber, Interpolation, Keyframe},
+    node::{
+        NodeCategory, NodeContext, NodeIo, NodeKind, NodeTimelines, NodeType, NodeTypeInput,
+        NodeTypeOutput, PipeableType, Restrictions, StreamFormat,
+    },
+};
+
+use super::{get_number_property, number_input};
+
+pub const IDENTIFIER: &str = "lfo";
+pub mod inputs {
+    pub const WAVEFORM: &str = "waveform";
+    pub const FREQUENCY: &str = "frequency";
+    pub const AMPLITUDE: &str = "amplitude";
+    pub const OFFSET: &str = "offset";
+    pub const DURATION: &str = "duration";
+}
+pub mod outputs {
+    pub const VALUE: &str = "value";
+}
+
+pub const SINE: i32 = 0;
+pub const TRIANGLE: i32 = 1;
+pub const SQUARE: i32 = 2;
+pub const SAWTOOTH: i32 = 3;
+
+const DEFAULT_FREQUENCY: f64 = 1.0;
+const DEFAULT_AMPLITUDE: f64 = 1.0;
+const DEFAULT_DURATION: f64 = 10.0;
+/// How long before the end of a cycle a sawtooth wave jumps back to its lowest value, in seconds
+const SAWTOOTH_JUMP: f64 = 0.001;
+
+pub struct LfoNode;
+
+impl NodeKind for LfoNode {
+    fn default_properties(&self) -> HashMap<String, NodeTypeInput> {
+        let mut default_properties = HashMap::new();
+
+        default_properties.insert(
+            String::from(inputs::WAVEFORM),
+            number_input(
+                inputs::WAVEFORM,
+                "Waveform",
+                "The shape of the wave: 0 for sine, 1 for triangle, 2 for square, 3 for sawtooth",
+                Restrictions {
+                    min: 0.0,
+                    max: 3.0,
+                    step: 1.0,
+                    default: SINE as f64,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::FREQUENCY),
+            number_input(
+                inputs::FREQUENCY,
+                "Frequency",
+                "The number of cycles per second, in Hz",
+                Restrictions {
+                    min: 0.01,
+                    max: 10.0,
+                    step: 0.01,
+                    default: DEFAULT_FREQUENCY,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::AMPLITUDE),
+            number_input(
+                inputs::AMPLITUDE,
+                "Amplitude",
+                "How far the value moves above and below the offset",
+                Restrictions {
+                    min: 0.0,
+                    max: 1000.0,
+                    step: 0.01,
+                    default: DEFAULT_AMPLITUDE,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::OFFSET),
+            number_input(
+                inputs::OFFSET,
+                "Offset",
+                "The value in the middle of the wave",
+                Restrictions {
+                    min: -1000.0,
+                    max: 1000.0,
+                    step: 0.01,
+                    default: 0.0,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::DURATION),
+            number_input(
+                inputs::DURATION,
+                "Duration",
+                "How long the wave lasts, in seconds; afterwards, the last value is held",
+                Restrictions {
+                    min: 0.1,
+                    max: 600.0,
+                    step: 0.1,
+                    default: DEFAULT_DURATION,
+                },
+            ),
+        );
+
+        default_properties
+    }
+
+    fn get_io(&self, context: &NodeContext) -> Result<NodeIo, PipelineError> {
+        let NodeContext { properties, .. } = *context;
+        let inputs = self.default_properties();
+        let mut outputs = HashMap::new();
+
+        let keyframes = get_lfo_keyframes(
+            get_number_property(properties, inputs::WAVEFORM, SINE as f64) as i32,
+            get_number_property(properties, inputs::FREQUENCY, DEFAULT_FREQUENCY),
+            get_number_property(properties, inputs::AMPLITUDE, DEFAULT_AMPLITUDE),
+            get_number_property(properties, inputs::OFFSET, 0.0),
+            get_number_property(properties, inputs::DURATION, DEFAULT_DURATION),
+        );
+        if keyframes.is_err() {
+            return Err(keyframes.unwrap_err().into());
+        }
+
+        outputs.insert(
+            outputs::VALUE.to_string(),
+            NodeTypeOutput {
+                name: outputs::VALUE.to_string(),
+                description: "The value of the wave over time".to_string(),
+                display_name: "Value".to_string(),
+                property_type: PipeableType {
+                    video: 0,
+                    audio: 0,
+                    subtitles: 0,
+                },
+                format: StreamFormat::default(),
+                number_stream: Some(AnimatedNumber::Keyframed {
+                    keyframes: keyframes.unwrap(),
+                }),
+            },
+        );
+
+        return Ok((inputs, outputs));
+    }
+
+    fn get_output(&self, context: &NodeContext) -> Result<NodeTimelines, PipelineError> {
+        let io = self.get_io(context);
+        if io.is_err() {
+            return Err(io.unwrap_err());
+        }
+
+        // the number stream is passed on directly through the outputs, so there are no timelines
+        Ok((HashMap::new(), Vec::new()))
+    }
+}
+
+pub fn lfo_node() -> NodeType {
+    NodeType::new(
+        IDENTIFIER,
+        "LFO",
+        "Generate a repeating wave of numbers, which can be linked into number properties of other nodes",
+        NodeCategory::Generator,
+        LfoNode,
+    )
+    .with_tags(&["oscillator", "wave", "sine", "modulate", "number"])
+    .with_units(inputs::FREQUENCY, "Hz")
+    .with_units(inputs::DURATION, "s")
+    .with_help(
+        inputs::AMPLITUDE,
+        "Link the value output into a number property of another node to animate it; the property moves between offset - amplitude and offset + amplitude",
+    )
+}
+
+/**
+ * Gets the keyframes of a wave lasting `duration` seconds, which moves `amplitude` above and below `offset`, `frequency` times a second.
+ * Each cycle starts at the lowest value; sine waves are made from eased keyframes at each peak and trough
+ */
+pub fn get_lfo_keyframes(
+    waveform: i32,
+    frequency: f64,
+    amplitude: f64,
+    offset: f64,
+    duration: f64,
+) -> Result<Vec<Keyframe>, String> {
+    if frequency <= 0.0 {
+        return Err(String::from("The frequency must be above 0"));
+    }
+    let period = 1.0 / frequency;
+    let (low, high) = (offset - amplitude, offset + amplitude);
+    let keyframe = |time: f64, value: f64, interpolation: Interpolation| Keyframe {
+        time,
+        value,
+        interpolation,
+    };
+
+    let mut keyframes = Vec::new();
+    if waveform == SAWTOOTH {
+        let mut cycle = 0;
+        loop {
+            let start = cycle as f64 * period;
+            keyframes.push(keyframe(start, low, Interpolation::Linear));
+            if start >= duration {
+                break;
+            }
+            keyframes.push(keyframe(
+                start + period - SAWTOOTH_JUMP,
+                high,
+                Interpolation::Hold,
+            ));
+            cycle += 1;
+        }
+        return Ok(keyframes);
+    }
+
+    let interpolation = match waveform {
+        SINE => Interpolation::Ease,
+        TRIANGLE => Interpolation::Linear,
+        SQUARE => Interpolation::Hold,
+        _ => return Err(format!("Unknown waveform {}", waveform)),
+    };
+    // a keyframe at every peak and trough
+    let mut half_cycle = 0;
+    loop {
+        let time = half_cycle as f64 * period / 2.0;
+        let value = if half_cycle % 2 == 0 { low } else { high };
+        keyframes.push(keyframe(time, value, interpolation));
+        if time >= duration {
+            break;
+        }
+        half_cycle += 1;
+    }
+    Ok(keyframes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keyframes::AnimatedNumber;
+
+    use super::{get_lfo_keyframes, SAWTOOTH, SINE, SQUARE};
+
+    #[test]
+    fn test_lfo_keyframes() {
+        let sine = AnimatedNumber::Keyframed {
+            keyframes: get_lfo_keyframes(SINE, 0.5, 2.0, 1.0, 4.0).unwrap(),
+        };
+        assert_eq!(sine.value_at(0.0), -1.0);
+        assert_eq!(sine.value_at(0.5), 1.0);
+        assert_eq!(sine.value_at(1.0), 3.0);
+        assert_eq!(sine.value_at(2.0), -1.0);
+        assert_eq!(sine.value_at(100.0), -1.0);
+
+        let square = AnimatedNumber::Keyframed {
+            keyframes: get_lfo_keyframes(SQUARE, 1.0, 1.0, 0.0, 1.0).unwrap(),
+        };
+        assert_eq!(square.value_at(0.4), -1.0);
+        assert_eq!(square.value_at(0.6), 1.0);
+
+        let sawtooth = AnimatedNumber::Keyframed {
+            keyframes: get_lfo_keyframes(SAWTOOTH, 1.0, 1.0, 0.0, 2.0).unwrap(),
+        };
+        assert!(sawtooth.value_at(1.5).abs() < 0.01);
+        assert_eq!(sawtooth.value_at(1.9995), 1.0);
+        assert_eq!(sawtooth.value_at(2.0), -1.0);
+
+        assert!(get_lfo_keyframes(7, 1.0, 1.0, 0.0, 1.0).is_err());
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+
+use ges::{
+    traits::{LayerExt, TimelineExt, UriClipAssetExt},
+    TrackType,
+};
+use glib::StaticType;
+
+use crate::{
+    error::PipelineError,
+    node::{
+        InputOrOutput, MemorySafetyWrapper, NodeCategory, NodeContext, NodeIo, NodeKind,
+        NodeTimelines, NodeType, NodeTypeInput, NodeTypeOutput, PipeableType, PipedType,
+        Restrictions, StreamFormat, Type,
+    },
+};
+
+use super::{get_number_property, number_input};
+
+pub const IDENTIFIER: &str = "loop";
+pub mod inputs {
+    pub const MEDIA: &str = "media";
+    pub const REPETITIONS: &str = "repetitions";
+    pub const DURATION: &str = "duration";
+    pub const CROSSFADE: &str = "crossfade";
+}
+pub mod outputs {
+    pub const OUTPUT: &str = "output";
+}
+
+const DEFAULT_REPETITIONS: f64 = 2.0;
+
+pub struct LoopNode;
+
+impl NodeKind for LoopNode {
+    fn default_properties(&self) -> HashMap<String, NodeTypeInput> {
+        let mut default_properties = HashMap::new();
+
+        default_properties.insert(
+            String::from(inputs::MEDIA),
+            NodeTypeInput {
+                name: String::from(inputs::MEDIA),
+                display_name: String::from("Media"),
+                description: String::from("The media to loop"),
+                property_type: Type::Pipeable(
+                    PipeableType {
+                        video: 0,
+                        audio: 0,
+                        subtitles: 0,
+                    },
+                    PipeableType {
+                        video: i32::MAX,
+                        audio: i32::MAX,
+                        subtitles: i32::MAX,
+                    },
+                ),
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::REPETITIONS),
+            number_input(
+                inputs::REPETITIONS,
+                "Repetitions",
+                "The number of times the media is played, if no duration is given",
+                Restrictions {
+                    min: 1.0,
+                    max: 1000.0,
+                    step: 1.0,
+                    default: DEFAULT_REPETITIONS,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::DURATION),
+            number_input(
+                inputs::DURATION,
+                "Duration",
+                "The length to loop the media until, in seconds; 0 uses the number of repetitions instead",
+                Restrictions {
+                    min: 0.0,
+                    max: 86400.0,
+                    step: 0.1,
+                    default: 0.0,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::CROSSFADE),
+            number_input(
+                inputs::CROSSFADE,
+                "Crossfade",
+                "How long each repetition fades into the next, in seconds; 0 cuts straight to the next repetition",
+                Restrictions {
+                    min: 0.0,
+                    max: 10.0,
+                    step: 0.1,
+                    default: 0.0,
+                },
+            ),
+        );
+
+        default_properties
+    }
+
+    fn get_io(&self, context: &NodeContext) -> Result<NodeIo, PipelineError> {
+        let NodeContext {
+            properties,
+            piped_inputs,
+            ..
+        } = *context;
+        let inputs = self.default_properties();
+        let mut outputs = HashMap::new();
+        let mut stream_type = PipeableType {
+            video: i32::MAX,
+            audio: i32::MAX,
+            subtitles: i32::MAX,
+        };
+        let mut format = StreamFormat::default();
+        if let Some(piped_input) = piped_inputs.get(inputs::MEDIA) {
+            stream_type = piped_input.stream_type;
+            format = piped_input.format;
+            format.duration = format.duration.and_then(|duration| {
+                get_loop_clips(
+                    duration,
+                    get_number_property(properties, inputs::REPETITIONS, DEFAULT_REPETITIONS)
+                        as u64,
+                    (get_number_property(properties, inputs::DURATION, 0.0) * 1000.0) as u64,
+                    (get_number_property(properties, inputs::CROSSFADE, 0.0) * 1000.0) as u64,
+                )
+                .ok()
+                .and_then(|clips| clips.last().map(|(start, duration)| start + duration))
+            });
+        }
+        outputs.insert(
+            outputs::OUTPUT.to_string(),
+            NodeTypeOutput {
+                name: outputs::OUTPUT.to_string(),
+                description: "The looped media".to_string(),
+                display_name: "Output".to_string(),
+                property_type: stream_type,
+                format,
+                number_stream: None,
+            },
+        );
+
+        return Ok((inputs, outputs));
+    }
+
+    fn get_output(&self, context: &NodeContext) -> Result<NodeTimelines, PipelineError> {
+        let NodeContext {
+            node_id,
+            properties,
+            piped_inputs,
+            ..
+        } = *context;
+        let io = self.get_io(context);
+        if io.is_err() {
+            return Err(io.unwrap_err());
+        }
+        let (_, outputs) = io.unwrap();
+
+        let media = piped_inputs.get(inputs::MEDIA);
+        if media.is_none() {
+            return Err(PipelineError::missing_input(inputs::MEDIA));
+        }
+        let media = media.unwrap();
+
+        let output = outputs.get(outputs::OUTPUT).unwrap();
+        let output = PipedType {
+            stream_type: output.property_type,
+            node_id,
+            property_name: String::from(outputs::OUTPUT),
+            io: InputOrOutput::Output,
+            cache_id: None,
+            format: output.format,
+        };
+
+        let timeline = output.create_timeline();
+        // overlapping repetitions are crossfaded into each other
+        timeline.set_auto_transition(true);
+        let layer = timeline.append_layer();
+
+        let location = media.get_gst_save_location_with_cache();
+        ges::Asset::needs_reload(ges::UriClip::static_type(), Some(location.as_str()));
+        let clip = ges::UriClipAsset::request_sync(location.as_str())?;
+        let clip_duration = clip.duration();
+        if clip_duration.is_none() {
+            return Err(PipelineError::unknown_duration(inputs::MEDIA));
+        }
+
+        let repetitions = get_loop_clips(
+            clip_duration.unwrap().mseconds(),
+            get_number_property(properties, inputs::REPETITIONS, DEFAULT_REPETITIONS) as u64,
+            (get_number_property(properties, inputs::DURATION, 0.0) * 1000.0) as u64,
+            (get_number_property(properties, inputs::CROSSFADE, 0.0) * 1000.0) as u64,
+        );
+        if repetitions.is_err() {
+            return Err(repetitions.unwrap_err().into());
+        }
+
+        for (start, duration) in repetitions.unwrap() {
+            layer.add_asset(
+                &clip,
+                Some(gst::ClockTime::from_mseconds(start)),
+                None,
+                Some(gst::ClockTime::from_mseconds(duration)),
+                TrackType::UNKNOWN,
+            )?;
+        }
+
+        let mut hm = HashMap::new();
+        hm.insert(outputs::OUTPUT.to_string(), timeline);
+        Ok((hm, vec![MemorySafetyWrapper::UriClipAsset(clip)]))
+    }
+}
+
+pub fn loop_node() -> NodeType {
+    NodeType::new(
+        IDENTIFIER,
+        "Loop",
+        "Repeat a media source a number of times, or until it reaches a given length",
+        NodeCategory::Utility,
+        LoopNode,
+    )
+    .with_tags(&["repeat", "cycle"])
+    .with_units(inputs::DURATION, "s")
+    .with_units(inputs::CROSSFADE, "s")
+}
+
+/**
+ * Gets the start and duration (in milliseconds) of each repetition of media lasting `duration` milliseconds.
+ * If `target_duration` is non-zero, the media is repeated until it reaches that length, with the last repetition cut short; otherwise it is repeated `repetitions` times.
+ * Each repetition overlaps the previous one by `crossfade` milliseconds
+ */
+pub fn get_loop_clips(
+    duration: u64,
+    repetitions: u64,
+    target_duration: u64,
+    crossfade: u64,
+) -> Result<Vec<(u64, u64)>, String> {
+    if duration == 0 {
+        return Err(String::from("Cannot loop media with no length"));
+    }
+    // a repetition can only overlap the one before it and the one after it
+    if crossfade * 2 >= duration {
+        return Err(String::from(
+            "The crossfade must be shorter than half the length of the media",
+        ));
+    }
+    let step = duration - crossfade;
+
+    let mut clips = Vec::new();
+    if target_duration == 0 {
+        for i in 0..u64::max(repetitions, 1) {
+            clips.push((i * step, duration));
+        }
+        return Ok(clips);
+    }
+
+    let mut start = 0;
+    // once the previous repetition reaches the target (including its crossfade), no more are needed
+    while start == 0 || start + crossfade < target_duration {
+        clips.push((start, u64::min(duration, target_duration - start)));
+        start += step;
+    }
+    Ok(clips)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::get_loop_clips;
+
+    #[test]
+    fn test_loop_clips() {
+        assert_eq!(
+            get_loop_clips(10000, 3, 0, 1000).unwrap(),
+            vec![(0, 10000), (9000, 10000), (18000, 10000)]
+        );
+        assert_eq!(
+            get_loop_clips(10000, 1, 25000, 1000).unwrap(),
+            vec![(0, 10000), (9000, 10000), (18000, 7000)]
+        );
+        // the second repetition would only be part of a crossfade, so the first is cut short instead
+        assert_eq!(
+            get_loop_clips(10000, 1, 9500, 1000).unwrap(),
+            vec![(0, 9500)]
+        );
+        assert!(get_loop_clips(10000, 2, 0, 5000).is_err());
+    }
+}
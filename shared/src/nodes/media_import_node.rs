@@ -1,61 +1,140 @@
 use std::collections::HashMap;
 
-use ges::traits::{LayerExt, TimelineExt};
+use ges::traits::{GESContainerExt, LayerExt, TimelineExt};
 use glib::StaticType;
 use serde_json::Value;
 
 use crate::{
     clip::{ClipIdentifier, ClipType},
+    error::PipelineError,
     node::{
-        self, MemorySafetyWrapper, NodeType, NodeTypeInput, NodeTypeOutput, PipeableType,
-        PipedType, Type,
+        self, MemorySafetyWrapper, NodeCategory, NodeContext, NodeIo, NodeKind, NodeTimelines,
+        NodeType, NodeTypeInput, NodeTypeOutput, PipeableType, PipedType, Restrictions,
+        StreamFormat, Type,
     },
-    nodes::NodeRegister,
-    store::Store,
+    nodes::{get_number_property, rotate_node::video_flip_method},
     ID,
 };
 
 pub const IDENTIFIER: &str = "clip_import";
 pub mod inputs {
     pub const CLIP: &str = "clip";
+    pub const AUTO_ROTATE: &str = "auto_rotate";
 }
 pub mod outputs {
     pub const OUTPUT: &str = "output";
 }
 
-fn default_properties() -> HashMap<String, NodeTypeInput> {
-    let mut default_properties = HashMap::new();
-
-    default_properties.insert(
-        String::from(inputs::CLIP),
-        NodeTypeInput {
-            name: String::from("clip"),
-            display_name: String::from("Clip"),
-            description: String::from("Clip to import"),
-            property_type: Type::Clip,
-        },
-    );
-    default_properties
-}
+pub struct MediaImportNode;
 
-fn get_io(
-    _node_id: ID,
-    properties: &HashMap<String, Value>,
-    _piped_inputs: &HashMap<String, PipedType>,
-    composited_clip_types: &HashMap<ID, PipedType>,
-    store: &Store,
-    _node_register: &NodeRegister,
-) -> Result<
-    (
-        HashMap<String, NodeTypeInput>,
-        HashMap<String, NodeTypeOutput>,
-    ),
-    String,
-> {
-    let inputs = default_properties();
+impl NodeKind for MediaImportNode {
+    fn default_properties(&self) -> HashMap<String, NodeTypeInput> {
+        let mut default_properties = HashMap::new();
 
-    let clip = properties.get(inputs::CLIP);
-    if clip.is_none() {
+        default_properties.insert(
+            String::from(inputs::CLIP),
+            NodeTypeInput {
+                name: String::from("clip"),
+                display_name: String::from("Clip"),
+                description: String::from("Clip to import"),
+                property_type: Type::Clip,
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::AUTO_ROTATE),
+            NodeTypeInput {
+                name: String::from(inputs::AUTO_ROTATE),
+                display_name: String::from("Auto Rotate"),
+                description: String::from(
+                    "Set to 1 to rotate source clips according to their orientation metadata",
+                ),
+                property_type: Type::Number(Restrictions {
+                    min: 0.0,
+                    max: 1.0,
+                    step: 1.0,
+                    default: 1.0,
+                }),
+            },
+        );
+        default_properties
+    }
+
+    fn get_io(&self, context: &NodeContext) -> Result<NodeIo, PipelineError> {
+        let NodeContext {
+            properties,
+            composited_clip_types,
+            store,
+            ..
+        } = *context;
+        let inputs = self.default_properties();
+
+        let clip = properties.get(inputs::CLIP);
+        if clip.is_none() {
+            let mut hm = HashMap::new();
+            hm.insert(
+                String::from(outputs::OUTPUT),
+                NodeTypeOutput {
+                    name: String::from(outputs::OUTPUT),
+                    display_name: String::from("Output"),
+                    description: String::from("The clip itself"),
+                    property_type: PipeableType {
+                        video: i32::MAX,
+                        audio: i32::MAX,
+                        subtitles: i32::MAX,
+                    },
+                    format: StreamFormat::default(),
+                    number_stream: None,
+                },
+            );
+            return Ok((inputs, hm));
+        }
+        let clip = clip.unwrap().to_owned();
+        let clip = serde_json::from_value::<ClipIdentifier>(clip);
+        if clip.is_err() {
+            return Err(PipelineError::missing_clip(
+                inputs::CLIP,
+                String::from("Clip identifier is malformed"),
+            ));
+        }
+        let clip = clip.unwrap();
+        let property_type;
+        let format;
+        match clip.clip_type {
+            ClipType::Source => {
+                // If it's a source clip, we get the relevant source clip from the store, and we get its clip type directly (by looking at the file)
+                let source_clip = store.clips.source.get(&clip.id);
+                if source_clip.is_none() {
+                    return Err(PipelineError::missing_clip(
+                        inputs::CLIP,
+                        String::from("Clip ID is invalid"),
+                    ));
+                }
+                let source_clip = source_clip.unwrap();
+                property_type = source_clip.get_clip_type();
+                let auto_rotate =
+                    get_number_property(properties, inputs::AUTO_ROTATE, 0.0) as i64 == 1;
+                format = if auto_rotate {
+                    source_clip
+                        .get_stream_format()
+                        .rotate(source_clip.get_orientation().rotation)
+                } else {
+                    source_clip.get_stream_format()
+                };
+            }
+            ClipType::Composited => {
+                let composited_clip_type = composited_clip_types.get(&clip.id);
+                if composited_clip_type.is_none() {
+                    return Err(PipelineError::missing_clip(
+                        inputs::CLIP,
+                        String::from("composited Clip type is invalid"),
+                    ));
+                }
+                let composited_clip_type = composited_clip_type.unwrap();
+
+                property_type = composited_clip_type.stream_type;
+                format = composited_clip_type.format;
+            }
+        }
         let mut hm = HashMap::new();
         hm.insert(
             String::from(outputs::OUTPUT),
@@ -63,165 +142,126 @@ fn get_io(
                 name: String::from(outputs::OUTPUT),
                 display_name: String::from("Output"),
                 description: String::from("The clip itself"),
-                property_type: PipeableType {
-                    video: i32::MAX,
-                    audio: i32::MAX,
-                    subtitles: i32::MAX,
-                },
+                property_type: property_type,
+                format,
+                number_stream: None,
             },
         );
         return Ok((inputs, hm));
     }
-    let clip = clip.unwrap().to_owned();
-    let clip = serde_json::from_value::<ClipIdentifier>(clip);
-    if clip.is_err() {
-        return Err(String::from("Clip identifier is malformed"));
-    }
-    let clip = clip.unwrap();
-    let property_type;
-    match clip.clip_type {
-        ClipType::Source => {
-            // If it's a source clip, we get the relevant source clip from the store, and we get its clip type directly (by looking at the file)
-            let source_clip = store.clips.source.get(&clip.id);
-            if source_clip.is_none() {
-                return Err(String::from("Clip ID is invalid"));
-            }
-            let source_clip = source_clip.unwrap();
-            property_type = source_clip.get_clip_type();
-        }
-        ClipType::Composited => {
-            let composited_clip_type = composited_clip_types.get(&clip.id);
-            if composited_clip_type.is_none() {
-                return Err(String::from("composited Clip type is invalid"));
-            }
-            let composited_clip_type = composited_clip_type.unwrap();
 
-            property_type = composited_clip_type.stream_type;
+    fn get_output(&self, context: &NodeContext) -> Result<NodeTimelines, PipelineError> {
+        let NodeContext {
+            node_id,
+            properties,
+            store,
+            ..
+        } = *context;
+        let io = self.get_io(context);
+        if io.is_err() {
+            return Err(io.unwrap_err());
         }
-    }
-    let mut hm = HashMap::new();
-    hm.insert(
-        String::from(outputs::OUTPUT),
-        NodeTypeOutput {
-            name: String::from(outputs::OUTPUT),
-            display_name: String::from("Output"),
-            description: String::from("The clip itself"),
-            property_type: property_type,
-        },
-    );
-    return Ok((inputs, hm));
-}
-fn get_output(
-    node_id: ID,
-    properties: &HashMap<String, Value>,
-    piped_inputs: &HashMap<String, PipedType>,
-    composited_clip_types: &HashMap<ID, PipedType>,
-    store: &Store,
-    node_register: &NodeRegister,
-) -> Result<(HashMap<String, ges::Timeline>, Vec<MemorySafetyWrapper>), String> {
-    let io = get_io(
-        node_id.clone(),
-        properties,
-        piped_inputs,
-        composited_clip_types,
-        store,
-        node_register,
-    );
-    if io.is_err() {
-        return Err(io.unwrap_err());
-    }
 
-    let (_, outputs) = io.unwrap();
+        let (_, outputs) = io.unwrap();
 
-    let clip_identifier = get_clip_identifier(properties);
-    if clip_identifier.is_err() {
-        return Err(clip_identifier.unwrap_err());
-    }
-    let clip_identifier = clip_identifier.unwrap();
-
-    let output = outputs.get(outputs::OUTPUT).unwrap();
-    let output = PipedType {
-        node_id: node_id.clone(),
-        io: node::InputOrOutput::Output,
-        stream_type: output.property_type,
-        property_name: outputs::OUTPUT.to_string(),
-        cache_id: None,
-    };
-
-    let (timeline, mem_safety) = match clip_identifier.clip_type {
-        ClipType::Source => {
-            let clip = store.clips.source.get(&clip_identifier.id).unwrap();
-
-            let timeline = output.stream_type.create_timeline();
-            let layer = timeline.append_layer();
-
-            let location = clip.get_server_url();
-            ges::Asset::needs_reload(ges::UriClip::static_type(), Some(location.as_str()));
-            let clip = ges::UriClipAsset::request_sync(location.as_str()).unwrap();
-            layer
-                .add_asset(&clip, None, None, None, ges::TrackType::UNKNOWN)
-                .unwrap();
-            (timeline, vec![MemorySafetyWrapper::UriClipAsset(clip)])
-        }
-        ClipType::Composited => {
-            let clip = store.clips.composited.get(&clip_identifier.id).unwrap();
-
-            let timeline = output.stream_type.create_timeline();
-            let layer = timeline.append_layer();
-
-            let location = clip.get_location();
-            ges::Asset::needs_reload(ges::UriClip::static_type(), Some(location.as_str()));
-            let clip = ges::UriClipAsset::request_sync(location.as_str()).unwrap();
-            layer
-                .add_asset(&clip, None, None, None, ges::TrackType::UNKNOWN)
-                .unwrap();
-            (timeline, vec![MemorySafetyWrapper::UriClipAsset(clip)])
+        let clip_identifier = get_clip_identifier(properties);
+        if clip_identifier.is_err() {
+            return Err(PipelineError::missing_clip(
+                inputs::CLIP,
+                clip_identifier.unwrap_err(),
+            ));
         }
-    };
+        let clip_identifier = clip_identifier.unwrap();
+
+        let output = outputs.get(outputs::OUTPUT).unwrap();
+        let output = PipedType {
+            node_id: node_id.clone(),
+            io: node::InputOrOutput::Output,
+            stream_type: output.property_type,
+            property_name: outputs::OUTPUT.to_string(),
+            cache_id: None,
+            format: output.format,
+        };
 
-    let mut hm = HashMap::new();
-    hm.insert(outputs::OUTPUT.to_string(), timeline);
-    Ok((hm, mem_safety))
+        let (timeline, mem_safety) = match clip_identifier.clip_type {
+            ClipType::Source => {
+                let clip = store.clips.source.get(&clip_identifier.id);
+                if clip.is_none() {
+                    return Err(PipelineError::missing_clip(
+                        inputs::CLIP,
+                        String::from("Clip ID is invalid"),
+                    ));
+                }
+                let clip = clip.unwrap();
+                let orientation = clip.get_orientation();
+
+                let timeline = output.create_timeline();
+                let layer = timeline.append_layer();
+
+                let location = clip.get_server_url();
+                ges::Asset::needs_reload(ges::UriClip::static_type(), Some(location.as_str()));
+                let clip = ges::UriClipAsset::request_sync(location.as_str())?;
+                let timeline_clip =
+                    layer.add_asset(&clip, None, None, None, ges::TrackType::UNKNOWN)?;
+
+                let mut mem_safety = vec![MemorySafetyWrapper::UriClipAsset(clip)];
+                let auto_rotate =
+                    get_number_property(properties, inputs::AUTO_ROTATE, 0.0) as i64 == 1;
+                if auto_rotate && !orientation.is_identity() {
+                    let method = video_flip_method(orientation.rotation, orientation.flip, false);
+                    if method.is_err() {
+                        return Err(method.unwrap_err().into());
+                    }
+                    let effect =
+                        ges::Effect::new(format!("videoflip method={}", method.unwrap()).as_str())?;
+                    timeline_clip.add(&effect)?;
+                    mem_safety.push(MemorySafetyWrapper::Effect(effect));
+                }
+                (timeline, mem_safety)
+            }
+            ClipType::Composited => {
+                let clip = store.clips.composited.get(&clip_identifier.id);
+                if clip.is_none() {
+                    return Err(PipelineError::missing_clip(
+                        inputs::CLIP,
+                        String::from("Clip ID is invalid"),
+                    ));
+                }
+                let clip = clip.unwrap();
+
+                let timeline = output.create_timeline();
+                let layer = timeline.append_layer();
+
+                let location = clip.get_location();
+                ges::Asset::needs_reload(ges::UriClip::static_type(), Some(location.as_str()));
+                let clip = ges::UriClipAsset::request_sync(location.as_str())?;
+                layer.add_asset(&clip, None, None, None, ges::TrackType::UNKNOWN)?;
+                (timeline, vec![MemorySafetyWrapper::UriClipAsset(clip)])
+            }
+        };
+
+        let mut hm = HashMap::new();
+        hm.insert(outputs::OUTPUT.to_string(), timeline);
+        Ok((hm, mem_safety))
+    }
 }
 
 pub fn media_import_node() -> NodeType {
-    NodeType {
-        id: String::from(IDENTIFIER),
-        display_name: String::from("Clip Import"),
-        description: String::from("Import a source or composited clip"),
-        default_properties: default_properties(),
-
-        get_io: |node_id: ID,
-                 properties: &HashMap<String, Value>,
-                 piped_inputs: &HashMap<String, PipedType>,
-                 composited_clip_types: &HashMap<ID, PipedType>,
-                 store: &Store,
-                 node_register: &NodeRegister| {
-            return get_io(
-                node_id,
-                properties,
-                piped_inputs,
-                composited_clip_types,
-                store,
-                node_register,
-            );
-        },
-        get_output: |node_id: ID,
-                     properties: &HashMap<String, Value>,
-                     piped_inputs: &HashMap<String, PipedType>,
-                     composited_clip_types: &HashMap<ID, PipedType>,
-                     store: &Store,
-                     node_register: &NodeRegister| {
-            return get_output(
-                node_id,
-                properties,
-                piped_inputs,
-                composited_clip_types,
-                store,
-                node_register,
-            );
-        },
-    }
+    NodeType::new(
+        IDENTIFIER,
+        "Clip Import",
+        "Import a source or composited clip",
+        NodeCategory::Generator,
+        MediaImportNode,
+    )
+    .with_tags(&["clip", "source", "input", "file"])
+    .with_version("1.1.0")
+    .with_migration("1.0.0", "1.1.0", |properties| {
+        // clips were never rotated before auto rotate existed, so keep old projects unrotated
+        if !properties.contains_key(inputs::AUTO_ROTATE) {
+            properties.insert(String::from(inputs::AUTO_ROTATE), Value::from(0.0));
+        }
+    })
 }
 
 pub fn get_clip_identifier(properties: &HashMap<String, Value>) -> Result<ClipIdentifier, String> {
@@ -238,3 +278,25 @@ pub fn get_clip_identifier(properties: &HashMap<String, Value>) -> Result<ClipId
 
     Ok(clip)
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{inputs, media_import_node};
+    use crate::node::Node;
+
+    #[test]
+    fn test_migrate_auto_rotate() {
+        let mut node = Node::new(String::from(super::IDENTIFIER), None);
+        media_import_node().migrate(&mut node);
+        assert_eq!(node.properties.get(inputs::AUTO_ROTATE), Some(&json!(0.0)));
+        assert_eq!(node.type_version, "1.1.0");
+
+        let mut node = Node::new(String::from(super::IDENTIFIER), None);
+        node.properties
+            .insert(String::from(inputs::AUTO_ROTATE), json!(1.0));
+        media_import_node().migrate(&mut node);
+        assert_eq!(node.properties.get(inputs::AUTO_ROTATE), Some(&json!(1.0)));
+    }
+}
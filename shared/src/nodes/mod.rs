@@ -1,21 +1,72 @@
 pub mod volume_node;
+pub mod audio_sync_node;
 pub mod blur_node;
 pub mod concat_node;
+pub mod ducking_node;
+pub mod effect_node;
+pub mod expression_node;
+pub mod framerate_node;
+pub mod grid_node;
+pub mod lfo_node;
+pub mod loop_node;
 pub mod media_import_node;
+pub mod node_group_node;
 pub mod output_node;
+pub mod rotate_node;
+pub mod silence_removal_node;
+pub mod switch_node;
+pub mod timecode_node;
+pub mod visualiser_node;
+pub mod watermark_node;
 
 use self::{
-    volume_node::volume_node, blur_node::blur_node, concat_node::concat_node,
-    output_node::output_node,
+    volume_node::volume_node, audio_sync_node::audio_sync_node, blur_node::blur_node,
+    concat_node::concat_node, ducking_node::ducking_node, expression_node::expression_node,
+    framerate_node::framerate_node, grid_node::grid_node, lfo_node::lfo_node,
+    loop_node::loop_node, node_group_node::node_group_node, output_node::output_node,
+    rotate_node::rotate_node, silence_removal_node::silence_removal_node,
+    switch_node::switch_node, timecode_node::timecode_node, visualiser_node::visualiser_node,
+    watermark_node::watermark_node,
 };
 
-use super::node::NodeType;
+use super::keyframes::AnimatedNumber;
+use super::node::{Node, NodeCategory, NodeType, NodeTypeInput, Restrictions, Type};
+use crate::clip::ClipType;
+use crate::constants::node_definitions_location;
+use crate::nodes::effect_node::{effect_node, load_effect_definitions, EffectDefinition};
 use crate::nodes::media_import_node::media_import_node;
+use crate::store::Store;
+use serde_json::{json, Map, Value};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 
 pub type NodeRegister = HashMap<String, NodeType>;
 
-pub fn get_node_register() -> NodeRegister {
+/// The property used by nodes which require an analysis pass on the server, to record what the stored analysis results were computed from
+pub const ANALYSIS_KEY: &str = "analysis_key";
+
+/**
+ * Loads the definitions of the effect node types from the node definitions directory, so they can be given to `get_node_register`
+ * Returns why any of the definitions could not be loaded
+ */
+pub fn load_node_definitions() -> (Vec<EffectDefinition>, Vec<String>) {
+    let mut definitions = Vec::new();
+    let mut errors = Vec::new();
+    for definition in load_effect_definitions(&node_definitions_location()) {
+        match definition {
+            Ok(definition) => definitions.push(definition),
+            Err(error) => errors.push(format!("Could not load node definition {}", error)),
+        }
+    }
+    (definitions, errors)
+}
+
+/**
+ * Gets the register of every node type, including an effect node type for each of the definitions
+ * Returns why any of the definitions could not be added
+ */
+pub fn get_node_register(definitions: &[EffectDefinition]) -> (NodeRegister, Vec<String>) {
     let mut register = HashMap::new();
 
     register.insert(
@@ -26,6 +77,439 @@ pub fn get_node_register() -> NodeRegister {
     register.insert(String::from(concat_node::IDENTIFIER), concat_node());
     register.insert(String::from(blur_node::IDENTIFIER), blur_node());
     register.insert(String::from(volume_node::IDENTIFIER), volume_node());
+    register.insert(String::from(audio_sync_node::IDENTIFIER), audio_sync_node());
+    register.insert(
+        String::from(silence_removal_node::IDENTIFIER),
+        silence_removal_node(),
+    );
+    register.insert(String::from(framerate_node::IDENTIFIER), framerate_node());
+    register.insert(String::from(rotate_node::IDENTIFIER), rotate_node());
+    register.insert(String::from(watermark_node::IDENTIFIER), watermark_node());
+    register.insert(String::from(timecode_node::IDENTIFIER), timecode_node());
+    register.insert(String::from(grid_node::IDENTIFIER), grid_node());
+    register.insert(String::from(ducking_node::IDENTIFIER), ducking_node());
+    register.insert(String::from(visualiser_node::IDENTIFIER), visualiser_node());
+    register.insert(String::from(loop_node::IDENTIFIER), loop_node());
+    register.insert(String::from(switch_node::IDENTIFIER), switch_node());
+    register.insert(String::from(lfo_node::IDENTIFIER), lfo_node());
+    register.insert(
+        String::from(expression_node::IDENTIFIER),
+        expression_node(),
+    );
+    register.insert(
+        String::from(node_group_node::IDENTIFIER),
+        node_group_node(),
+    );
+
+    // simple effect nodes can also be defined in files, so they can be added without recompiling
+    let mut errors = Vec::new();
+    for definition in definitions {
+        match effect_node(definition.clone()) {
+            Ok(node_type) if register.contains_key(&node_type.id) => errors.push(format!(
+                "Skipping node definition {}, since a node type with that id already exists",
+                node_type.id
+            )),
+            Ok(node_type) => {
+                register.insert(node_type.id.clone(), node_type);
+            }
+            Err(error) => errors.push(format!(
+                "Could not load node definition {}: {}",
+                definition.id, error
+            )),
+        }
+    }
+
+    (register, errors)
+}
 
+/**
+ * Filters an already loaded register down to the node types in one category, or copies all of it if no category is given
+ */
+pub fn filter_node_register(
+    register: &NodeRegister,
+    category: Option<NodeCategory>,
+) -> NodeRegister {
     register
+        .iter()
+        .filter(|(_, node_type)| {
+            category.map_or(true, |category| node_type.category == category)
+        })
+        .map(|(id, node_type)| (id.clone(), node_type.clone()))
+        .collect()
+}
+
+/**
+ * Gets the ids of the node types listed in each category of the palette, sorted by display name
+ * Group nodes are listed once for each saved node group instead, so are left out
+ */
+pub fn get_node_palette(register: &NodeRegister) -> Vec<(NodeCategory, Vec<String>)> {
+    NodeCategory::ALL
+        .iter()
+        .map(|category| {
+            let mut node_types: Vec<&NodeType> = register
+                .values()
+                .filter(|node_type| {
+                    node_type.category == *category && node_type.id != node_group_node::IDENTIFIER
+                })
+                .collect();
+            node_types.sort_by(|a, b| a.display_name.cmp(&b.display_name));
+            (
+                *category,
+                node_types.iter().map(|node_type| node_type.id.clone()).collect(),
+            )
+        })
+        .collect()
+}
+
+/**
+ * Gets the value of a numeric property, or the default value if it has not been set or is not a number
+ * Keyframed properties give their value at the start; nodes which support keyframes should use `AnimatedNumber` instead
+ */
+pub fn get_number_property(properties: &HashMap<String, Value>, name: &str, default: f64) -> f64 {
+    match properties.get(name) {
+        Some(Value::Number(value)) => value.as_f64().unwrap_or(default),
+        Some(value) => match AnimatedNumber::from_value(value) {
+            Ok(number) => number.initial_value(),
+            Err(_) => default,
+        },
+        _ => default,
+    }
+}
+
+/**
+ * Gets the number of inputs of a node whose inputs can be added and removed, rounded to a whole number within the node's limits
+ */
+pub fn get_number_of_inputs(
+    properties: &HashMap<String, Value>,
+    name: &str,
+    default: i32,
+    min: i32,
+    max: i32,
+) -> i32 {
+    let number_of_inputs = get_number_property(properties, name, default as f64);
+    (number_of_inputs.round() as i32).clamp(min, max)
+}
+
+/**
+ * Gets the value of a boolean property, or the default value if it has not been set
+ */
+pub fn get_bool_property(properties: &HashMap<String, Value>, name: &str, default: bool) -> bool {
+    match properties.get(name) {
+        Some(Value::Bool(value)) => *value,
+        _ => default,
+    }
+}
+
+/**
+ * Gets a key for what is linked into an input of a node, made from the link and everything upstream of it (the types and properties of the nodes, and the clips they import)
+ * Analysis results are stored against this key, so they are computed again when anything which affects the input changes, rather than only when it is relinked
+ * Returns `None` if nothing is linked into the input
+ */
+pub fn get_input_key(node: &Node, input: &str, store: &Store) -> Option<String> {
+    let link = store
+        .pipeline
+        .links
+        .iter()
+        .find(|link| link.to.node_id == node.id && link.to.property == input)?;
+
+    let mut upstream = Map::new();
+    let mut nodes_to_visit = vec![link.from.node_id];
+    while let Some(id) = nodes_to_visit.pop() {
+        if upstream.contains_key(&id.to_string()) {
+            continue;
+        }
+        let upstream_node = store.nodes.get(&id);
+        if upstream_node.is_none() {
+            continue;
+        }
+        let upstream_node = upstream_node.unwrap();
+
+        let mut links: Vec<String> = Vec::new();
+        for upstream_link in &store.pipeline.links {
+            if upstream_link.to.node_id == id {
+                links.push(upstream_link.get_id());
+                nodes_to_visit.push(upstream_link.from.node_id);
+            }
+        }
+        links.sort();
+
+        // composited clips are made by the nodes linked into their output nodes
+        if upstream_node.node_type == media_import_node::IDENTIFIER {
+            if let Ok(clip) = media_import_node::get_clip_identifier(&upstream_node.properties) {
+                if clip.clip_type == ClipType::Composited {
+                    for (output_id, output) in &store.nodes {
+                        if output.node_type == output_node::IDENTIFIER
+                            && media_import_node::get_clip_identifier(&output.properties)
+                                .map_or(false, |output_clip| output_clip.id == clip.id)
+                        {
+                            nodes_to_visit.push(*output_id);
+                        }
+                    }
+                }
+            }
+        }
+
+        let group = node_group_node::get_node_group(&upstream_node.properties, store).ok();
+        // converting to a `Value` sorts the properties, so the key does not depend on the order of the hashmaps
+        upstream.insert(
+            id.to_string(),
+            json!({
+                "node_type": upstream_node.node_type,
+                "bypassed": upstream_node.bypassed,
+                "properties": upstream_node.properties,
+                "links": links,
+                "group": group,
+            }),
+        );
+    }
+
+    let mut hasher = DefaultHasher::new();
+    Value::Object(upstream).to_string().hash(&mut hasher);
+    Some(format!("{}#{:x}", link.get_id(), hasher.finish()))
+}
+
+/**
+ * Whether a property of a node can be keyframed; a property of a group node can be if the property inside the group it stands for can be
+ */
+pub fn is_animatable(
+    node: &Node,
+    property: &str,
+    store: &Store,
+    node_register: &NodeRegister,
+) -> bool {
+    if node.node_type == node_group_node::IDENTIFIER {
+        let group = node_group_node::get_node_group(&node.properties, store);
+        if group.is_err() {
+            return false;
+        }
+        let group = group.unwrap();
+        let group_property = group
+            .properties
+            .iter()
+            .find(|group_property| group_property.name == property);
+        if group_property.is_none() {
+            return false;
+        }
+        let endpoint = &group_property.unwrap().endpoint;
+        // groups cannot contain group nodes, so the inner node is never a group node itself
+        return match group.nodes.get(&endpoint.node_id) {
+            Some(inner_node) => is_animatable(inner_node, &endpoint.property, store, node_register),
+            None => false,
+        };
+    }
+
+    match node_register.get(&node.node_type) {
+        Some(node_type) => node_type
+            .animatable
+            .iter()
+            .any(|animatable| animatable == property),
+        None => false,
+    }
+}
+
+/**
+ * Shorthand for creating a numeric input
+ */
+pub fn number_input(
+    name: &str,
+    display_name: &str,
+    description: &str,
+    restrictions: Restrictions,
+) -> NodeTypeInput {
+    NodeTypeInput {
+        name: String::from(name),
+        display_name: String::from(display_name),
+        description: String::from(description),
+        property_type: Type::Number(restrictions),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::{
+        blur_node::{self, blur_node},
+        filter_node_register, get_input_key, get_node_palette, get_number_of_inputs, is_animatable,
+        media_import_node,
+        node_group_node::{self, node_group_node},
+        rotate_node::{self, rotate_node},
+        volume_node,
+    };
+    use crate::{
+        node::{Node, NodeCategory},
+        node_group::{GroupEndpoint, NodeGroup},
+        pipeline::{Link, LinkEndpoint},
+        store::Store,
+    };
+
+    #[test]
+    fn test_filter_node_register() {
+        let mut register = HashMap::new();
+        for node_type in vec![
+            rotate_node(),
+            blur_node(),
+            volume_node::volume_node(),
+            node_group_node(),
+        ] {
+            register.insert(node_type.id.clone(), node_type);
+        }
+
+        let video = filter_node_register(&register, Some(NodeCategory::Video));
+        assert_eq!(video.len(), 2);
+        assert!(video.contains_key("blur") && video.contains_key("rotate"));
+        assert_eq!(filter_node_register(&register, None).len(), 4);
+
+        let palette = get_node_palette(&register);
+        assert_eq!(
+            palette[0],
+            (
+                NodeCategory::Video,
+                vec![String::from("blur"), String::from("rotate")]
+            )
+        );
+        assert_eq!(
+            palette[1],
+            (
+                NodeCategory::Audio,
+                vec![String::from(volume_node::IDENTIFIER)]
+            )
+        );
+        assert!(palette[2].1.is_empty());
+        // group nodes are listed by node group instead
+        assert!(palette[3].1.is_empty());
+    }
+
+    #[test]
+    fn test_get_input_key() {
+        let mut store = Store::new();
+        let mut import = Node::new(String::from(media_import_node::IDENTIFIER), None);
+        import.properties.insert(
+            String::from(media_import_node::inputs::CLIP),
+            json!({"id": "00000000-0000-0000-0000-000000000001", "clip_type": "Source"}),
+        );
+        let blur = Node::new(String::from(blur_node::IDENTIFIER), None);
+        let link = |from: &Node, output: &str, to: &Node, input: &str| Link {
+            from: LinkEndpoint {
+                node_id: from.id,
+                property: String::from(output),
+            },
+            to: LinkEndpoint {
+                node_id: to.id,
+                property: String::from(input),
+            },
+        };
+        let target = Node::new(String::from(blur_node::IDENTIFIER), None);
+        store.pipeline.links.push(link(
+            &import,
+            media_import_node::outputs::OUTPUT,
+            &blur,
+            blur_node::inputs::MEDIA,
+        ));
+        store.pipeline.links.push(link(
+            &blur,
+            blur_node::outputs::OUTPUT,
+            &target,
+            blur_node::inputs::MEDIA,
+        ));
+        for node in [&import, &blur, &target] {
+            store.nodes.insert(node.id, node.clone());
+        }
+
+        let key = get_input_key(&target, blur_node::inputs::MEDIA, &store).unwrap();
+        assert!(get_input_key(&target, "missing", &store).is_none());
+
+        // moving a node does not change what it outputs
+        store.nodes.get_mut(&blur.id).unwrap().position.x = 100;
+        assert_eq!(
+            get_input_key(&target, blur_node::inputs::MEDIA, &store).unwrap(),
+            key
+        );
+
+        // swapping the clip two nodes upstream does, even though the links are the same
+        store.nodes.get_mut(&import.id).unwrap().properties.insert(
+            String::from(media_import_node::inputs::CLIP),
+            json!({"id": "00000000-0000-0000-0000-000000000002", "clip_type": "Source"}),
+        );
+        assert_ne!(
+            get_input_key(&target, blur_node::inputs::MEDIA, &store).unwrap(),
+            key
+        );
+    }
+
+    #[test]
+    fn test_get_number_of_inputs() {
+        let mut properties = HashMap::new();
+        assert_eq!(get_number_of_inputs(&properties, "inputs", 4, 2, 8), 4);
+        properties.insert(String::from("inputs"), json!(2.6));
+        assert_eq!(get_number_of_inputs(&properties, "inputs", 4, 2, 8), 3);
+        properties.insert(String::from("inputs"), json!(100));
+        assert_eq!(get_number_of_inputs(&properties, "inputs", 4, 2, 8), 8);
+        properties.insert(String::from("inputs"), json!(0));
+        assert_eq!(get_number_of_inputs(&properties, "inputs", 4, 2, 8), 2);
+    }
+
+    #[test]
+    fn test_is_animatable() {
+        let mut register = HashMap::new();
+        for node_type in vec![blur_node(), rotate_node(), node_group_node()] {
+            register.insert(node_type.id.clone(), node_type);
+        }
+        let mut store = Store::new();
+        let blur = Node::new(String::from(blur_node::IDENTIFIER), None);
+        assert!(is_animatable(
+            &blur,
+            blur_node::inputs::SIGMA,
+            &store,
+            &register
+        ));
+        assert!(!is_animatable(
+            &blur,
+            blur_node::inputs::MEDIA,
+            &store,
+            &register
+        ));
+
+        let rotate = Node::new(String::from(rotate_node::IDENTIFIER), None);
+        store.nodes.insert(blur.id.clone(), blur.clone());
+        store.nodes.insert(rotate.id.clone(), rotate.clone());
+        let endpoint = |node: &Node, property: &str| GroupEndpoint {
+            name: format!("{}_{}", node.node_type, property),
+            display_name: String::from(property),
+            endpoint: LinkEndpoint {
+                node_id: node.id.clone(),
+                property: String::from(property),
+            },
+        };
+        let group = NodeGroup::from_selection(
+            &store,
+            &[blur.id.clone(), rotate.id.clone()],
+            String::from("Group"),
+            String::new(),
+            Vec::new(),
+            Vec::new(),
+            vec![
+                endpoint(&blur, blur_node::inputs::SIGMA),
+                endpoint(&rotate, rotate_node::inputs::ROTATION),
+            ],
+        )
+        .unwrap();
+        let mut group_node = Node::new(String::from(node_group_node::IDENTIFIER), None);
+        group_node.properties.insert(
+            String::from(node_group_node::inputs::NODE_GROUP),
+            json!(group.id),
+        );
+        store.node_groups.insert(group.id.clone(), group);
+
+        let sigma = format!("{}_{}", blur_node::IDENTIFIER, blur_node::inputs::SIGMA);
+        let rotation = format!(
+            "{}_{}",
+            rotate_node::IDENTIFIER,
+            rotate_node::inputs::ROTATION
+        );
+        assert!(is_animatable(&group_node, &sigma, &store, &register));
+        assert!(!is_animatable(&group_node, &rotation, &store, &register));
+    }
 }
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::{
+    error::PipelineError,
+    node::{
+        NodeCategory, NodeContext, NodeIo, NodeKind, NodeTimelines, NodeType, NodeTypeInput,
+        NodeTypeOutput, Type,
+    },
+    node_group::NodeGroup,
+    store::Store,
+    ID,
+};
+
+pub const IDENTIFIER: &str = "node_group";
+pub mod inputs {
+    pub const NODE_GROUP: &str = "node_group";
+}
+
+/**
+ * Finds the node group which a group node is a copy of
+ */
+pub fn get_node_group<'a>(
+    properties: &HashMap<String, Value>,
+    store: &'a Store,
+) -> Result<&'a NodeGroup, String> {
+    let id = properties
+        .get(inputs::NODE_GROUP)
+        .and_then(|id| serde_json::from_value::<ID>(id.clone()).ok());
+    if id.is_none() {
+        return Err(String::from("No node group has been chosen"));
+    }
+    let group = store.node_groups.get(&id.unwrap());
+    if group.is_none() {
+        return Err(String::from("The node group does not exist"));
+    }
+    Ok(group.unwrap())
+}
+
+pub struct NodeGroupNode;
+
+impl NodeKind for NodeGroupNode {
+    fn default_properties(&self) -> HashMap<String, NodeTypeInput> {
+        let mut default_properties = HashMap::new();
+        default_properties.insert(
+            String::from(inputs::NODE_GROUP),
+            NodeTypeInput {
+                name: String::from(inputs::NODE_GROUP),
+                display_name: String::from("Node Group"),
+                description: String::from("The ID of the node group this node is a copy of"),
+                property_type: Type::String(36),
+            },
+        );
+        default_properties
+    }
+
+    /**
+     * Group nodes are expanded before the pipeline is generated, so this is only used to find the types of the group's inputs when validating; outputs are given the types they have with nothing piped in
+     */
+    fn get_io(&self, context: &NodeContext) -> Result<NodeIo, PipelineError> {
+        let NodeContext {
+            properties,
+            composited_clip_types,
+            store,
+            node_register,
+            ..
+        } = *context;
+        let mut inputs = self.default_properties();
+        let mut outputs = HashMap::new();
+        let group = get_node_group(properties, store)
+            .map_err(|error| PipelineError::from(error).with_property(inputs::NODE_GROUP))?;
+
+        let inner_io = |node_id: &ID| {
+            let node = group.nodes.get(node_id)?;
+            let node_type = node_register.get(&node.node_type)?;
+            node_type
+                .kind
+                .get_io(&NodeContext {
+                    node_id: node.id.clone(),
+                    properties: &node.properties,
+                    piped_inputs: &HashMap::new(),
+                    composited_clip_types,
+                    store,
+                    node_register,
+                })
+                .ok()
+        };
+        for input in group.inputs.iter().chain(&group.properties) {
+            if let Some((inner_inputs, _)) = inner_io(&input.endpoint.node_id) {
+                if let Some(inner_input) = inner_inputs.get(&input.endpoint.property) {
+                    inputs.insert(
+                        input.name.clone(),
+                        NodeTypeInput {
+                            name: input.name.clone(),
+                            display_name: input.display_name.clone(),
+                            ..inner_input.clone()
+                        },
+                    );
+                }
+            }
+        }
+        for output in &group.outputs {
+            if let Some((_, inner_outputs)) = inner_io(&output.endpoint.node_id) {
+                if let Some(inner_output) = inner_outputs.get(&output.endpoint.property) {
+                    outputs.insert(
+                        output.name.clone(),
+                        NodeTypeOutput {
+                            name: output.name.clone(),
+                            display_name: output.display_name.clone(),
+                            ..inner_output.clone()
+                        },
+                    );
+                }
+            }
+        }
+
+        Ok((inputs, outputs))
+    }
+
+    fn get_output(&self, _context: &NodeContext) -> Result<NodeTimelines, PipelineError> {
+        Err(PipelineError::from(String::from(
+            "Group nodes are replaced with the nodes in their group before the pipeline is generated",
+        )))
+    }
+}
+
+pub fn node_group_node() -> NodeType {
+    NodeType::new(
+        IDENTIFIER,
+        "Node Group",
+        "A copy of a saved group of nodes",
+        NodeCategory::Utility,
+        NodeGroupNode,
+    )
+    .with_tags(&["group", "reuse"])
+}
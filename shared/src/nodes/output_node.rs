@@ -9,15 +9,14 @@ use serde_json::Value;
 
 use crate::{
     clip::{ClipIdentifier, CompositedClip},
+    error::PipelineError,
     node::{
-        MemorySafetyWrapper, NodeType, NodeTypeInput, NodeTypeOutput, PipeableType, PipedType, Type,
+        NodeCategory, NodeContext, NodeIo, NodeKind, NodeTimelines, NodeType, NodeTypeInput,
+        PipeableType, Type,
     },
     store::Store,
-    ID,
 };
 
-use super::NodeRegister;
-
 pub const IDENTIFIER: &str = "output";
 pub mod inputs {
     pub const MEDIA: &str = "media";
@@ -25,151 +24,125 @@ pub mod inputs {
 }
 pub mod outputs {}
 
-fn default_properties() -> HashMap<String, NodeTypeInput> {
-    let mut default_properties = HashMap::new();
-
-    default_properties.insert(
-        String::from(inputs::MEDIA),
-        NodeTypeInput {
-            name: String::from(inputs::MEDIA),
-            display_name: String::from("Media"),
-            description: String::from("Media to output to clip"),
-            property_type: Type::Pipeable(
-                PipeableType {
-                    video: 0,
-                    audio: 0,
-                    subtitles: 0,
-                },
-                PipeableType {
-                    video: i32::MAX,
-                    audio: i32::MAX,
-                    subtitles: i32::MAX,
-                },
-            ),
-        },
-    );
-
-    default_properties.insert(
-        String::from(inputs::CLIP),
-        NodeTypeInput {
-            name: String::from(inputs::CLIP),
-            display_name: String::from("Clip"),
-            description: String::from("Clip to output"),
-            property_type: Type::Clip,
-        },
-    );
-    default_properties
-}
-
-fn get_io(
-    _node_id: ID,
-    _properties: &HashMap<String, Value>,
-    _piped_inputs: &HashMap<String, PipedType>,
-    _composited_clip_types: &HashMap<ID, PipedType>,
-    _store: &Store,
-    _node_register: &NodeRegister,
-) -> Result<
-    (
-        HashMap<String, NodeTypeInput>,
-        HashMap<String, NodeTypeOutput>,
-    ),
-    String,
-> {
-    let inputs = default_properties();
-    let outputs = HashMap::new();
-    return Ok((inputs, outputs));
-}
-fn get_output(
-    _node_id: ID,
-    properties: &HashMap<String, Value>,
-    piped_inputs: &HashMap<String, PipedType>,
-    _composited_clip_types: &HashMap<ID, PipedType>,
-    store: &Store,
-    _node_register: &NodeRegister,
-) -> Result<(HashMap<String, ges::Timeline>, Vec<MemorySafetyWrapper>), String> {
-    let media = piped_inputs.get(inputs::MEDIA);
-    if media.is_none() {
-        return Err(format!("Media is none!"));
-    }
-    let media = media.unwrap();
-    let clip = get_clip(properties, store);
-    if clip.is_err() {
-        return Err(clip.unwrap_err());
+pub struct OutputNode;
+
+impl NodeKind for OutputNode {
+    fn default_properties(&self) -> HashMap<String, NodeTypeInput> {
+        let mut default_properties = HashMap::new();
+
+        default_properties.insert(
+            String::from(inputs::MEDIA),
+            NodeTypeInput {
+                name: String::from(inputs::MEDIA),
+                display_name: String::from("Media"),
+                description: String::from("Media to output to clip"),
+                property_type: Type::Pipeable(
+                    PipeableType {
+                        video: 0,
+                        audio: 0,
+                        subtitles: 0,
+                    },
+                    PipeableType {
+                        video: i32::MAX,
+                        audio: i32::MAX,
+                        subtitles: i32::MAX,
+                    },
+                ),
+            },
+        );
+
+        default_properties.insert(
+            String::from(inputs::CLIP),
+            NodeTypeInput {
+                name: String::from(inputs::CLIP),
+                display_name: String::from("Clip"),
+                description: String::from("Clip to output"),
+                property_type: Type::Clip,
+            },
+        );
+        default_properties
     }
-    let clip = clip.unwrap();
-
-    let output_location = clip.get_location();
-
-    let timeline = media.stream_type.create_timeline();
 
-    ges::Asset::needs_reload(
-        ges::UriClip::static_type(),
-        Some(media.get_gst_save_location_with_cache().as_str()),
-    );
-    let clip =
-        ges::UriClipAsset::request_sync(media.get_gst_save_location_with_cache().as_str()).unwrap();
-
-    let layer = timeline.append_layer();
-    layer
-        .add_asset(&clip, None, None, None, TrackType::UNKNOWN)
-        .unwrap();
-
-    timeline
-        .save_to_uri(output_location.as_str(), None as Option<&ges::Asset>, true)
-        .unwrap();
+    fn get_io(&self, _context: &NodeContext) -> Result<NodeIo, PipelineError> {
+        let inputs = self.default_properties();
+        let outputs = HashMap::new();
+        return Ok((inputs, outputs));
+    }
 
-    ges::Asset::needs_reload(ges::UriClip::static_type(), Some(output_location.as_str()));
-    Ok((HashMap::new(), vec![]))
+    fn get_output(&self, context: &NodeContext) -> Result<NodeTimelines, PipelineError> {
+        let NodeContext {
+            properties,
+            piped_inputs,
+            store,
+            ..
+        } = *context;
+        let media = piped_inputs.get(inputs::MEDIA);
+        if media.is_none() {
+            return Err(PipelineError::missing_input(inputs::MEDIA));
+        }
+        let media = media.unwrap();
+        let clip = get_clip(properties, store)?;
+
+        let output_location = clip.get_location();
+
+        let timeline = media.create_timeline();
+
+        ges::Asset::needs_reload(
+            ges::UriClip::static_type(),
+            Some(media.get_gst_save_location_with_cache().as_str()),
+        );
+        let clip =
+            ges::UriClipAsset::request_sync(media.get_gst_save_location_with_cache().as_str())?;
+
+        let layer = timeline.append_layer();
+        layer.add_asset(&clip, None, None, None, TrackType::UNKNOWN)?;
+
+        timeline.save_to_uri(output_location.as_str(), None as Option<&ges::Asset>, true)?;
+
+        ges::Asset::needs_reload(ges::UriClip::static_type(), Some(output_location.as_str()));
+        Ok((HashMap::new(), vec![]))
+    }
 }
 
 pub fn output_node() -> NodeType {
-    NodeType {
-        id: String::from(IDENTIFIER),
-        display_name: String::from("Output"),
-        description: String::from("Output media to a clip"),
-        default_properties: default_properties(),
-        get_io: |node_id: ID,
-                 properties: &HashMap<String, Value>,
-                 piped_inputs: &HashMap<String, PipedType>,
-                 composited_clip_types: &HashMap<ID, PipedType>,
-                 store: &Store,
-                 node_register: &NodeRegister| {
-            return get_io(
-                node_id,
-                properties,
-                piped_inputs,
-                composited_clip_types,
-                store,
-                node_register,
-            );
-        },
-        get_output: |node_id: ID,
-                     properties: &HashMap<String, Value>,
-                     piped_inputs: &HashMap<String, PipedType>,
-                     composited_clip_types: &HashMap<ID, PipedType>,
-                     store: &Store,
-                     node_register: &NodeRegister| {
-            return get_output(
-                node_id,
-                properties,
-                piped_inputs,
-                composited_clip_types,
-                store,
-                node_register,
-            );
-        },
-    }
+    NodeType::new(
+        IDENTIFIER,
+        "Output",
+        "Output media to a clip",
+        NodeCategory::Utility,
+        OutputNode,
+    )
+    .with_tags(&["export", "composite", "clip"])
 }
 
+/**
+ * Gets the composited clip the node outputs to
+ */
 pub fn get_clip(
     properties: &HashMap<String, Value>,
     store: &Store,
-) -> Result<CompositedClip, String> {
+) -> Result<CompositedClip, PipelineError> {
     let clip = properties.get(inputs::CLIP);
     if clip.is_none() {
-        return Err(String::from("No clip given"));
+        return Err(PipelineError::missing_clip(
+            inputs::CLIP,
+            String::from("No clip given"),
+        ));
     }
     let clip = clip.unwrap().to_owned();
-    let clip = serde_json::from_value::<ClipIdentifier>(clip).unwrap();
-    return Ok(store.clips.composited.get(&clip.id).unwrap().clone());
+    let clip = serde_json::from_value::<ClipIdentifier>(clip);
+    if clip.is_err() {
+        return Err(PipelineError::missing_clip(
+            inputs::CLIP,
+            String::from("Clip identifier is malformed"),
+        ));
+    }
+    let clip = store.clips.composited.get(&clip.unwrap().id);
+    if clip.is_none() {
+        return Err(PipelineError::missing_clip(
+            inputs::CLIP,
+            String::from("Clip ID is invalid"),
+        ));
+    }
+    Ok(clip.unwrap().clone())
 }
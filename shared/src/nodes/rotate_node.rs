@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::PipelineError,
+    node::{
+        NodeCategory, NodeContext, NodeIo, NodeKind, NodeTimelines, NodeType, NodeTypeInput,
+        PipeableType, Restrictions, Type,
+    },
+};
+
+use super::get_number_property;
+
+pub const IDENTIFIER: &str = "rotate";
+pub mod inputs {
+    pub const MEDIA: &str = "media";
+    pub const ROTATION: &str = "rotation";
+    pub const FLIP_HORIZONTAL: &str = "flip_horizontal";
+    pub const FLIP_VERTICAL: &str = "flip_vertical";
+}
+pub mod outputs {
+    pub const OUTPUT: &str = "output";
+}
+
+pub struct RotateNode;
+
+impl NodeKind for RotateNode {
+    fn default_properties(&self) -> HashMap<String, NodeTypeInput> {
+        let mut default_properties = HashMap::new();
+
+        default_properties.insert(
+            String::from(inputs::MEDIA),
+            NodeTypeInput {
+                name: String::from(inputs::MEDIA),
+                display_name: String::from("Media"),
+                description: String::from("The media to rotate or flip"),
+                property_type: Type::Pipeable(
+                    PipeableType {
+                        video: 1,
+                        audio: 0,
+                        subtitles: 0,
+                    },
+                    PipeableType {
+                        video: 1,
+                        audio: i32::MAX,
+                        subtitles: i32::MAX,
+                    },
+                ),
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::ROTATION),
+            NodeTypeInput {
+                name: String::from(inputs::ROTATION),
+                display_name: String::from("Rotation"),
+                description: String::from("The clockwise rotation to apply, in degrees"),
+                property_type: Type::Number(Restrictions {
+                    min: 0.0,
+                    max: 270.0,
+                    step: 90.0,
+                    default: 0.0,
+                }),
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::FLIP_HORIZONTAL),
+            NodeTypeInput {
+                name: String::from(inputs::FLIP_HORIZONTAL),
+                display_name: String::from("Flip Horizontally"),
+                description: String::from(
+                    "Set to 1 to flip the video horizontally, after rotating it",
+                ),
+                property_type: Type::Number(Restrictions {
+                    min: 0.0,
+                    max: 1.0,
+                    step: 1.0,
+                    default: 0.0,
+                }),
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::FLIP_VERTICAL),
+            NodeTypeInput {
+                name: String::from(inputs::FLIP_VERTICAL),
+                display_name: String::from("Flip Vertically"),
+                description: String::from(
+                    "Set to 1 to flip the video vertically, after rotating it",
+                ),
+                property_type: Type::Number(Restrictions {
+                    min: 0.0,
+                    max: 1.0,
+                    step: 1.0,
+                    default: 0.0,
+                }),
+            },
+        );
+
+        default_properties
+    }
+
+    fn get_io(&self, context: &NodeContext) -> Result<NodeIo, PipelineError> {
+        let (inputs, mut outputs) = self.single_media_io(
+            context,
+            inputs::MEDIA,
+            outputs::OUTPUT,
+            "The rotated media",
+            PipeableType {
+                video: i32::MAX,
+                audio: i32::MAX,
+                subtitles: i32::MAX,
+            },
+        )?;
+        // flips keep the width and height, so only the rotation affects the resolution
+        let rotation = get_number_property(context.properties, inputs::ROTATION, 0.0) as u32;
+        if let Some(output) = outputs.get_mut(outputs::OUTPUT) {
+            output.format = output.format.rotate(rotation);
+        }
+        Ok((inputs, outputs))
+    }
+
+    fn get_output(&self, context: &NodeContext) -> Result<NodeTimelines, PipelineError> {
+        let properties = context.properties;
+        let method = video_flip_method(
+            get_number_property(properties, inputs::ROTATION, 0.0) as u32,
+            get_number_property(properties, inputs::FLIP_HORIZONTAL, 0.0) as i64 == 1,
+            get_number_property(properties, inputs::FLIP_VERTICAL, 0.0) as i64 == 1,
+        );
+        if method.is_err() {
+            return Err(PipelineError::from(method.unwrap_err()).with_property(inputs::ROTATION));
+        }
+
+        let effect = ges::Effect::new(format!("videoflip method={}", method.unwrap()).as_str())?;
+        self.single_effect_output(context, inputs::MEDIA, outputs::OUTPUT, &effect)
+    }
+}
+
+pub fn rotate_node() -> NodeType {
+    NodeType::new(
+        IDENTIFIER,
+        "Rotate/Flip",
+        "Rotate a media source by a multiple of 90 degrees, or flip it",
+        NodeCategory::Video,
+        RotateNode,
+    )
+    .with_tags(&["flip", "mirror", "orientation"])
+    .with_units(inputs::ROTATION, "°")
+}
+
+/**
+ * Gets the `videoflip` method which rotates the video clockwise by `rotation` degrees, and then applies the given flips
+ */
+pub fn video_flip_method(
+    rotation: u32,
+    flip_horizontal: bool,
+    flip_vertical: bool,
+) -> Result<&'static str, String> {
+    if rotation % 90 != 0 {
+        return Err(format!(
+            "Rotation must be a multiple of 90 degrees, not {}",
+            rotation
+        ));
+    }
+
+    // Track the transformation as a horizontal flip (if `mirrored`), followed by a clockwise rotation
+    let mut rotation = rotation % 360;
+    let mut mirrored = false;
+    if flip_horizontal {
+        rotation = (360 - rotation) % 360;
+        mirrored = !mirrored;
+    }
+    if flip_vertical {
+        // a vertical flip is a horizontal flip followed by a half turn
+        rotation = (540 - rotation) % 360;
+        mirrored = !mirrored;
+    }
+
+    Ok(match (rotation, mirrored) {
+        (0, false) => "none",
+        (90, false) => "clockwise",
+        (180, false) => "rotate-180",
+        (270, false) => "counterclockwise",
+        (0, true) => "horizontal-flip",
+        (90, true) => "upper-right-diagonal",
+        (180, true) => "vertical-flip",
+        _ => "upper-left-diagonal",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::video_flip_method;
+
+    #[test]
+    fn test_video_flip_method() {
+        assert_eq!(video_flip_method(0, false, false).unwrap(), "none");
+        assert_eq!(video_flip_method(90, false, false).unwrap(), "clockwise");
+        assert_eq!(
+            video_flip_method(270, false, false).unwrap(),
+            "counterclockwise"
+        );
+        assert_eq!(video_flip_method(0, false, true).unwrap(), "vertical-flip");
+        assert_eq!(
+            video_flip_method(180, true, false).unwrap(),
+            "vertical-flip"
+        );
+        assert_eq!(video_flip_method(0, true, true).unwrap(), "rotate-180");
+        // matches GStreamer's interpretation of the `flip-rotate-90` orientation tag
+        assert_eq!(
+            video_flip_method(90, true, false).unwrap(),
+            "upper-left-diagonal"
+        );
+        assert_eq!(
+            video_flip_method(270, true, false).unwrap(),
+            "upper-right-diagonal"
+        );
+        assert!(video_flip_method(45, false, false).is_err());
+    }
+}
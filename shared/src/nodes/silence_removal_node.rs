@@ -0,0 +1,458 @@
+use std::collections::HashMap;
+
+use ges::{
+    traits::{LayerExt, TimelineExt, UriClipAssetExt},
+    TrackType,
+};
+use glib::StaticType;
+use serde_json::Value;
+
+use crate::{
+    constants::AUDIO_ANALYSIS_WINDOW,
+    error::PipelineError,
+    node::{
+        InputOrOutput, MemorySafetyWrapper, Node, NodeCategory, NodeContext, NodeIo, NodeKind,
+        NodeTimelines, NodeType, NodeTypeInput, NodeTypeOutput, PipeableType, PipedType,
+        Restrictions, StreamFormat, Type,
+    },
+    store::Store,
+};
+
+use super::{get_input_key, get_number_property, ANALYSIS_KEY};
+
+pub const IDENTIFIER: &str = "silence_removal";
+pub mod inputs {
+    pub const MEDIA: &str = "media";
+    pub const THRESHOLD: &str = "threshold";
+    pub const MIN_SILENCE_LENGTH: &str = "min_silence_length";
+    pub const CUTS: &str = "cuts";
+}
+pub mod outputs {
+    pub const OUTPUT: &str = "output";
+}
+
+const DEFAULT_THRESHOLD: f64 = -40.0;
+const DEFAULT_MIN_SILENCE_LENGTH: f64 = 0.5;
+
+/// The longest list of cuts which can be stored; lists of ranges found by analysing long media are much longer than other strings
+pub const MAX_CUTS_LENGTH: i32 = 65536;
+
+pub struct SilenceRemovalNode;
+
+impl NodeKind for SilenceRemovalNode {
+    fn default_properties(&self) -> HashMap<String, NodeTypeInput> {
+        let mut default_properties = HashMap::new();
+
+        default_properties.insert(
+            String::from(inputs::MEDIA),
+            NodeTypeInput {
+                name: String::from(inputs::MEDIA),
+                display_name: String::from("Media"),
+                description: String::from("The media to remove the silences from"),
+                property_type: Type::Pipeable(
+                    PipeableType {
+                        video: 0,
+                        audio: 1,
+                        subtitles: 0,
+                    },
+                    PipeableType {
+                        video: i32::MAX,
+                        audio: i32::MAX,
+                        subtitles: i32::MAX,
+                    },
+                ),
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::THRESHOLD),
+            NodeTypeInput {
+                name: String::from(inputs::THRESHOLD),
+                display_name: String::from("Threshold"),
+                description: String::from("Audio quieter than this level is treated as silence"),
+                property_type: Type::Number(Restrictions {
+                    min: -100.0,
+                    max: 0.0,
+                    step: 1.0,
+                    default: DEFAULT_THRESHOLD,
+                }),
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::MIN_SILENCE_LENGTH),
+            NodeTypeInput {
+                name: String::from(inputs::MIN_SILENCE_LENGTH),
+                display_name: String::from("Minimum Silence Length"),
+                description: String::from("The shortest silence (in seconds) that will be removed"),
+                property_type: Type::Number(Restrictions {
+                    min: 0.05,
+                    max: 10.0,
+                    step: 0.05,
+                    default: DEFAULT_MIN_SILENCE_LENGTH,
+                }),
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::CUTS),
+            NodeTypeInput {
+                name: String::from(inputs::CUTS),
+                display_name: String::from("Cuts"),
+                description: String::from(
+                    "The ranges to remove, in seconds (e.g. 1.2-2.5, 5-6.1). These are detected automatically, but can be edited",
+                ),
+                property_type: Type::String(MAX_CUTS_LENGTH),
+            },
+        );
+
+        default_properties
+    }
+
+    fn get_io(&self, context: &NodeContext) -> Result<NodeIo, PipelineError> {
+        let NodeContext {
+            properties,
+            piped_inputs,
+            ..
+        } = *context;
+        let inputs = self.default_properties();
+        let mut outputs = HashMap::new();
+        let mut stream_type = PipeableType {
+            video: i32::MAX,
+            audio: i32::MAX,
+            subtitles: i32::MAX,
+        };
+        let mut format = StreamFormat::default();
+        if let Some(piped_input) = piped_inputs.get(inputs::MEDIA) {
+            stream_type = piped_input.stream_type;
+            format = piped_input.format;
+            let cuts = match properties.get(inputs::CUTS) {
+                Some(Value::String(cuts)) => parse_cuts(cuts).ok(),
+                _ => Some(Vec::new()),
+            };
+            format.duration = match (format.duration, cuts) {
+                (Some(duration), Some(cuts)) => Some(
+                    get_kept_ranges(&cuts, duration)
+                        .iter()
+                        .map(|(start, end)| end - start)
+                        .sum(),
+                ),
+                _ => None,
+            };
+        }
+        outputs.insert(
+            outputs::OUTPUT.to_string(),
+            NodeTypeOutput {
+                name: outputs::OUTPUT.to_string(),
+                description: "The media with the silences removed".to_string(),
+                display_name: "Output".to_string(),
+                property_type: stream_type,
+                format,
+                number_stream: None,
+            },
+        );
+
+        return Ok((inputs, outputs));
+    }
+
+    fn get_output(&self, context: &NodeContext) -> Result<NodeTimelines, PipelineError> {
+        let NodeContext {
+            node_id,
+            properties,
+            piped_inputs,
+            ..
+        } = *context;
+        let io = self.get_io(context);
+        if io.is_err() {
+            return Err(io.unwrap_err());
+        }
+        let (_, outputs) = io.unwrap();
+
+        let media = piped_inputs.get(inputs::MEDIA);
+        if media.is_none() {
+            return Err(PipelineError::missing_input(inputs::MEDIA));
+        }
+        let media = media.unwrap();
+
+        // Until the server has analysed the media, nothing is cut
+        let cuts = match properties.get(inputs::CUTS) {
+            Some(Value::String(cuts)) => parse_cuts(cuts)?,
+            _ => Vec::new(),
+        };
+
+        let output = outputs.get(outputs::OUTPUT).unwrap();
+        let output = PipedType {
+            stream_type: output.property_type,
+            node_id,
+            property_name: String::from(outputs::OUTPUT),
+            io: InputOrOutput::Output,
+            cache_id: None,
+            format: output.format,
+        };
+
+        let timeline = output.create_timeline();
+        let layer = timeline.append_layer();
+
+        let location = media.get_gst_save_location_with_cache();
+        ges::Asset::needs_reload(ges::UriClip::static_type(), Some(location.as_str()));
+        let clip = ges::UriClipAsset::request_sync(location.as_str())?;
+        let duration = clip.duration();
+        if duration.is_none() {
+            return Err(PipelineError::unknown_duration(inputs::MEDIA));
+        }
+        let duration = duration.unwrap().mseconds();
+
+        let mut start = 0;
+        for (inpoint, end) in get_kept_ranges(&cuts, duration) {
+            layer.add_asset(
+                &clip,
+                Some(gst::ClockTime::from_mseconds(start)),
+                Some(gst::ClockTime::from_mseconds(inpoint)),
+                Some(gst::ClockTime::from_mseconds(end - inpoint)),
+                TrackType::UNKNOWN,
+            )?;
+            start += end - inpoint;
+        }
+
+        let mut hm = HashMap::new();
+        hm.insert(outputs::OUTPUT.to_string(), timeline);
+        Ok((hm, vec![MemorySafetyWrapper::UriClipAsset(clip)]))
+    }
+}
+
+pub fn silence_removal_node() -> NodeType {
+    NodeType::new(
+        IDENTIFIER,
+        "Silence Removal",
+        "Detect silences in the audio, and cut them out of the media",
+        NodeCategory::Audio,
+        SilenceRemovalNode,
+    )
+    .with_tags(&["cut", "trim", "pause", "jump cut"])
+    .with_units(inputs::THRESHOLD, "dB")
+    .with_units(inputs::MIN_SILENCE_LENGTH, "s")
+    .with_units(inputs::CUTS, "s")
+}
+
+/**
+ * Gets the media that needs its audio analysing, if the cuts are out of date. The returned key should be stored alongside the results of the analysis
+ *
+ * The cuts are only detected again when the input or the detection settings change, so any edits the user makes to the cuts are kept
+ */
+pub fn get_analysis_request(
+    node: &Node,
+    piped_inputs: &HashMap<String, PipedType>,
+    store: &Store,
+) -> Option<(String, Vec<PipedType>)> {
+    let piped_input = piped_inputs.get(inputs::MEDIA);
+    let input_key = get_input_key(node, inputs::MEDIA, store);
+    if piped_input.is_none() || input_key.is_none() {
+        return None;
+    }
+
+    let key = format!(
+        "{}:{}:{}",
+        input_key.unwrap(),
+        get_number_property(&node.properties, inputs::THRESHOLD, DEFAULT_THRESHOLD),
+        get_number_property(
+            &node.properties,
+            inputs::MIN_SILENCE_LENGTH,
+            DEFAULT_MIN_SILENCE_LENGTH
+        )
+    );
+
+    let existing_key = node.properties.get(ANALYSIS_KEY);
+    let up_to_date = match existing_key {
+        Some(Value::String(existing_key)) => *existing_key == key,
+        _ => false,
+    };
+    if up_to_date && node.properties.contains_key(inputs::CUTS) {
+        return None;
+    }
+    Some((key, vec![piped_input.unwrap().clone()]))
+}
+
+/**
+ * Detects the silences in the media from its audio levels (one level per `AUDIO_ANALYSIS_WINDOW`, in dB), returning the properties to update
+ */
+pub fn analyse(
+    properties: &HashMap<String, Value>,
+    levels: &Vec<Vec<f64>>,
+) -> Result<HashMap<String, Value>, String> {
+    if levels.is_empty() {
+        return Err(String::from("No audio to analyse"));
+    }
+    let threshold = get_number_property(properties, inputs::THRESHOLD, DEFAULT_THRESHOLD);
+    let min_silence_length = get_number_property(
+        properties,
+        inputs::MIN_SILENCE_LENGTH,
+        DEFAULT_MIN_SILENCE_LENGTH,
+    );
+
+    let cuts = detect_silences(
+        &levels[0],
+        threshold,
+        (min_silence_length * 1000.0) as u64 / AUDIO_ANALYSIS_WINDOW,
+    );
+
+    let formatted_cuts = format_cuts(&cuts);
+    if formatted_cuts.chars().count() > MAX_CUTS_LENGTH as usize {
+        return Err(format!(
+            "Found too many silences to store ({}); try a longer minimum silence length or a lower threshold",
+            cuts.len()
+        ));
+    }
+
+    let mut properties = HashMap::new();
+    properties.insert(String::from(inputs::CUTS), Value::String(formatted_cuts));
+    Ok(properties)
+}
+
+/**
+ * Finds the ranges (in milliseconds) where the level stays below the threshold for at least `min_windows` windows
+ */
+pub fn detect_silences(levels: &Vec<f64>, threshold: f64, min_windows: u64) -> Vec<(u64, u64)> {
+    let mut silences = Vec::new();
+    let mut silence_start = None;
+    for i in 0..(levels.len() + 1) {
+        let is_silent = i < levels.len() && levels[i] < threshold;
+        match (is_silent, silence_start) {
+            (true, None) => silence_start = Some(i),
+            (false, Some(start)) => {
+                if (i - start) as u64 >= u64::max(min_windows, 1) {
+                    silences.push((
+                        start as u64 * AUDIO_ANALYSIS_WINDOW,
+                        i as u64 * AUDIO_ANALYSIS_WINDOW,
+                    ));
+                }
+                silence_start = None;
+            }
+            _ => {}
+        }
+    }
+    silences
+}
+
+/**
+ * Parses a list of cuts in the form `1.2-2.5, 5-6.1` (in seconds) into ranges in milliseconds
+ */
+pub fn parse_cuts(cuts: &str) -> Result<Vec<(u64, u64)>, String> {
+    let mut ranges = Vec::new();
+    for cut in cuts.split(",") {
+        let cut = cut.trim();
+        if cut.is_empty() {
+            continue;
+        }
+        let parts: Vec<&str> = cut.split("-").collect();
+        if parts.len() != 2 {
+            return Err(format!("Cut '{}' is not of the form start-end", cut));
+        }
+        let start = parts[0].trim().parse::<f64>();
+        let end = parts[1].trim().parse::<f64>();
+        if start.is_err() || end.is_err() {
+            return Err(format!("Cut '{}' does not contain valid times", cut));
+        }
+        let (start, end) = (start.unwrap(), end.unwrap());
+        if start < 0.0 || end < start {
+            return Err(format!("Cut '{}' is not a valid range", cut));
+        }
+        ranges.push((
+            (start * 1000.0).round() as u64,
+            (end * 1000.0).round() as u64,
+        ));
+    }
+    Ok(ranges)
+}
+
+/**
+ * Formats a list of cuts (in milliseconds) so they can be read and edited by the user
+ */
+pub fn format_cuts(cuts: &Vec<(u64, u64)>) -> String {
+    cuts.iter()
+        .map(|(start, end)| format!("{:.3}-{:.3}", *start as f64 / 1000.0, *end as f64 / 1000.0))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/**
+ * Gets the ranges of the media (in milliseconds) that remain once the cuts have been removed
+ */
+pub fn get_kept_ranges(cuts: &Vec<(u64, u64)>, duration: u64) -> Vec<(u64, u64)> {
+    let mut cuts = cuts.clone();
+    cuts.sort();
+
+    let mut ranges = Vec::new();
+    let mut position = 0;
+    for (start, end) in cuts {
+        let start = u64::min(start, duration);
+        if start > position {
+            ranges.push((position, start));
+        }
+        position = u64::max(position, u64::min(end, duration));
+    }
+    if position < duration {
+        ranges.push((position, duration));
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::{analyse, detect_silences, format_cuts, get_kept_ranges, inputs, parse_cuts};
+
+    #[test]
+    fn test_detect_silences() {
+        let mut levels = vec![-10.0; 100];
+        for i in 20..60 {
+            levels[i] = -60.0; // long enough to be cut
+        }
+        for i in 70..73 {
+            levels[i] = -60.0; // too short to be cut
+        }
+        for i in 90..100 {
+            levels[i] = -60.0; // silence running to the end of the media
+        }
+        assert_eq!(
+            detect_silences(&levels, -40.0, 10),
+            vec![(200, 600), (900, 1000)]
+        );
+    }
+
+    #[test]
+    fn test_cuts_round_trip() {
+        let cuts = vec![(1200, 2500), (5000, 6100)];
+        assert_eq!(format_cuts(&cuts), "1.200-2.500, 5.000-6.100");
+        assert_eq!(parse_cuts(&format_cuts(&cuts)).unwrap(), cuts);
+        assert!(parse_cuts("1.2-").is_err());
+        assert!(parse_cuts("").unwrap().is_empty());
+        // 1.001 * 1000 is just under 1001
+        assert_eq!(parse_cuts("1.001-2.003").unwrap(), vec![(1001, 2003)]);
+    }
+
+    #[test]
+    fn test_too_many_cuts() {
+        let mut properties = HashMap::new();
+        properties.insert(String::from(inputs::MIN_SILENCE_LENGTH), json!(0.05));
+        // a short silence every 60ms, for 10 minutes
+        let levels: Vec<f64> = (0..60000)
+            .map(|i| if i % 6 == 0 { -10.0 } else { -60.0 })
+            .collect();
+        assert!(analyse(&properties, &vec![levels]).is_err());
+
+        let levels: Vec<f64> = (0..60000)
+            .map(|i| if i % 600 == 0 { -10.0 } else { -60.0 })
+            .collect();
+        assert!(analyse(&properties, &vec![levels]).is_ok());
+    }
+
+    #[test]
+    fn test_kept_ranges() {
+        let cuts = vec![(5000, 6000), (0, 1000), (5500, 7000), (9000, 12000)];
+        assert_eq!(
+            get_kept_ranges(&cuts, 10000),
+            vec![(1000, 5000), (7000, 9000)]
+        );
+        assert_eq!(get_kept_ranges(&Vec::new(), 10000), vec![(0, 10000)]);
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+
+use ges::{
+    traits::{LayerExt, TimelineExt},
+    TrackType,
+};
+use glib::StaticType;
+use serde_json::Value;
+
+use crate::{
+    error::PipelineError,
+    node::{
+        InputOrOutput, MemorySafetyWrapper, NodeCategory, NodeContext, NodeIo, NodeKind,
+        NodeTimelines, NodeType, NodeTypeInput, NodeTypeOutput, PipeableType, PipedType,
+        Restrictions, StreamFormat, Type,
+    },
+};
+
+use super::{get_number_property, number_input};
+
+pub const IDENTIFIER: &str = "switch";
+pub mod inputs {
+    pub const NUMBER_OF_INPUTS: &str = "number_of_inputs";
+    pub const ACTIVE_INPUT: &str = "active_input";
+
+    pub fn media(i: i32) -> String {
+        format!("media{}", i)
+    }
+}
+pub mod outputs {
+    pub const OUTPUT: &str = "output";
+}
+
+const MAX_INPUTS: i32 = 16;
+const DEFAULT_NUMBER_OF_INPUTS: f64 = 2.0;
+
+fn media_input(i: i32) -> NodeTypeInput {
+    NodeTypeInput {
+        name: inputs::media(i),
+        display_name: format!("Media {}", i),
+        description: format!("The media output when input {} is active", i),
+        property_type: Type::Pipeable(
+            PipeableType {
+                video: 0,
+                audio: 0,
+                subtitles: 0,
+            },
+            PipeableType {
+                video: i32::MAX,
+                audio: i32::MAX,
+                subtitles: i32::MAX,
+            },
+        ),
+    }
+}
+
+fn get_number_of_inputs(properties: &HashMap<String, Value>) -> i32 {
+    super::get_number_of_inputs(
+        properties,
+        inputs::NUMBER_OF_INPUTS,
+        DEFAULT_NUMBER_OF_INPUTS as i32,
+        2,
+        MAX_INPUTS,
+    )
+}
+
+/**
+ * Gets the name of the input which is currently output by the switch
+ */
+pub fn get_active_input(properties: &HashMap<String, Value>) -> String {
+    let active_input = get_number_property(properties, inputs::ACTIVE_INPUT, 1.0) as i32;
+    inputs::media(i32::max(
+        1,
+        i32::min(active_input, get_number_of_inputs(properties)),
+    ))
+}
+
+pub struct SwitchNode;
+
+impl NodeKind for SwitchNode {
+    fn default_properties(&self) -> HashMap<String, NodeTypeInput> {
+        let mut default_properties = HashMap::new();
+
+        default_properties.insert(
+            String::from(inputs::NUMBER_OF_INPUTS),
+            number_input(
+                inputs::NUMBER_OF_INPUTS,
+                "Number of Inputs",
+                "The number of alternative media to choose between",
+                Restrictions {
+                    min: 2.0,
+                    max: MAX_INPUTS as f64,
+                    step: 1.0,
+                    default: DEFAULT_NUMBER_OF_INPUTS,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::ACTIVE_INPUT),
+            number_input(
+                inputs::ACTIVE_INPUT,
+                "Active Input",
+                "The number of the input which is output",
+                Restrictions {
+                    min: 1.0,
+                    max: MAX_INPUTS as f64,
+                    step: 1.0,
+                    default: 1.0,
+                },
+            ),
+        );
+
+        default_properties
+    }
+
+    fn get_io(&self, context: &NodeContext) -> Result<NodeIo, PipelineError> {
+        let NodeContext {
+            properties,
+            piped_inputs,
+            ..
+        } = *context;
+        let mut inputs = self.default_properties();
+        let mut outputs = HashMap::new();
+        for i in 1..(get_number_of_inputs(properties) + 1) {
+            inputs.insert(inputs::media(i), media_input(i));
+        }
+
+        let mut stream_type = PipeableType {
+            video: i32::MAX,
+            audio: i32::MAX,
+            subtitles: i32::MAX,
+        };
+        let mut format = StreamFormat::default();
+        if let Some(piped_input) = piped_inputs.get(&get_active_input(properties)) {
+            stream_type = piped_input.stream_type;
+            format = piped_input.format;
+        }
+        outputs.insert(
+            outputs::OUTPUT.to_string(),
+            NodeTypeOutput {
+                name: outputs::OUTPUT.to_string(),
+                description: "The media of the active input".to_string(),
+                display_name: "Output".to_string(),
+                property_type: stream_type,
+                format,
+                number_stream: None,
+            },
+        );
+
+        return Ok((inputs, outputs));
+    }
+
+    fn get_output(&self, context: &NodeContext) -> Result<NodeTimelines, PipelineError> {
+        let NodeContext {
+            node_id,
+            properties,
+            piped_inputs,
+            ..
+        } = *context;
+        let io = self.get_io(context);
+        if io.is_err() {
+            return Err(io.unwrap_err());
+        }
+        let (_, outputs) = io.unwrap();
+
+        let active_input = get_active_input(properties);
+        let media = piped_inputs.get(&active_input);
+        if media.is_none() {
+            return Err(PipelineError::missing_input(&active_input));
+        }
+        let media = media.unwrap();
+
+        let output = outputs.get(outputs::OUTPUT).unwrap();
+        let output = PipedType {
+            stream_type: output.property_type,
+            node_id,
+            property_name: String::from(outputs::OUTPUT),
+            io: InputOrOutput::Output,
+            cache_id: None,
+            format: output.format,
+        };
+
+        let timeline = output.create_timeline();
+        let layer = timeline.append_layer();
+
+        let location = media.get_gst_save_location_with_cache();
+        ges::Asset::needs_reload(ges::UriClip::static_type(), Some(location.as_str()));
+        let clip = ges::UriClipAsset::request_sync(location.as_str())?;
+        layer.add_asset(&clip, None, None, None, TrackType::UNKNOWN)?;
+
+        let mut hm = HashMap::new();
+        hm.insert(outputs::OUTPUT.to_string(), timeline);
+        Ok((hm, vec![MemorySafetyWrapper::UriClipAsset(clip)]))
+    }
+}
+
+pub fn switch_node() -> NodeType {
+    NodeType::new(
+        IDENTIFIER,
+        "Switch",
+        "Choose one of several alternative media sources, without relinking the graph",
+        NodeCategory::Utility,
+        SwitchNode,
+    )
+    .with_tags(&["choose", "select", "alternative"])
+}
@@ -0,0 +1,376 @@
+use std::collections::{HashMap, HashSet};
+
+use ges::traits::{GESContainerExt, LayerExt, TimelineExt};
+use serde_json::Value;
+
+use crate::{
+    error::PipelineError,
+    node::{
+        InputOrOutput, MemorySafetyWrapper, NodeCategory, NodeContext, NodeIo, NodeKind,
+        NodeTimelines, NodeType, NodeTypeInput, NodeTypeOutput, PipeableType, PipedType,
+        Restrictions, StreamFormat, Type,
+    },
+    store::Store,
+    ID,
+};
+
+use super::{get_number_property, number_input, output_node};
+
+pub const IDENTIFIER: &str = "timecode";
+pub mod inputs {
+    pub const MEDIA: &str = "media";
+    pub const MODE: &str = "mode";
+    pub const FRAME_COUNTER: &str = "frame_counter";
+    pub const TEXT: &str = "text";
+    pub const X: &str = "x";
+    pub const Y: &str = "y";
+    pub const FONT: &str = "font";
+    pub const FONT_SIZE: &str = "font_size";
+}
+pub mod outputs {
+    pub const OUTPUT: &str = "output";
+}
+
+/// Replaced in the text with the name(s) of the composited clip(s) that the node outputs to
+pub const CLIP_NAME_PLACEHOLDER: &str = "{clip_name}";
+
+const MODE_RUNNING_TIME: i64 = 0;
+const MODE_TIMECODE: i64 = 1;
+
+const DEFAULT_FONT: &str = "Sans";
+const DEFAULT_FONT_SIZE: f64 = 24.0;
+
+pub struct TimecodeNode;
+
+impl NodeKind for TimecodeNode {
+    fn default_properties(&self) -> HashMap<String, NodeTypeInput> {
+        let mut default_properties = HashMap::new();
+
+        default_properties.insert(
+            String::from(inputs::MEDIA),
+            NodeTypeInput {
+                name: String::from(inputs::MEDIA),
+                display_name: String::from("Media"),
+                description: String::from("The media to burn the timecode into"),
+                property_type: Type::Pipeable(
+                    PipeableType {
+                        video: 1,
+                        audio: 0,
+                        subtitles: 0,
+                    },
+                    PipeableType {
+                        video: 1,
+                        audio: i32::MAX,
+                        subtitles: i32::MAX,
+                    },
+                ),
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::MODE),
+            number_input(
+                inputs::MODE,
+                "Mode",
+                "0 shows the running time, 1 shows an SMPTE timecode",
+                Restrictions {
+                    min: MODE_RUNNING_TIME as f64,
+                    max: MODE_TIMECODE as f64,
+                    step: 1.0,
+                    default: MODE_RUNNING_TIME as f64,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::FRAME_COUNTER),
+            number_input(
+                inputs::FRAME_COUNTER,
+                "Frame Counter",
+                "Set to 1 to show the frame number below the time",
+                Restrictions {
+                    min: 0.0,
+                    max: 1.0,
+                    step: 1.0,
+                    default: 0.0,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::TEXT),
+            NodeTypeInput {
+                name: String::from(inputs::TEXT),
+                display_name: String::from("Text"),
+                description: format!(
+                    "Text to show before the time; {} is replaced with the name of the clip being output",
+                    CLIP_NAME_PLACEHOLDER
+                ),
+                property_type: Type::String(256),
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::X),
+            number_input(
+                inputs::X,
+                "X Position",
+                "The horizontal position of the text, from 0 (left) to 1 (right)",
+                Restrictions {
+                    min: 0.0,
+                    max: 1.0,
+                    step: 0.01,
+                    default: 0.05,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::Y),
+            number_input(
+                inputs::Y,
+                "Y Position",
+                "The vertical position of the text, from 0 (top) to 1 (bottom)",
+                Restrictions {
+                    min: 0.0,
+                    max: 1.0,
+                    step: 0.01,
+                    default: 0.9,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::FONT),
+            NodeTypeInput {
+                name: String::from(inputs::FONT),
+                display_name: String::from("Font"),
+                description: format!("The font family of the text (default {})", DEFAULT_FONT),
+                property_type: Type::String(64),
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::FONT_SIZE),
+            number_input(
+                inputs::FONT_SIZE,
+                "Font Size",
+                "The size of the text",
+                Restrictions {
+                    min: 6.0,
+                    max: 200.0,
+                    step: 1.0,
+                    default: DEFAULT_FONT_SIZE,
+                },
+            ),
+        );
+
+        default_properties
+    }
+
+    fn get_io(&self, context: &NodeContext) -> Result<NodeIo, PipelineError> {
+        let NodeContext { piped_inputs, .. } = *context;
+        let inputs = self.default_properties();
+        let mut outputs = HashMap::new();
+        let mut stream_type = PipeableType {
+            video: i32::MAX,
+            audio: i32::MAX,
+            subtitles: i32::MAX,
+        };
+        let mut format = StreamFormat::default();
+        if let Some(piped_input) = piped_inputs.get(inputs::MEDIA) {
+            stream_type = piped_input.stream_type;
+            format = piped_input.format;
+        }
+        outputs.insert(
+            outputs::OUTPUT.to_string(),
+            NodeTypeOutput {
+                name: outputs::OUTPUT.to_string(),
+                description: "The media with the timecode burnt in".to_string(),
+                display_name: "Output".to_string(),
+                property_type: stream_type,
+                format,
+                number_stream: None,
+            },
+        );
+
+        return Ok((inputs, outputs));
+    }
+
+    fn get_output(&self, context: &NodeContext) -> Result<NodeTimelines, PipelineError> {
+        let NodeContext {
+            node_id,
+            properties,
+            piped_inputs,
+            store,
+            ..
+        } = *context;
+        let io = self.get_io(context);
+        if io.is_err() {
+            return Err(io.unwrap_err());
+        }
+        let (_, outputs) = io.unwrap();
+
+        let media = piped_inputs.get(inputs::MEDIA);
+        if media.is_none() {
+            return Err(PipelineError::missing_input(inputs::MEDIA));
+        }
+        let media = media.unwrap();
+
+        let text = match properties.get(inputs::TEXT) {
+            Some(Value::String(text)) => text.replace(
+                CLIP_NAME_PLACEHOLDER,
+                get_output_clip_names(&node_id, store).join(", ").as_str(),
+            ),
+            _ => String::new(),
+        };
+        let font = match properties.get(inputs::FONT) {
+            Some(Value::String(font)) if !font.is_empty() => font.clone(),
+            _ => String::from(DEFAULT_FONT),
+        };
+
+        let description = get_effect_description(
+            get_number_property(properties, inputs::MODE, MODE_RUNNING_TIME as f64) as i64,
+            get_number_property(properties, inputs::FRAME_COUNTER, 0.0) as i64 == 1,
+            &text,
+            get_number_property(properties, inputs::X, 0.05),
+            get_number_property(properties, inputs::Y, 0.9),
+            &font,
+            get_number_property(properties, inputs::FONT_SIZE, DEFAULT_FONT_SIZE) as u32,
+        );
+        if description.is_err() {
+            return Err(description.unwrap_err().into());
+        }
+
+        let output = outputs.get(outputs::OUTPUT).unwrap();
+        let output = PipedType {
+            stream_type: output.property_type,
+            node_id,
+            property_name: String::from(outputs::OUTPUT),
+            io: InputOrOutput::Output,
+            cache_id: None,
+            format: output.format,
+        };
+
+        let effect = ges::Effect::new(description.unwrap().as_str())?;
+        let timeline = output.create_timeline();
+        let layer = timeline.append_layer();
+        let clip = ges::UriClip::new(media.get_gst_save_location_with_cache().as_str())?;
+
+        clip.add(&effect)?;
+        layer.add_clip(&clip)?;
+
+        let mut hm = HashMap::new();
+        hm.insert(outputs::OUTPUT.to_string(), timeline);
+        Ok((
+            hm,
+            vec![
+                MemorySafetyWrapper::UriClip(clip),
+                MemorySafetyWrapper::Effect(effect),
+            ],
+        ))
+    }
+}
+
+/**
+ * Gets the names of the composited clips which this node's output ends up in, by following the links downstream to the output nodes
+ */
+fn get_output_clip_names(node_id: &ID, store: &Store) -> Vec<String> {
+    let mut visited = HashSet::new();
+    let mut to_visit = vec![node_id.clone()];
+    let mut names = Vec::new();
+    while let Some(current) = to_visit.pop() {
+        if !visited.insert(current.clone()) {
+            continue;
+        }
+        if let Some(node) = store.nodes.get(&current) {
+            if node.node_type == output_node::IDENTIFIER {
+                if let Ok(clip) = output_node::get_clip(&node.properties, store) {
+                    names.push(clip.name);
+                }
+            }
+        }
+        for link in &store.pipeline.links {
+            if link.from.node_id == current {
+                to_visit.push(link.to.node_id.clone());
+            }
+        }
+    }
+    names.sort();
+    names
+}
+
+/**
+ * Quotes a string so it can be used as a property value in a GStreamer bin description
+ */
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace("\\", "\\\\").replace("\"", "\\\""))
+}
+
+/**
+ * Gets the GStreamer bin description for the effect which draws the time (and optionally the frame number) onto the video
+ */
+fn get_effect_description(
+    mode: i64,
+    frame_counter: bool,
+    text: &str,
+    x: f64,
+    y: f64,
+    font: &str,
+    font_size: u32,
+) -> Result<String, String> {
+    let overlay = |time_mode: &str, text: &str, line: u32| {
+        format!(
+            "timeoverlay time-mode={} text={} halignment=position valignment=position xpos={} ypos={} deltay={} font-desc={} shaded-background=true",
+            time_mode,
+            quote(text),
+            x,
+            y,
+            line * font_size * 2, // each line is placed below the previous one
+            quote(format!("{} {}", font, font_size).as_str())
+        )
+    };
+
+    let mut elements = Vec::new();
+    match mode {
+        MODE_RUNNING_TIME => {
+            elements.push(overlay("running-time", text, 0));
+        }
+        MODE_TIMECODE => {
+            elements.push(String::from("timecodestamper"));
+            elements.push(overlay("time-code", text, 0));
+        }
+        _ => {
+            return Err(format!("Invalid timecode mode: {}", mode));
+        }
+    }
+    if frame_counter {
+        elements.push(overlay("buffer-count", "Frame ", 1));
+    }
+    Ok(elements.join(" ! "))
+}
+
+pub fn timecode_node() -> NodeType {
+    NodeType::new(
+        IDENTIFIER,
+        "Timecode",
+        "Burn the time, frame number and other text into a video",
+        NodeCategory::Video,
+        TimecodeNode,
+    )
+    .with_tags(&["time", "text", "overlay", "smpte", "burn in"])
+    .with_units(inputs::FONT_SIZE, "pt")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::get_effect_description;
+
+    #[test]
+    fn test_effect_description() {
+        let description =
+            get_effect_description(1, true, "Clip \"A\"", 0.1, 0.9, "Sans", 20).unwrap();
+        let elements: Vec<&str> = description.split(" ! ").collect();
+        assert_eq!(elements.len(), 3);
+        assert_eq!(elements[0], "timecodestamper");
+        assert!(elements[1].starts_with("timeoverlay time-mode=time-code text=\"Clip \\\"A\\\"\""));
+        assert!(elements[1].contains("deltay=0 font-desc=\"Sans 20\""));
+        assert!(elements[2].starts_with("timeoverlay time-mode=buffer-count text=\"Frame \""));
+        assert!(elements[2].contains("deltay=40"));
+
+        assert!(get_effect_description(2, false, "", 0.0, 0.0, "Sans", 20).is_err());
+    }
+}
@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+
+use ges::{
+    traits::{LayerExt, TimelineElementExt, TimelineExt, UriClipAssetExt},
+    TrackType,
+};
+use glib::{ObjectExt, StaticType};
+use serde_json::Value;
+
+use crate::{
+    constants::intermediate_files_location,
+    error::{PipelineError, PipelineErrorKind},
+    node::{
+        InputOrOutput, MemorySafetyWrapper, Node, NodeCategory, NodeContext, NodeIo, NodeKind,
+        NodeTimelines, NodeType, NodeTypeInput, NodeTypeOutput, PipeableType, PipedType,
+        Restrictions, StreamFormat, Type,
+    },
+    store::Store,
+    ID,
+};
+
+use super::{
+    get_input_key, get_number_property, grid_node::parse_colour, number_input, ANALYSIS_KEY,
+};
+
+pub const IDENTIFIER: &str = "visualiser";
+pub mod inputs {
+    pub const MEDIA: &str = "media";
+    pub const STYLE: &str = "style";
+    pub const WIDTH: &str = "width";
+    pub const HEIGHT: &str = "height";
+    pub const COLOUR: &str = "colour";
+}
+pub mod outputs {
+    pub const OUTPUT: &str = "output";
+}
+
+/// The property the location of the rendered visualisation is stored in, once the server has rendered it
+pub const VISUALISATION: &str = "visualisation";
+/// The frame rate the visualisation is rendered at
+pub const FRAMERATE: f64 = 30.0;
+
+const DEFAULT_WIDTH: f64 = 1280.0;
+const DEFAULT_HEIGHT: f64 = 720.0;
+
+pub struct VisualiserNode;
+
+impl NodeKind for VisualiserNode {
+    fn default_properties(&self) -> HashMap<String, NodeTypeInput> {
+        let mut default_properties = HashMap::new();
+
+        default_properties.insert(
+            String::from(inputs::MEDIA),
+            NodeTypeInput {
+                name: String::from(inputs::MEDIA),
+                display_name: String::from("Media"),
+                description: String::from("The audio to visualise"),
+                property_type: Type::Pipeable(
+                    PipeableType {
+                        video: 0,
+                        audio: 1,
+                        subtitles: 0,
+                    },
+                    PipeableType {
+                        video: 0,
+                        audio: i32::MAX,
+                        subtitles: 0,
+                    },
+                ),
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::STYLE),
+            number_input(
+                inputs::STYLE,
+                "Style",
+                "0 draws the waveform, 1 the spectrum, 2 a synaesthesia-style scope, 3 a stereo scope",
+                Restrictions {
+                    min: 0.0,
+                    max: 3.0,
+                    step: 1.0,
+                    default: 0.0,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::WIDTH),
+            number_input(
+                inputs::WIDTH,
+                "Width",
+                "The width of the video, in pixels",
+                Restrictions {
+                    min: 16.0,
+                    max: 7680.0,
+                    step: 2.0,
+                    default: DEFAULT_WIDTH,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::HEIGHT),
+            number_input(
+                inputs::HEIGHT,
+                "Height",
+                "The height of the video, in pixels",
+                Restrictions {
+                    min: 16.0,
+                    max: 4320.0,
+                    step: 2.0,
+                    default: DEFAULT_HEIGHT,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::COLOUR),
+            NodeTypeInput {
+                name: String::from(inputs::COLOUR),
+                display_name: String::from("Colour"),
+                description: String::from(
+                    "The colour to draw the visualisation in, as a hex code; if not set, the visualiser's own colours are used",
+                ),
+                property_type: Type::String(7),
+            },
+        );
+
+        default_properties
+    }
+
+    fn get_io(&self, context: &NodeContext) -> Result<NodeIo, PipelineError> {
+        let NodeContext {
+            properties,
+            piped_inputs,
+            ..
+        } = *context;
+        let inputs = self.default_properties();
+        let mut outputs = HashMap::new();
+        let mut stream_type = PipeableType {
+            video: 1,
+            audio: i32::MAX,
+            subtitles: 0,
+        };
+        let mut format = StreamFormat::default();
+        if let Some(piped_input) = piped_inputs.get(inputs::MEDIA) {
+            stream_type.audio = piped_input.stream_type.audio;
+            format = piped_input.format;
+        }
+        format.framerate = Some(FRAMERATE);
+        format.resolution = Some(get_resolution(properties));
+        outputs.insert(
+            outputs::OUTPUT.to_string(),
+            NodeTypeOutput {
+                name: outputs::OUTPUT.to_string(),
+                description: "The audio, with the visualisation as its video".to_string(),
+                display_name: "Output".to_string(),
+                property_type: stream_type,
+                format,
+                number_stream: None,
+            },
+        );
+
+        return Ok((inputs, outputs));
+    }
+
+    fn get_output(&self, context: &NodeContext) -> Result<NodeTimelines, PipelineError> {
+        let NodeContext {
+            node_id,
+            properties,
+            piped_inputs,
+            ..
+        } = *context;
+        let io = self.get_io(context);
+        if io.is_err() {
+            return Err(io.unwrap_err());
+        }
+        let (_, outputs) = io.unwrap();
+
+        let media = piped_inputs.get(inputs::MEDIA);
+        if media.is_none() {
+            return Err(PipelineError::missing_input(inputs::MEDIA));
+        }
+        let media = media.unwrap();
+
+        let output = outputs.get(outputs::OUTPUT).unwrap();
+        let output = PipedType {
+            stream_type: output.property_type,
+            node_id,
+            property_name: String::from(outputs::OUTPUT),
+            io: InputOrOutput::Output,
+            cache_id: None,
+            format: output.format,
+        };
+
+        let timeline = output.create_timeline();
+        let video_layer = timeline.append_layer();
+        let audio_layer = timeline.append_layer();
+
+        let location = media.get_gst_save_location_with_cache();
+        ges::Asset::needs_reload(ges::UriClip::static_type(), Some(location.as_str()));
+        let audio_asset = ges::UriClipAsset::request_sync(location.as_str())?;
+        audio_layer.add_asset(&audio_asset, None, None, None, TrackType::AUDIO)?;
+        let duration = audio_asset.duration();
+        if duration.is_none() {
+            return Err(PipelineError::unknown_duration(inputs::MEDIA));
+        }
+        let duration = duration.unwrap();
+
+        let mut memory_safety_vars = vec![MemorySafetyWrapper::UriClipAsset(audio_asset)];
+        match properties.get(VISUALISATION) {
+            Some(Value::String(visualisation)) => {
+                let location = format!("file:///{}", visualisation).replace("\\", "/");
+                ges::Asset::needs_reload(ges::UriClip::static_type(), Some(location.as_str()));
+                let video_asset = ges::UriClipAsset::request_sync(location.as_str());
+                if video_asset.is_err() {
+                    return Err(PipelineError::new(
+                        PipelineErrorKind::Ges,
+                        format!(
+                            "Could not load the visualisation: {}",
+                            video_asset.unwrap_err()
+                        ),
+                    ));
+                }
+                let video_asset = video_asset.unwrap();
+                video_layer.add_asset(
+                    &video_asset,
+                    None,
+                    None,
+                    Some(duration),
+                    TrackType::VIDEO,
+                )?;
+                memory_safety_vars.push(MemorySafetyWrapper::UriClipAsset(video_asset));
+            }
+            _ => {
+                // Until the server has rendered the visualisation, the video is left blank
+                let placeholder = ges::TestClip::new();
+                if placeholder.is_none() {
+                    return Err(PipelineError::new(
+                        PipelineErrorKind::Ges,
+                        String::from("Could not create the placeholder video"),
+                    ));
+                }
+                let placeholder = placeholder.unwrap();
+                placeholder.set_property("vpattern", ges::VideoTestPattern::Black);
+                placeholder.set_property("mute", true);
+                placeholder.set_duration(duration);
+                video_layer.add_clip(&placeholder)?;
+            }
+        }
+
+        let mut hm = HashMap::new();
+        hm.insert(outputs::OUTPUT.to_string(), timeline);
+        Ok((hm, memory_safety_vars))
+    }
+}
+
+pub fn visualiser_node() -> NodeType {
+    NodeType::new(
+        IDENTIFIER,
+        "Audio Visualiser",
+        "Generate a video from audio, using a waveform, spectrum or scope visualisation",
+        NodeCategory::Generator,
+        VisualiserNode,
+    )
+    .with_tags(&["waveform", "spectrum", "scope", "audiogram"])
+    .with_units(inputs::WIDTH, "px")
+    .with_units(inputs::HEIGHT, "px")
+}
+
+/**
+ * Gets the media that needs its visualisation rendering, if the rendered visualisation is out of date. The returned key should be stored alongside the location of the visualisation
+ */
+pub fn get_analysis_request(
+    node: &Node,
+    piped_inputs: &HashMap<String, PipedType>,
+    store: &Store,
+) -> Option<(String, Vec<PipedType>)> {
+    let piped_input = piped_inputs.get(inputs::MEDIA);
+    let input_key = get_input_key(node, inputs::MEDIA, store);
+    if piped_input.is_none() || input_key.is_none() {
+        return None;
+    }
+
+    let (width, height) = get_resolution(&node.properties);
+    let colour = match node.properties.get(inputs::COLOUR) {
+        Some(Value::String(colour)) => colour.clone(),
+        _ => String::new(),
+    };
+    let key = format!(
+        "{}:{}:{}x{}:{}",
+        input_key.unwrap(),
+        get_number_property(&node.properties, inputs::STYLE, 0.0),
+        width,
+        height,
+        colour
+    );
+
+    let existing_key = node.properties.get(ANALYSIS_KEY);
+    let up_to_date = match existing_key {
+        Some(Value::String(existing_key)) => *existing_key == key,
+        _ => false,
+    };
+    if up_to_date && node.properties.contains_key(VISUALISATION) {
+        return None;
+    }
+    Some((key, vec![piped_input.unwrap().clone()]))
+}
+
+/**
+ * Gets where the server renders the visualisation for a node
+ */
+pub fn get_visualisation_location(node_id: &ID) -> String {
+    format!(
+        "{}/{}_{}.mp4",
+        intermediate_files_location(),
+        node_id,
+        VISUALISATION
+    )
+    .replace("\\", "/")
+}
+
+/**
+ * Gets the width and height of the visualisation
+ */
+pub fn get_resolution(properties: &HashMap<String, Value>) -> (u32, u32) {
+    (
+        get_number_property(properties, inputs::WIDTH, DEFAULT_WIDTH) as u32,
+        get_number_property(properties, inputs::HEIGHT, DEFAULT_HEIGHT) as u32,
+    )
+}
+
+/**
+ * Gets the colour (as an ARGB value) to draw the visualisation in, or `None` to use the visualiser's own colours
+ */
+pub fn get_colour(properties: &HashMap<String, Value>) -> Result<Option<u32>, String> {
+    match properties.get(inputs::COLOUR) {
+        Some(Value::String(colour)) if !colour.is_empty() => Ok(Some(parse_colour(colour)?)),
+        _ => Ok(None),
+    }
+}
+
+/**
+ * Gets the name of the GStreamer element which draws the given style of visualisation
+ */
+pub fn get_scope_element(style: i64) -> Result<&'static str, String> {
+    match style {
+        0 => Ok("wavescope"),
+        1 => Ok("spectrascope"),
+        2 => Ok("synaescope"),
+        3 => Ok("spacescope"),
+        _ => Err(format!("Invalid visualisation style: {}", style)),
+    }
+}
+
+/**
+ * Redraws an RGBx frame in a single colour, keeping the brightness of each pixel
+ */
+pub fn tint_frame(frame: &mut [u8], colour: u32) {
+    let channels = [(colour >> 16) & 0xff, (colour >> 8) & 0xff, colour & 0xff];
+    for pixel in frame.chunks_exact_mut(4) {
+        let brightness = u32::max(pixel[0] as u32, u32::max(pixel[1] as u32, pixel[2] as u32));
+        for i in 0..3 {
+            pixel[i] = (brightness * channels[i] / 255) as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_scope_element, tint_frame};
+
+    #[test]
+    fn test_tint_frame() {
+        let mut frame = vec![255, 255, 255, 0, 0, 0, 0, 0, 0, 128, 64, 0];
+        tint_frame(&mut frame, 0xff00ff80);
+        assert_eq!(frame, vec![0, 255, 128, 0, 0, 0, 0, 0, 0, 128, 64, 0]);
+        assert_eq!(get_scope_element(1).unwrap(), "spectrascope");
+        assert!(get_scope_element(4).is_err());
+    }
+}
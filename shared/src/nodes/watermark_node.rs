@@ -0,0 +1,423 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use ges::{
+    traits::{LayerExt, TimelineElementExt, TimelineExt, UriClipAssetExt},
+    TrackType,
+};
+use glib::{StaticType, ToValue};
+use gst::prelude::Cast;
+use gst_pbutils::DiscovererVideoInfo;
+use serde_json::Value;
+
+use crate::{
+    clip::{ClipIdentifier, ClipType},
+    constants::source_files_location,
+    error::{PipelineError, PipelineErrorKind},
+    node::{
+        InputOrOutput, MemorySafetyWrapper, NodeCategory, NodeContext, NodeIo, NodeKind,
+        NodeTimelines, NodeType, NodeTypeInput, NodeTypeOutput, PipeableType, PipedType,
+        Restrictions, StreamFormat, Type,
+    },
+    store::Store,
+    ID,
+};
+
+use super::{get_number_property, number_input};
+
+pub const IDENTIFIER: &str = "watermark";
+pub mod inputs {
+    pub const MEDIA: &str = "media";
+    pub const IMAGE: &str = "image";
+    pub const IMAGE_PATH: &str = "image_path";
+    pub const X: &str = "x";
+    pub const Y: &str = "y";
+    pub const SCALE: &str = "scale";
+    pub const OPACITY: &str = "opacity";
+    pub const START: &str = "start";
+    pub const END: &str = "end";
+}
+pub mod outputs {
+    pub const OUTPUT: &str = "output";
+}
+
+pub struct WatermarkNode;
+
+impl NodeKind for WatermarkNode {
+    fn default_properties(&self) -> HashMap<String, NodeTypeInput> {
+        let mut default_properties = HashMap::new();
+
+        default_properties.insert(
+            String::from(inputs::MEDIA),
+            NodeTypeInput {
+                name: String::from(inputs::MEDIA),
+                display_name: String::from("Media"),
+                description: String::from("The media to overlay the image onto"),
+                property_type: Type::Pipeable(
+                    PipeableType {
+                        video: 1,
+                        audio: 0,
+                        subtitles: 0,
+                    },
+                    PipeableType {
+                        video: 1,
+                        audio: i32::MAX,
+                        subtitles: i32::MAX,
+                    },
+                ),
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::IMAGE),
+            NodeTypeInput {
+                name: String::from(inputs::IMAGE),
+                display_name: String::from("Image"),
+                description: String::from("The source clip of the image to overlay"),
+                property_type: Type::Clip,
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::IMAGE_PATH),
+            NodeTypeInput {
+                name: String::from(inputs::IMAGE_PATH),
+                display_name: String::from("Image Path"),
+                description: String::from(
+                    "The path of an image to overlay, relative to the server's source files folder, used if no image clip is given",
+                ),
+                property_type: Type::String(512),
+            },
+        );
+        default_properties.insert(
+            String::from(inputs::X),
+            number_input(
+                inputs::X,
+                "X Position",
+                "The distance of the image from the left of the video, in pixels",
+                Restrictions {
+                    min: 0.0,
+                    max: 7680.0,
+                    step: 1.0,
+                    default: 20.0,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::Y),
+            number_input(
+                inputs::Y,
+                "Y Position",
+                "The distance of the image from the top of the video, in pixels",
+                Restrictions {
+                    min: 0.0,
+                    max: 4320.0,
+                    step: 1.0,
+                    default: 20.0,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::SCALE),
+            number_input(
+                inputs::SCALE,
+                "Scale",
+                "The size of the image, relative to its original size",
+                Restrictions {
+                    min: 0.01,
+                    max: 10.0,
+                    step: 0.01,
+                    default: 1.0,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::OPACITY),
+            number_input(
+                inputs::OPACITY,
+                "Opacity",
+                "How opaque the image is, from 0 (invisible) to 1 (fully opaque)",
+                Restrictions {
+                    min: 0.0,
+                    max: 1.0,
+                    step: 0.01,
+                    default: 1.0,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::START),
+            number_input(
+                inputs::START,
+                "Start Time",
+                "When the image appears, in seconds",
+                Restrictions {
+                    min: 0.0,
+                    max: 86400.0,
+                    step: 0.1,
+                    default: 0.0,
+                },
+            ),
+        );
+        default_properties.insert(
+            String::from(inputs::END),
+            number_input(
+                inputs::END,
+                "End Time",
+                "When the image disappears, in seconds; 0 shows the image until the end of the media",
+                Restrictions {
+                    min: 0.0,
+                    max: 86400.0,
+                    step: 0.1,
+                    default: 0.0,
+                },
+            ),
+        );
+
+        default_properties
+    }
+
+    fn get_io(&self, context: &NodeContext) -> Result<NodeIo, PipelineError> {
+        let NodeContext { piped_inputs, .. } = *context;
+        let inputs = self.default_properties();
+        let mut outputs = HashMap::new();
+        let mut stream_type = PipeableType {
+            video: i32::MAX,
+            audio: i32::MAX,
+            subtitles: i32::MAX,
+        };
+        let mut format = StreamFormat::default();
+        if let Some(piped_input) = piped_inputs.get(inputs::MEDIA) {
+            stream_type = piped_input.stream_type;
+            format = piped_input.format;
+        }
+        outputs.insert(
+            outputs::OUTPUT.to_string(),
+            NodeTypeOutput {
+                name: outputs::OUTPUT.to_string(),
+                description: "The media with the image overlaid".to_string(),
+                display_name: "Output".to_string(),
+                property_type: stream_type,
+                format,
+                number_stream: None,
+            },
+        );
+
+        return Ok((inputs, outputs));
+    }
+
+    fn get_output(&self, context: &NodeContext) -> Result<NodeTimelines, PipelineError> {
+        let NodeContext {
+            node_id,
+            properties,
+            piped_inputs,
+            store,
+            ..
+        } = *context;
+        let io = self.get_io(context);
+        if io.is_err() {
+            return Err(io.unwrap_err());
+        }
+        let (_, outputs) = io.unwrap();
+
+        let media = piped_inputs.get(inputs::MEDIA);
+        if media.is_none() {
+            return Err(PipelineError::missing_input(inputs::MEDIA));
+        }
+        let media = media.unwrap();
+
+        let image_location = get_image_location(properties, store);
+        if image_location.is_err() {
+            return Err(PipelineError::missing_clip(
+                inputs::IMAGE,
+                image_location.unwrap_err(),
+            ));
+        }
+        let image_location = image_location.unwrap();
+
+        let output = outputs.get(outputs::OUTPUT).unwrap();
+        let output = PipedType {
+            stream_type: output.property_type,
+            node_id,
+            property_name: String::from(outputs::OUTPUT),
+            io: InputOrOutput::Output,
+            cache_id: None,
+            format: output.format,
+        };
+
+        let timeline = output.create_timeline();
+        // layers with a lower priority are drawn on top, so the image's layer is added first
+        let image_layer = timeline.append_layer();
+        let media_layer = timeline.append_layer();
+
+        let location = media.get_gst_save_location_with_cache();
+        ges::Asset::needs_reload(ges::UriClip::static_type(), Some(location.as_str()));
+        let media_asset = ges::UriClipAsset::request_sync(location.as_str())?;
+        media_layer.add_asset(&media_asset, None, None, None, TrackType::UNKNOWN)?;
+        let media_duration = media_asset.duration();
+        if media_duration.is_none() {
+            return Err(PipelineError::unknown_duration(inputs::MEDIA));
+        }
+        let media_duration = media_duration.unwrap().mseconds();
+
+        let image_asset = ges::UriClipAsset::request_sync(image_location.as_str());
+        if image_asset.is_err() {
+            return Err(PipelineError::new(
+                PipelineErrorKind::Ges,
+                format!("Could not load image: {}", image_asset.unwrap_err()),
+            )
+            .with_property(inputs::IMAGE));
+        }
+        let image_asset = image_asset.unwrap();
+
+        let start = (get_number_property(properties, inputs::START, 0.0) * 1000.0) as u64;
+        let mut end = (get_number_property(properties, inputs::END, 0.0) * 1000.0) as u64;
+        if end == 0 || end > media_duration {
+            end = media_duration;
+        }
+        if start >= end {
+            return Err(
+                PipelineError::from(String::from("The image must start before it ends"))
+                    .with_property(inputs::END),
+            );
+        }
+
+        let image_clip = image_layer.add_asset(
+            &image_asset,
+            Some(gst::ClockTime::from_mseconds(start)),
+            None,
+            Some(gst::ClockTime::from_mseconds(end - start)),
+            TrackType::VIDEO,
+        )?;
+
+        let (width, height) = get_image_size(&image_asset)?;
+        let scale = get_number_property(properties, inputs::SCALE, 1.0);
+        let child_properties = [
+            (
+                "posx",
+                (get_number_property(properties, inputs::X, 20.0) as i32).to_value(),
+            ),
+            (
+                "posy",
+                (get_number_property(properties, inputs::Y, 20.0) as i32).to_value(),
+            ),
+            ("width", ((width as f64 * scale) as i32).to_value()),
+            ("height", ((height as f64 * scale) as i32).to_value()),
+            (
+                "alpha",
+                get_number_property(properties, inputs::OPACITY, 1.0).to_value(),
+            ),
+        ];
+        for (name, value) in child_properties {
+            if image_clip.set_child_property(name, &value).is_err() {
+                return Err(PipelineError::new(
+                    PipelineErrorKind::Ges,
+                    format!("Could not set the image's {}", name),
+                ));
+            }
+        }
+
+        let mut hm = HashMap::new();
+        hm.insert(outputs::OUTPUT.to_string(), timeline);
+        Ok((
+            hm,
+            vec![
+                MemorySafetyWrapper::UriClipAsset(media_asset),
+                MemorySafetyWrapper::UriClipAsset(image_asset),
+            ],
+        ))
+    }
+}
+
+/**
+ * Gets the location of the image to overlay; an image source clip takes priority over a path on the server
+ */
+fn get_image_location(
+    properties: &HashMap<String, Value>,
+    store: &Store,
+) -> Result<String, String> {
+    if let Some(clip) = properties.get(inputs::IMAGE) {
+        let clip = serde_json::from_value::<ClipIdentifier>(clip.to_owned());
+        if clip.is_err() {
+            return Err(String::from("Clip identifier is malformed"));
+        }
+        let clip = clip.unwrap();
+        if clip.clip_type != ClipType::Source {
+            return Err(String::from("The image must be a source clip"));
+        }
+        let source_clip = store.clips.source.get(&clip.id);
+        if source_clip.is_none() {
+            return Err(String::from("Clip ID is invalid"));
+        }
+        return Ok(source_clip.unwrap().get_server_url());
+    }
+
+    if let Some(Value::String(path)) = properties.get(inputs::IMAGE_PATH) {
+        if !path.is_empty() {
+            let path = get_server_image_path(path);
+            if path.is_err() {
+                return Err(path.unwrap_err());
+            }
+            return Ok(format!("file:///{}", path.unwrap()).replace("\\", "/"));
+        }
+    }
+    Err(String::from("No image given"))
+}
+
+/**
+ * Resolves a server image path relative to the source files folder, rejecting any path which leads outside of it
+ */
+fn get_server_image_path(path: &str) -> Result<String, String> {
+    let source_folder = fs::canonicalize(source_files_location());
+    if source_folder.is_err() {
+        return Err(String::from("Could not find the source files folder"));
+    }
+    let source_folder = source_folder.unwrap();
+
+    let image_path = fs::canonicalize(source_folder.join(Path::new(path)));
+    if image_path.is_err() {
+        return Err(format!("Could not find the image {}", path));
+    }
+    let image_path = image_path.unwrap();
+    let relative_path = image_path.strip_prefix(&source_folder);
+    if relative_path.is_err() || !image_path.is_file() {
+        return Err(String::from(
+            "The image path must lead to a file inside the source files folder",
+        ));
+    }
+    // rebuilt from the configured folder, as canonical paths are prefixed with \\?\ on Windows
+    Ok(format!(
+        "{}/{}",
+        source_files_location(),
+        relative_path.unwrap().to_string_lossy()
+    ))
+}
+
+/**
+ * Gets the original width and height of an image
+ */
+fn get_image_size(image_asset: &ges::UriClipAsset) -> Result<(u32, u32), String> {
+    let video_stream = image_asset
+        .info()
+        .video_streams()
+        .into_iter()
+        .next()
+        .and_then(|stream| stream.downcast::<DiscovererVideoInfo>().ok());
+    if video_stream.is_none() {
+        return Err(String::from("Image has no video stream"));
+    }
+    let video_stream = video_stream.unwrap();
+    Ok((video_stream.width(), video_stream.height()))
+}
+
+pub fn watermark_node() -> NodeType {
+    NodeType::new(
+        IDENTIFIER,
+        "Watermark",
+        "Overlay an image, such as a logo, onto a media source",
+        NodeCategory::Video,
+        WatermarkNode,
+    )
+    .with_tags(&["logo", "image", "overlay"])
+    .with_units(inputs::X, "px")
+    .with_units(inputs::Y, "px")
+    .with_units(inputs::START, "s")
+    .with_units(inputs::END, "s")
+}
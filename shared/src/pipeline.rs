@@ -1,6 +1,11 @@
-use std::{collections::HashMap, fs, sync::mpsc, thread};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    sync::mpsc,
+    thread,
+};
 
-use ges::traits::TimelineExt;
+use ges::traits::{LayerExt, TimelineExt};
 use gst::{glib, prelude::*};
 use petgraph::graph::NodeIndex;
 use petgraph::visit::EdgeRef;
@@ -12,8 +17,11 @@ use uuid::Uuid;
 use crate::cache::Cache;
 use crate::{
     clip::{ClipIdentifier, ClipType},
-    node::{InputOrOutput, PipedType},
-    nodes::NodeRegister,
+    error::{PipelineError, PipelineErrorKind},
+    keyframes::AnimatedNumber,
+    node::{InputOrOutput, MemorySafetyWrapper, NodeContext, NodeTimelines, PipedType, Type},
+    node_group::{expand_node_groups, GroupEndpoint},
+    nodes::{node_group_node, NodeRegister},
 };
 
 use super::{
@@ -55,6 +63,7 @@ impl Pipeline {
 
     /**
      * Generates a directed graph representation; no data will be supplied to the nodes or edges
+     * Edges are weighted with the output and input properties they link, which may be a number stream output linked into a number property, rather than media
      * Also returns a `BiMap` indicating what node IDs correspond to the `NodeIndex` in the graph
      */
     pub fn get_graph(
@@ -65,7 +74,7 @@ impl Pipeline {
             DiGraph<HashMap<String, PipedType>, Option<(String, String)>>,
             BiMap<Uuid, NodeIndex>,
         ),
-        String,
+        PipelineError,
     > {
         let mut graph = DiGraph::new();
 
@@ -80,20 +89,32 @@ impl Pipeline {
             if node.node_type == output_node::IDENTIFIER {
                 let clip = node.properties.get(output_node::inputs::CLIP);
                 if clip.is_none() {
-                    return Err(format!("Output node with no clip detected!"));
+                    return Err(PipelineError::missing_clip(
+                        output_node::inputs::CLIP,
+                        String::from("Output node with no clip detected!"),
+                    )
+                    .for_node(*id));
                 }
                 let clip = clip.unwrap().to_owned();
                 let clip = serde_json::from_value::<ClipIdentifier>(clip);
 
                 if clip.is_err() {
-                    return Err(format!("Clip identifier for output node not valid!"));
+                    return Err(PipelineError::missing_clip(
+                        output_node::inputs::CLIP,
+                        String::from("Clip identifier for output node not valid!"),
+                    )
+                    .for_node(*id));
                 }
                 let clip = clip.unwrap();
 
                 if clip.clip_type != ClipType::Composited {
-                    return Err(format!(
-                        "Clip identifier for output node not valid (must be composited clip)!"
-                    ));
+                    return Err(PipelineError::missing_clip(
+                        output_node::inputs::CLIP,
+                        String::from(
+                            "Clip identifier for output node not valid (must be composited clip)!",
+                        ),
+                    )
+                    .for_node(*id));
                 }
                 composited_clip_to_index.insert(clip.id, node_idx);
             }
@@ -104,21 +125,33 @@ impl Pipeline {
             if node.node_type == media_import_node::IDENTIFIER {
                 let clip = node.properties.get(media_import_node::inputs::CLIP);
                 if clip.is_none() {
-                    return Err(format!("Input node with no clip detected!"));
+                    return Err(PipelineError::missing_clip(
+                        media_import_node::inputs::CLIP,
+                        String::from("Input node with no clip detected!"),
+                    )
+                    .for_node(*id));
                 }
 
                 let clip = clip.unwrap().to_owned();
                 let clip = serde_json::from_value::<ClipIdentifier>(clip);
 
                 if clip.is_err() {
-                    return Err(format!("Clip identifier for input node not valid!"));
+                    return Err(PipelineError::missing_clip(
+                        media_import_node::inputs::CLIP,
+                        String::from("Clip identifier for input node not valid!"),
+                    )
+                    .for_node(*id));
                 }
                 let clip = clip.unwrap();
 
                 if clip.clip_type == ClipType::Composited {
                     let composited_clip_idx = composited_clip_to_index.get_by_left(&clip.id);
                     if composited_clip_idx.is_none() {
-                        return Err(format!("Reference to composited clip with no output!"));
+                        return Err(PipelineError::missing_clip(
+                            media_import_node::inputs::CLIP,
+                            String::from("Reference to composited clip with no output!"),
+                        )
+                        .for_node(*id));
                     }
                     let composited_clip_idx = composited_clip_idx.unwrap();
 
@@ -136,7 +169,9 @@ impl Pipeline {
             );
 
             if from_node_idx.is_none() || to_node_idx.is_none() {
-                return Err(format!("Link contains reference to non-existent node"));
+                return Err(PipelineError::from(String::from(
+                    "Link contains reference to non-existent node",
+                )));
             }
             let (from_node_idx, to_node_idx) = (from_node_idx.unwrap(), to_node_idx.unwrap());
 
@@ -150,8 +185,105 @@ impl Pipeline {
         Ok((graph, node_id_to_index))
     }
 
+    /**
+     * Checks that a link which is about to be added joins compatible types
+     * Only the nodes the link comes from are generated to find the output's type, and the input is found from the node it goes into with the output piped into it, so problems elsewhere in the pipeline do not stop links being made
+     */
+    pub fn check_link(
+        &self,
+        link: &Link,
+        store: &Store,
+        node_register: &NodeRegister,
+        cache: &Cache,
+    ) -> Result<(), String> {
+        let (from_node, to_node) = (
+            store.nodes.get(&link.from.node_id),
+            store.nodes.get(&link.to.node_id),
+        );
+        if from_node.is_none() || to_node.is_none() {
+            return Err(format!("Link contains reference to non-existent node"));
+        }
+        let to_node = to_node.unwrap();
+        let to_node_type = node_register.get(&to_node.node_type);
+        if to_node_type.is_none() {
+            return Err(format!(
+                "The node type {} does not exist",
+                to_node.node_type
+            ));
+        }
+
+        let upstream = get_upstream_store(store, &link.from.node_id);
+        if upstream.nodes.contains_key(&link.to.node_id) {
+            return Err(format!("The link would form a cycle"));
+        }
+        let types = upstream
+            .pipeline
+            .generate_pipeline(&upstream, node_register, false, cache);
+        if types.is_err() {
+            return Err(format!(
+                "The link cannot be checked, since the nodes it comes from could not be generated: {}",
+                types.unwrap_err()
+            ));
+        }
+        let (node_type_data, composited_clip_data, error) = types.unwrap();
+
+        let output = node_type_data
+            .get(&link.from.node_id)
+            .and_then(|(_, _, outputs)| outputs.get(&link.from.property));
+        if output.is_none() {
+            return Err(match error {
+                Some(error) => format!(
+                    "The link cannot be checked, since the nodes it comes from could not be generated: {}",
+                    error
+                ),
+                None => format!("Node has no output {}", link.from.property),
+            });
+        }
+        let output = output.unwrap();
+
+        // the node's inputs can depend on what is piped into it
+        let mut piped_inputs = HashMap::new();
+        if output.number_stream.is_none() {
+            piped_inputs.insert(
+                link.to.property.clone(),
+                PipedType {
+                    stream_type: output.property_type,
+                    node_id: link.to.node_id,
+                    property_name: link.to.property.clone(),
+                    io: InputOrOutput::Input,
+                    cache_id: None,
+                    format: output.format,
+                },
+            );
+        }
+        let io = to_node_type.unwrap().kind.get_io(&NodeContext {
+            node_id: to_node.id,
+            properties: &to_node.properties,
+            piped_inputs: &piped_inputs,
+            composited_clip_types: &composited_clip_data,
+            store,
+            node_register,
+        });
+        if io.is_err() {
+            return Err(format!(
+                "The link cannot be checked, since the inputs of the node it goes into could not be found: {}",
+                io.unwrap_err()
+            ));
+        }
+        let (inputs, _) = io.unwrap();
+
+        let input = inputs.get(&link.to.property);
+        if input.is_none() {
+            return Err(format!("Node has no input {}", link.to.property));
+        }
+        output.check_link_to(input.unwrap())
+    }
+
     /**
      * Generates the pipeline; will not generate timeline files if `get_output` = `false`
+     * Errors in working out a node's inputs and outputs, generating its output, or a link between incompatible types do not stop the generation, so the types of every node which does not depend on the error are still found; the first such error is returned alongside the types instead
+     * Group nodes are first replaced with copies of the nodes in their groups, and are then given the inputs and outputs of the nodes they stand for
+     * Number streams are passed on by replacing the linked properties of the nodes they are linked into
      * Will utilise the cache when possible
      */
     pub fn generate_pipeline(
@@ -171,25 +303,38 @@ impl Pipeline {
                 ),
             >,
             HashMap<Uuid, PipedType>,
-            bool,
+            Option<PipelineError>,
         ),
-        String,
+        PipelineError,
     > {
-        let (mut graph, node_id_to_index) = self.get_graph(store)?;
+        let (expanded_store, expansions) = expand_node_groups(store)?;
+        let store = &expanded_store;
+        let (mut graph, node_id_to_index) = store.pipeline.get_graph(store)?;
 
         // topologically sort graph
         let sorted = petgraph::algo::toposort(&graph, None);
         // if there's a cycle, it's an invalid pipeline anyway
         if sorted.is_err() {
-            return Err(format!("Found cycle in the graph!"));
+            let node_id = node_id_to_index.get_by_right(&sorted.unwrap_err().node_id());
+            let error = PipelineError::new(
+                PipelineErrorKind::Cycle,
+                String::from("Found cycle in the graph!"),
+            );
+            return Err(match node_id {
+                Some(node_id) => error.for_node(*node_id),
+                None => error,
+            });
         }
 
         let mut node_type_data = HashMap::new();
         let mut composited_clip_data = HashMap::new();
+        // the number streams linked into each node, by the property they are linked into
+        let mut number_inputs: HashMap<NodeIndex, HashMap<String, AnimatedNumber>> = HashMap::new();
 
         let sorted = sorted.unwrap();
 
-        let mut do_return = true;
+        let mut do_return = get_output;
+        let mut output_error = None;
 
         // we can then iterate through the nodes in this order, assign the piped inputs to the dependent nodes before the dependent nodes' relevant method is called
         for node_idx in sorted {
@@ -200,47 +345,110 @@ impl Pipeline {
                 .nodes
                 .get(node_id_to_index.get_by_right(&node_idx).unwrap())
                 .unwrap();
-            let node_registration = node_register.get(&node.node_type).unwrap();
-            let io = (node_registration.get_io)(
-                node.id.clone(),
-                &node.properties,
-                &piped_inputs,
-                &composited_clip_data,
+            // node types defined by effect definitions may have failed to load, or been removed
+            let node_registration = node_register.get(&node.node_type);
+            if node_registration.is_none() {
+                return Err(PipelineError::new(
+                    PipelineErrorKind::Other,
+                    format!("The node type {} does not exist", node.node_type),
+                )
+                .for_node(node.id));
+            }
+            let node_registration = node_registration.unwrap();
+
+            // number streams linked into the node take the place of the values of those properties
+            let mut properties = node.properties.clone();
+            if let Some(number_streams) = number_inputs.get(&node_idx) {
+                for (property, number_stream) in number_streams {
+                    properties.insert(
+                        property.clone(),
+                        serde_json::to_value(number_stream).unwrap(),
+                    );
+                }
+            }
+
+            let context = NodeContext {
+                node_id: node.id.clone(),
+                properties: &properties,
+                piped_inputs: &piped_inputs,
+                composited_clip_types: &composited_clip_data,
                 store,
                 node_register,
-            );
+            };
+            let io = node_registration.kind.get_io(&context);
             // get the inputs and outputs based off the current set of piped inputs, properties, etc.
 
+            // a node whose inputs and outputs cannot be found is given its default inputs and no outputs, so the nodes which do not depend on it still get their types
             if io.is_err() {
-                return Err(format!(
-                    "Could not find IO data for node {}: {}",
-                    node.id,
-                    io.unwrap_err()
-                ));
+                do_return = false;
+                if output_error.is_none() {
+                    output_error = Some(io.unwrap_err().for_node(node.id));
+                }
+                node_type_data.insert(
+                    node.id.clone(),
+                    (
+                        piped_inputs.clone(),
+                        node_registration.default_properties.clone(),
+                        HashMap::new(),
+                    ),
+                );
+                continue;
+            }
+            let (inputs, mut outputs) = io.unwrap();
+
+            // bypassed nodes pass their primary media input straight through, so the output takes on the input's streams and format
+            let bypass = if node.bypassed {
+                get_bypass_io(&inputs, &outputs, piped_inputs)
+            } else {
+                None
+            };
+            if let Some((input, output)) = &bypass {
+                let piped_input = piped_inputs.get(input).unwrap();
+                let output = outputs.get_mut(output).unwrap();
+                output.property_type = piped_input.stream_type;
+                output.format = piped_input.format;
             }
-            let (inputs, outputs) = io.unwrap();
 
             let data = (piped_inputs.clone(), inputs.clone(), outputs.clone());
             // println!("Data for node {}: {:#?}", node.id.clone(), data.clone());
             node_type_data.insert(node.id.clone(), data);
 
-            let pipeline = if get_output {
-                (node_registration.get_output)(
-                    node.id.clone(),
-                    &node.properties,
-                    &piped_inputs,
-                    &composited_clip_data,
-                    store,
-                    node_register,
-                )
+            // check that every link into this node joins compatible types; like an error generating an output, an invalid link stops the output being generated, but the types of the nodes after it are still found, so every invalid link can be flagged
+            for edge in graph.edges_directed(node_idx, EdgeDirection::Incoming) {
+                if let Some((from_property, to_property)) = edge.weight() {
+                    let from_node = node_id_to_index.get_by_right(&edge.source()).unwrap();
+                    let output = node_type_data
+                        .get(from_node)
+                        .and_then(|(_, _, outputs)| outputs.get(from_property));
+                    if let (Some(output), Some(input)) = (output, inputs.get(to_property)) {
+                        let valid = output.check_link_to(input);
+                        if valid.is_err() && output_error.is_none() {
+                            do_return = false;
+                            output_error = Some(
+                                PipelineError::type_mismatch(
+                                    to_property,
+                                    format!("Invalid link: {}", valid.unwrap_err()),
+                                )
+                                .for_node(node.id),
+                            );
+                        }
+                    }
+                }
+            }
+
+            let pipeline = if !do_return {
+                None
+            } else if let Some((input, output)) = &bypass {
+                Some(get_bypass_output(piped_inputs.get(input).unwrap(), output))
             } else {
-                Err(String::from(""))
+                Some(node_registration.kind.get_output(&context))
             };
 
-            if pipeline.is_err() {
+            if let Some(Err(error)) = pipeline {
                 do_return = false;
-            } else {
-                let (timeline_outputs, memory_safety_vars) = pipeline.unwrap();
+                output_error = Some(error.for_node(node.id));
+            } else if let Some(Ok(pipeline)) = pipeline {
+                let (timeline_outputs, memory_safety_vars) = pipeline;
 
                 for (k, v) in timeline_outputs {
                     let out_type = outputs.get(&k).unwrap();
@@ -251,12 +459,16 @@ impl Pipeline {
                         property_name: k.clone(),
                         io: InputOrOutput::Output,
                         cache_id: None,
+                        format: out_type.format,
                     };
 
                     let output_location = from_piped_type.get_gst_save_location();
 
-                    v.save_to_uri(output_location.as_str(), None as Option<&ges::Asset>, true)
-                        .unwrap();
+                    let saved =
+                        v.save_to_uri(output_location.as_str(), None as Option<&ges::Asset>, true);
+                    if saved.is_err() {
+                        return Err(PipelineError::from(saved.unwrap_err()).for_node(node.id));
+                    }
                     ges::Asset::needs_reload(
                         ges::UriClip::static_type(),
                         Some(output_location.as_str()),
@@ -297,14 +509,42 @@ impl Pipeline {
                     let (from_property, to_property) = edge.weight().as_ref().unwrap();
                     let target = edge.target();
 
-                    let out_type = outputs.get(from_property).unwrap();
+                    let out_type = outputs.get(from_property);
+                    if out_type.is_none() {
+                        return Err(PipelineError::new(
+                            PipelineErrorKind::Other,
+                            format!(
+                                "A link comes from {}, which the node does not have",
+                                from_property
+                            ),
+                        )
+                        .for_node(node.id));
+                    }
+                    let out_type = out_type.unwrap();
+
+                    // number streams have no files to copy; they are substituted into the next node's properties instead
+                    if let Some(number_stream) = &out_type.number_stream {
+                        number_inputs
+                            .entry(target)
+                            .or_insert_with(HashMap::new)
+                            .insert(to_property.clone(), number_stream.clone());
+                        continue;
+                    }
+
                     let to_node = node_id_to_index.get_by_right(&target).unwrap();
 
                     let next_node_inputs = graph.node_weight_mut(target).unwrap();
 
                     let cache_id = if let Some(node_outputs) = cache.get(&node.id) {
-                        let output = node_outputs.get(from_property).unwrap();
-                        Some(output.clone())
+                        let output = node_outputs.get(from_property);
+                        if output.is_none() {
+                            return Err(PipelineError::new(
+                                PipelineErrorKind::Other,
+                                format!("The cached output {} no longer exists", from_property),
+                            )
+                            .for_node(node.id));
+                        }
+                        Some(output.unwrap().clone())
                     } else {
                         None
                     };
@@ -315,6 +555,7 @@ impl Pipeline {
                         property_name: from_property.clone(),
                         io: InputOrOutput::Output,
                         cache_id,
+                        format: out_type.format,
                     };
 
                     let to_piped_type = PipedType {
@@ -323,12 +564,16 @@ impl Pipeline {
                         property_name: to_property.clone(),
                         io: InputOrOutput::Input,
                         cache_id,
+                        format: out_type.format,
                     };
 
                     if do_return {
                         let from_location = from_piped_type.get_save_location();
                         let to_location = to_piped_type.get_save_location();
-                        fs::copy(from_location, to_location).unwrap();
+                        let copied = fs::copy(from_location, to_location);
+                        if copied.is_err() {
+                            return Err(PipelineError::from(copied.unwrap_err()).for_node(node.id));
+                        }
 
                         let to_location = to_piped_type.get_gst_save_location();
                         ges::Asset::needs_reload(
@@ -342,10 +587,436 @@ impl Pipeline {
             }
         }
 
+        // group nodes take on the types of the inputs and outputs of the nodes they stand for
+        for (group_node_id, expansion) in expansions {
+            let mut piped_inputs = HashMap::new();
+            let mut inputs = node_register
+                .get(node_group_node::IDENTIFIER)
+                .unwrap()
+                .default_properties
+                .clone();
+            let mut outputs = HashMap::new();
+            for GroupEndpoint {
+                name,
+                display_name,
+                endpoint,
+            } in expansion.inputs
+            {
+                if let Some((inner_piped_inputs, inner_inputs, _)) =
+                    node_type_data.get(&endpoint.node_id)
+                {
+                    if let Some(piped_input) = inner_piped_inputs.get(&endpoint.property) {
+                        piped_inputs.insert(name.clone(), piped_input.clone());
+                    }
+                    if let Some(input) = inner_inputs.get(&endpoint.property) {
+                        inputs.insert(
+                            name.clone(),
+                            NodeTypeInput {
+                                name,
+                                display_name,
+                                ..input.clone()
+                            },
+                        );
+                    }
+                }
+            }
+            for GroupEndpoint {
+                name,
+                display_name,
+                endpoint,
+            } in expansion.outputs
+            {
+                if let Some((_, _, inner_outputs)) = node_type_data.get(&endpoint.node_id) {
+                    if let Some(output) = inner_outputs.get(&endpoint.property) {
+                        outputs.insert(
+                            name.clone(),
+                            NodeTypeOutput {
+                                name,
+                                display_name,
+                                ..output.clone()
+                            },
+                        );
+                    }
+                }
+            }
+            node_type_data.insert(group_node_id, (piped_inputs, inputs, outputs));
+        }
+
         // we should then have populated both the node type hashmap and the composited clip type hashmap.
 
-        let output = (node_type_data, composited_clip_data, do_return);
+        let output = (node_type_data, composited_clip_data, output_error);
 
         return Ok(output);
     }
 }
+
+/**
+ * Gets the input and output a bypassed node passes its media through: the first media input with something piped into it (so `media1` before `media2`), and the first media output
+ * Returns `None` if the node has no such input or output, in which case it cannot be bypassed
+ */
+fn get_bypass_io(
+    inputs: &HashMap<String, NodeTypeInput>,
+    outputs: &HashMap<String, NodeTypeOutput>,
+    piped_inputs: &HashMap<String, PipedType>,
+) -> Option<(String, String)> {
+    let input = inputs
+        .values()
+        .filter(|input| matches!(input.property_type, Type::Pipeable(_, _)))
+        .map(|input| &input.name)
+        .filter(|name| piped_inputs.contains_key(*name))
+        .min()?;
+    let output = get_first_media_output(outputs)?;
+    Some((input.clone(), output))
+}
+
+/**
+ * Gets the first media output of a node, by name (so `output1` before `output2`), skipping any number streams
+ */
+fn get_first_media_output(outputs: &HashMap<String, NodeTypeOutput>) -> Option<String> {
+    outputs
+        .values()
+        .filter(|output| output.number_stream.is_none())
+        .map(|output| &output.name)
+        .min()
+        .cloned()
+}
+
+/**
+ * Gets the output of a bypassed node, which is its input unchanged
+ */
+fn get_bypass_output(
+    piped_input: &PipedType,
+    output: &str,
+) -> Result<NodeTimelines, PipelineError> {
+    let timeline = piped_input.create_timeline();
+
+    let location = piped_input.get_gst_save_location_with_cache();
+    ges::Asset::needs_reload(ges::UriClip::static_type(), Some(location.as_str()));
+    let asset = ges::UriClipAsset::request_sync(location.as_str())?;
+
+    let layer = timeline.append_layer();
+    layer.add_asset(&asset, None, None, None, ges::TrackType::UNKNOWN)?;
+
+    let mut timelines = HashMap::new();
+    timelines.insert(String::from(output), timeline);
+    Ok((timelines, vec![MemorySafetyWrapper::UriClipAsset(asset)]))
+}
+
+/**
+ * Gets a copy of the store for previewing a soloed node in a composited clip; only the solo node and the nodes it depends on are kept, and the solo node's first media output is linked into the clip's output node in place of its media
+ * Returns the store unchanged if no node is soloed
+ */
+pub fn get_soloed_store(
+    store: &Store,
+    node_register: &NodeRegister,
+    composited_clip_id: &ID,
+) -> Result<Store, PipelineError> {
+    let solo_node = match store.solo_node.and_then(|id| store.nodes.get(&id)) {
+        Some(node) if node.node_type != output_node::IDENTIFIER => node,
+        _ => return Ok(store.clone()),
+    };
+
+    // the solo node's outputs depend on what is piped into it, so are found from the nodes it depends on
+    let upstream = get_upstream_store(store, &solo_node.id);
+    let (node_type_data, _, _) =
+        upstream
+            .pipeline
+            .generate_pipeline(&upstream, node_register, false, &Cache::new())?;
+    let solo_output = node_type_data
+        .get(&solo_node.id)
+        .and_then(|(_, _, outputs)| get_first_media_output(outputs));
+    if solo_output.is_none() {
+        return Err(PipelineError::new(
+            PipelineErrorKind::Other,
+            String::from("The soloed node has no media output to preview"),
+        )
+        .for_node(solo_node.id));
+    }
+
+    Ok(link_solo_node_to_clip(
+        store,
+        &upstream,
+        &solo_node.id,
+        &solo_output.unwrap(),
+        composited_clip_id,
+    ))
+}
+
+/**
+ * Gets a copy of the store with only the given node and the nodes it depends on, including through composited clips
+ * Only the links are followed, so problems in the rest of the pipeline do not stop it being found
+ */
+fn get_upstream_store(store: &Store, node_id: &ID) -> Store {
+    let mut kept = HashSet::new();
+    let mut nodes_to_visit = vec![*node_id];
+    while let Some(id) = nodes_to_visit.pop() {
+        if !store.nodes.contains_key(&id) || !kept.insert(id) {
+            continue;
+        }
+        for link in &store.pipeline.links {
+            if link.to.node_id == id {
+                nodes_to_visit.push(link.from.node_id);
+            }
+        }
+
+        // importing a composited clip depends on the nodes which output to it
+        let node = &store.nodes[&id];
+        if node.node_type != media_import_node::IDENTIFIER {
+            continue;
+        }
+        let clip_id = get_composited_clip(node, media_import_node::inputs::CLIP);
+        if clip_id.is_none() {
+            continue;
+        }
+        for (output_id, output) in &store.nodes {
+            if output.node_type == output_node::IDENTIFIER
+                && get_composited_clip(output, output_node::inputs::CLIP) == clip_id
+            {
+                nodes_to_visit.push(*output_id);
+            }
+        }
+    }
+
+    let mut upstream = store.clone();
+    upstream.nodes.retain(|id, _| kept.contains(id));
+    upstream
+        .pipeline
+        .links
+        .retain(|link| kept.contains(&link.from.node_id) && kept.contains(&link.to.node_id));
+    upstream
+}
+
+/**
+ * Gets the composited clip a clip property refers to, if it refers to one
+ */
+fn get_composited_clip(node: &Node, property: &str) -> Option<ID> {
+    let clip = node
+        .properties
+        .get(property)
+        .and_then(|clip| serde_json::from_value::<ClipIdentifier>(clip.clone()).ok())?;
+    if clip.clip_type != ClipType::Composited {
+        return None;
+    }
+    Some(clip.id)
+}
+
+/**
+ * Adds the output nodes of a composited clip to the nodes a solo node depends on, with the solo node's output linked into them
+ * Output nodes which the solo node itself depends on are left as they are, since linking the solo node into them would form a cycle
+ */
+fn link_solo_node_to_clip(
+    store: &Store,
+    upstream: &Store,
+    solo_node_id: &ID,
+    solo_output: &str,
+    composited_clip_id: &ID,
+) -> Store {
+    let mut soloed = upstream.clone();
+    for (id, node) in &store.nodes {
+        if node.node_type != output_node::IDENTIFIER || upstream.nodes.contains_key(id) {
+            continue;
+        }
+        if get_composited_clip(node, output_node::inputs::CLIP) != Some(*composited_clip_id) {
+            continue;
+        }
+        soloed.nodes.insert(*id, node.clone());
+        soloed.pipeline.links.push(Link {
+            from: LinkEndpoint {
+                node_id: *solo_node_id,
+                property: String::from(solo_output),
+            },
+            to: LinkEndpoint {
+                node_id: *id,
+                property: String::from(output_node::inputs::MEDIA),
+            },
+        });
+    }
+    soloed
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use crate::{
+        cache::Cache,
+        clip::{ClipIdentifier, ClipType},
+        node::Node,
+        nodes::{blur_node, expression_node, media_import_node, output_node},
+        store::Store,
+    };
+
+    use std::collections::HashMap;
+
+    use super::{get_soloed_store, get_upstream_store, link_solo_node_to_clip, Link, LinkEndpoint};
+
+    fn link(from: &Node, to: &Node, to_property: &str) -> Link {
+        Link {
+            from: LinkEndpoint {
+                node_id: from.id,
+                property: String::from(blur_node::outputs::OUTPUT),
+            },
+            to: LinkEndpoint {
+                node_id: to.id,
+                property: String::from(to_property),
+            },
+        }
+    }
+
+    #[test]
+    fn test_get_soloed_store() {
+        let mut import = Node::new(String::from(media_import_node::IDENTIFIER), None);
+        import.properties.insert(
+            String::from(media_import_node::inputs::CLIP),
+            json!(ClipIdentifier {
+                id: Uuid::new_v4(),
+                clip_type: ClipType::Source,
+            }),
+        );
+        let blur1 = Node::new(String::from(blur_node::IDENTIFIER), None);
+        let blur2 = Node::new(String::from(blur_node::IDENTIFIER), None);
+        let output_node = |clip_id| {
+            let mut output = Node::new(String::from(output_node::IDENTIFIER), None);
+            output.properties.insert(
+                String::from(output_node::inputs::CLIP),
+                json!(ClipIdentifier {
+                    id: clip_id,
+                    clip_type: ClipType::Composited,
+                }),
+            );
+            output
+        };
+        let clip_id = Uuid::new_v4();
+        let output = output_node(clip_id);
+        let other_output = output_node(Uuid::new_v4());
+
+        let mut store = Store::new();
+        store.pipeline.links = vec![
+            link(&import, &blur1, blur_node::inputs::MEDIA),
+            link(&blur1, &blur2, blur_node::inputs::MEDIA),
+            link(&blur2, &output, output_node::inputs::MEDIA),
+            link(&blur2, &other_output, output_node::inputs::MEDIA),
+        ];
+        for node in [&import, &blur1, &blur2, &output, &other_output] {
+            store.nodes.insert(node.id, node.clone());
+        }
+
+        // nothing is soloed, so nothing changes
+        let soloed = get_soloed_store(&store, &HashMap::new(), &clip_id).unwrap();
+        assert_eq!(soloed.nodes.len(), 5);
+
+        store.solo_node = Some(blur1.id);
+        let upstream = get_upstream_store(&store, &blur1.id);
+        assert_eq!(upstream.nodes.len(), 2);
+        let soloed = link_solo_node_to_clip(
+            &store,
+            &upstream,
+            &blur1.id,
+            blur_node::outputs::OUTPUT,
+            &clip_id,
+        );
+        // only the output node of the previewed clip is kept
+        assert_eq!(soloed.nodes.len(), 3);
+        assert!(!soloed.nodes.contains_key(&blur2.id));
+        assert!(!soloed.nodes.contains_key(&other_output.id));
+        let links: Vec<String> = soloed.pipeline.links.iter().map(Link::get_id).collect();
+        assert_eq!(links.len(), 2);
+        assert!(links.contains(&link(&import, &blur1, blur_node::inputs::MEDIA).get_id()));
+        assert!(links.contains(&link(&blur1, &output, output_node::inputs::MEDIA).get_id()));
+    }
+
+    #[test]
+    fn test_generate_pipeline_with_invalid_node() {
+        let mut node_register = HashMap::new();
+        let node_type = expression_node::expression_node();
+        node_register.insert(node_type.id.clone(), node_type);
+
+        let expression = |text: &str| {
+            let mut node = Node::new(String::from(expression_node::IDENTIFIER), None);
+            node.properties.insert(
+                String::from(expression_node::inputs::EXPRESSION),
+                json!(text),
+            );
+            node
+        };
+        let invalid = expression("a +");
+        let mut source = expression("a + 1");
+        source
+            .properties
+            .insert(String::from(expression_node::inputs::A), json!(1.0));
+        let target = expression("a * 2");
+
+        let mut store = Store::new();
+        store.pipeline.links = vec![Link {
+            from: LinkEndpoint {
+                node_id: source.id,
+                property: String::from(expression_node::outputs::VALUE),
+            },
+            to: LinkEndpoint {
+                node_id: target.id,
+                property: String::from(expression_node::inputs::A),
+            },
+        }];
+        for node in [&invalid, &source, &target] {
+            store.nodes.insert(node.id, node.clone());
+        }
+
+        // the invalid expression is reported on its node, and the other chain still gets its types
+        let (node_type_data, _, error) = store
+            .pipeline
+            .generate_pipeline(&store, &node_register, false, &Cache::new())
+            .unwrap();
+        assert_eq!(error.unwrap().node_id, Some(invalid.id));
+        assert!(node_type_data[&invalid.id].2.is_empty());
+        let value = node_type_data[&target.id].2[expression_node::outputs::VALUE]
+            .number_stream
+            .clone()
+            .unwrap();
+        assert_eq!(value.initial_value(), 4.0);
+    }
+
+    #[test]
+    fn test_check_link() {
+        let mut node_register = HashMap::new();
+        for node_type in [
+            expression_node::expression_node(),
+            blur_node::blur_node(),
+            media_import_node::media_import_node(),
+        ] {
+            node_register.insert(node_type.id.clone(), node_type);
+        }
+
+        let number_link = |from: &Node, to: &Node, to_property: &str| Link {
+            from: LinkEndpoint {
+                node_id: from.id,
+                property: String::from(expression_node::outputs::VALUE),
+            },
+            to: LinkEndpoint {
+                node_id: to.id,
+                property: String::from(to_property),
+            },
+        };
+        // an import with no clip elsewhere in the pipeline should not stop links being checked
+        let broken = Node::new(String::from(media_import_node::IDENTIFIER), None);
+        let source = Node::new(String::from(expression_node::IDENTIFIER), None);
+        let target = Node::new(String::from(expression_node::IDENTIFIER), None);
+        let blur = Node::new(String::from(blur_node::IDENTIFIER), None);
+
+        let mut store = Store::new();
+        store.pipeline.links = vec![number_link(&source, &target, expression_node::inputs::A)];
+        for node in [&broken, &source, &target, &blur] {
+            store.nodes.insert(node.id, node.clone());
+        }
+        let check = |link: Link| {
+            store
+                .pipeline
+                .check_link(&link, &store, &node_register, &Cache::new())
+        };
+
+        assert!(check(number_link(&target, &source, expression_node::inputs::B)).is_err());
+        assert!(check(number_link(&source, &target, expression_node::inputs::B)).is_ok());
+        assert!(check(number_link(&source, &blur, blur_node::inputs::SIGMA)).is_ok());
+        assert!(check(number_link(&source, &blur, blur_node::inputs::MEDIA)).is_err());
+    }
+}
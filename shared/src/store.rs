@@ -8,7 +8,7 @@ use uuid::Uuid;
 
 use crate::{
     clip::{ClipIdentifier, ClipType},
-    nodes::output_node,
+    nodes::{output_node, NodeRegister},
 };
 
 /// ---------------------------------------------------------------------------------------
@@ -29,6 +29,7 @@ where
 use super::{
     clip::{CompositedClip, SourceClip},
     node::Node,
+    node_group::NodeGroup,
     pipeline::Pipeline,
     ID,
 };
@@ -54,6 +55,12 @@ pub struct Store {
     pub nodes: HashMap<ID, Node>,
     pub clips: ClipStore,
     pub pipeline: Pipeline,
+    /// The saved groups of nodes which can be added as group nodes
+    #[serde(default, serialize_with = "ordered_map")]
+    pub node_groups: HashMap<ID, NodeGroup>,
+    /// If set, the preview only renders the pipeline up to this node, showing its output in every composited clip
+    #[serde(default)]
+    pub solo_node: Option<ID>,
 }
 impl Store {
     pub fn new() -> Self {
@@ -61,13 +68,19 @@ impl Store {
             nodes: HashMap::new(),
             clips: ClipStore::new(),
             pipeline: Pipeline::new(),
+            node_groups: HashMap::new(),
+            solo_node: None,
         }
     }
 
     /**
      * Utility function for obtaining a store via a JSON file
+     * Nodes saved with older versions of their node types are migrated to the current versions in the register; a description of each change is returned alongside the store
      */
-    pub fn from_file(filename: String) -> Result<Self, String> {
+    pub fn from_file(
+        filename: String,
+        node_register: &NodeRegister,
+    ) -> Result<(Self, Vec<String>), String> {
         let f = std::fs::read(filename);
 
         if f.is_err() {
@@ -80,7 +93,37 @@ impl Store {
         if store.is_err() {
             return Err(store.unwrap_err().to_string());
         }
-        Ok(store.unwrap())
+        let mut store: Store = store.unwrap();
+        let changes = store.migrate_nodes(node_register);
+        Ok((store, changes))
+    }
+
+    /**
+     * Migrates every node, including those in node groups, which was saved with an older version of its node type
+     * Returns a description of each change made; nodes of unknown types are left as they are
+     */
+    pub fn migrate_nodes(&mut self, node_register: &NodeRegister) -> Vec<String> {
+        let nodes = self.nodes.values_mut().chain(
+            self.node_groups
+                .values_mut()
+                .flat_map(|group| group.nodes.values_mut()),
+        );
+
+        let mut changes = Vec::new();
+        for node in nodes {
+            let node_type = node_register.get(&node.node_type);
+            if node_type.is_none() || node_type.unwrap().version == node.type_version {
+                continue;
+            }
+            let old_version = node.type_version.clone();
+            for change in node_type.unwrap().migrate(node) {
+                changes.push(format!(
+                    "{} ({} {} to {}): {}",
+                    node.id, node.node_type, old_version, node.type_version, change
+                ));
+            }
+        }
+        changes
     }
 
     /**
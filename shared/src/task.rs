@@ -4,6 +4,7 @@ use uuid::Uuid;
 use crate::{
     clip::{ClipIdentifier, ClipType, CompositedClip, SourceClip},
     node::Node,
+    node_group::NodeGroup,
     pipeline::Link,
     store::Store,
     ID,
@@ -19,6 +20,8 @@ pub enum Task {
     UpdateClip(ID, ClipType, Value),
     CreateSourceClip(SourceClip),
     CreateCompositedClip(CompositedClip),
+    CreateNodeGroup(NodeGroup),
+    SoloNode(Option<ID>),
 }
 
 #[derive(Clone)]
@@ -31,6 +34,8 @@ pub enum NetworkTask {
     DeleteLinks(ID, Option<String>),
     DeleteNode(ID),
     UpdateClip(ID, ClipType),
+    CreateNodeGroup(ID),
+    SoloNode,
 }
 
 impl Task {
@@ -94,6 +99,9 @@ impl Task {
                         .collect();
                     store.pipeline.links = new_links;
                     store.nodes.remove(&id);
+                    if store.solo_node == Some(id) {
+                        store.solo_node = None;
+                    }
 
                     network_jobs.push(NetworkTask::DeleteNode(id));
                 }
@@ -116,6 +124,14 @@ impl Task {
                     store.clips.composited.insert(clip.id.clone(), clip);
                     store.nodes.insert(output_node.id.clone(), output_node);
                 }
+                Task::CreateNodeGroup(group) => {
+                    network_jobs.push(NetworkTask::CreateNodeGroup(group.id.clone()));
+                    store.node_groups.insert(group.id.clone(), group);
+                }
+                Task::SoloNode(id) => {
+                    store.solo_node = id;
+                    network_jobs.push(NetworkTask::SoloNode);
+                }
                 Task::UpdateClip(id, clip_type, clip) => {
                     match clip_type {
                         ClipType::Source => {
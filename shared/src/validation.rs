@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::{
+    clip::ClipIdentifier,
+    keyframes::AnimatedNumber,
+    node::{Node, NodeContext, NodeTypeInput, Restrictions, Type},
+    nodes::{is_animatable, NodeRegister},
+    store::Store,
+    ID,
+};
+
+/**
+ * Why the value of a property was rejected
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ValidationErrorKind {
+    WrongType { expected: String },
+    TooLong { max_length: i32 },
+    UnknownNodeType,
+}
+
+/**
+ * A property of a node which has a value that does not match its declared type
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    pub node_id: ID,
+    pub property: String,
+    pub kind: ValidationErrorKind,
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ValidationErrorKind::WrongType { expected } => write!(
+                f,
+                "Property {} of node {} must be a {}",
+                self.property, self.node_id, expected
+            ),
+            ValidationErrorKind::TooLong { max_length } => write!(
+                f,
+                "Property {} of node {} must be at most {} characters long",
+                self.property, self.node_id, max_length
+            ),
+            ValidationErrorKind::UnknownNodeType => {
+                write!(f, "Node {} has an unknown node type", self.node_id)
+            }
+        }
+    }
+}
+
+/**
+ * Checks a value against the type of the input it is for; numbers outside of the restrictions are clamped, so the value to use is returned
+ * Pipeable inputs are given their values through links, so any value is accepted
+ */
+pub fn validate_property(
+    input: &NodeTypeInput,
+    value: &Value,
+) -> Result<Value, ValidationErrorKind> {
+    match input.property_type {
+        Type::Number(restrictions) => {
+            let number = AnimatedNumber::from_value(value);
+            if number.is_err() {
+                return Err(ValidationErrorKind::WrongType {
+                    expected: String::from("number or list of keyframes"),
+                });
+            }
+            let number = match number.unwrap() {
+                AnimatedNumber::Constant(value) => {
+                    AnimatedNumber::Constant(clamp(value, &restrictions))
+                }
+                AnimatedNumber::Keyframed { mut keyframes } => {
+                    for keyframe in &mut keyframes {
+                        keyframe.value = clamp(keyframe.value, &restrictions);
+                    }
+                    AnimatedNumber::Keyframed { keyframes }
+                }
+            };
+            Ok(serde_json::to_value(number).unwrap())
+        }
+        Type::String(max_length) => match value {
+            Value::String(string) if string.chars().count() > max_length as usize => {
+                Err(ValidationErrorKind::TooLong { max_length })
+            }
+            Value::String(_) => Ok(value.clone()),
+            _ => Err(ValidationErrorKind::WrongType {
+                expected: String::from("string"),
+            }),
+        },
+        Type::Clip => {
+            if serde_json::from_value::<ClipIdentifier>(value.clone()).is_err() {
+                return Err(ValidationErrorKind::WrongType {
+                    expected: String::from("clip"),
+                });
+            }
+            Ok(value.clone())
+        }
+        Type::Boolean(_) => match value {
+            Value::Bool(_) => Ok(value.clone()),
+            _ => Err(ValidationErrorKind::WrongType {
+                expected: String::from("boolean"),
+            }),
+        },
+        Type::Pipeable(_, _) => Ok(value.clone()),
+    }
+}
+
+fn is_keyframed(value: &Value) -> bool {
+    matches!(
+        AnimatedNumber::from_value(value),
+        Ok(AnimatedNumber::Keyframed { .. })
+    )
+}
+
+fn clamp(value: f64, restrictions: &Restrictions) -> f64 {
+    f64::max(restrictions.min, f64::min(value, restrictions.max))
+}
+
+/**
+ * Gets the inputs a node has with its current properties; the inputs do not depend on what is piped into the node for any of the properties which can be validated
+ * Falls back to the node type's default properties if they cannot be found
+ */
+fn get_inputs(
+    node: &Node,
+    store: &Store,
+    node_register: &NodeRegister,
+) -> Option<HashMap<String, NodeTypeInput>> {
+    let node_type = node_register.get(&node.node_type)?;
+    let io = node_type.kind.get_io(&NodeContext {
+        node_id: node.id.clone(),
+        properties: &node.properties,
+        piped_inputs: &HashMap::new(),
+        composited_clip_types: &HashMap::new(),
+        store,
+        node_register,
+    });
+    match io {
+        Ok((inputs, _)) => Some(inputs),
+        Err(_) => Some(node_type.default_properties.clone()),
+    }
+}
+
+/**
+ * Validates all the properties of a node against the types of its inputs, clamping any numbers which are out of range
+ * Only animatable properties can be keyframed, and properties which are not inputs (such as stored analysis results) are left as they are
+ */
+pub fn validate_node(
+    node: &mut Node,
+    store: &Store,
+    node_register: &NodeRegister,
+) -> Result<(), Vec<ValidationError>> {
+    let inputs = get_inputs(node, store, node_register);
+    if inputs.is_none() {
+        return Err(vec![ValidationError {
+            node_id: node.id.clone(),
+            property: String::from("node_type"),
+            kind: ValidationErrorKind::UnknownNodeType,
+        }]);
+    }
+    let inputs = inputs.unwrap();
+    let animatable: Vec<String> = node
+        .properties
+        .keys()
+        .filter(|property| is_animatable(node, property, store, node_register))
+        .cloned()
+        .collect();
+
+    let mut errors = Vec::new();
+    for (property, value) in node.properties.iter_mut() {
+        if let Some(input) = inputs.get(property) {
+            match validate_property(input, value) {
+                Ok(valid_value) if is_keyframed(&valid_value) && !animatable.contains(property) => {
+                    errors.push(ValidationError {
+                        node_id: node.id.clone(),
+                        property: property.clone(),
+                        kind: ValidationErrorKind::WrongType {
+                            expected: String::from("number"),
+                        },
+                    })
+                }
+                Ok(valid_value) => *value = valid_value,
+                Err(kind) => errors.push(ValidationError {
+                    node_id: node.id.clone(),
+                    property: property.clone(),
+                    kind,
+                }),
+            }
+        }
+    }
+
+    if errors.len() > 0 {
+        return Err(errors);
+    }
+    Ok(())
+}
+
+/**
+ * Prepares a node which is being created; missing properties are filled in from their defaults (strings default to being empty, and clips must be chosen), any invalid properties are replaced with their defaults, and the node is marked with the current version of its node type
+ * Returns the errors for the properties which were replaced
+ */
+pub fn validate_new_node(
+    node: &mut Node,
+    store: &Store,
+    node_register: &NodeRegister,
+) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    if let Err(validation_errors) = validate_node(node, store, node_register) {
+        errors = validation_errors;
+    }
+    for error in &errors {
+        node.properties.remove(&error.property);
+    }
+
+    if let Some(node_type) = node_register.get(&node.node_type) {
+        node.type_version = node_type.version.clone();
+        for (property, input) in &node_type.default_properties {
+            let default = match input.property_type {
+                Type::Number(restrictions) => Value::from(restrictions.default),
+                Type::Boolean(default) => Value::from(default),
+                Type::String(_) => Value::from(""),
+                Type::Clip | Type::Pipeable(_, _) => continue,
+            };
+            if !node.properties.contains_key(property) {
+                node.properties.insert(property.clone(), default);
+            }
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use crate::{
+        node::{Node, NodeTypeInput, Restrictions, Type},
+        nodes::grid_node::{self, grid_node},
+        store::Store,
+    };
+
+    use super::{validate_new_node, validate_property, ValidationErrorKind};
+
+    #[test]
+    fn test_validate_property() {
+        let input = |property_type: Type| NodeTypeInput {
+            name: String::from("input"),
+            display_name: String::from("Input"),
+            description: String::new(),
+            property_type,
+        };
+        let number = input(Type::Number(Restrictions {
+            min: 0.0,
+            max: 10.0,
+            step: 0.1,
+            default: 1.0,
+        }));
+
+        assert_eq!(validate_property(&number, &json!(5.5)), Ok(json!(5.5)));
+        assert_eq!(validate_property(&number, &json!(-3)), Ok(json!(0.0)));
+        assert_eq!(
+            validate_property(
+                &number,
+                &json!({"keyframes": [{"time": 0.0, "value": 20.0, "interpolation": "hold"}]})
+            ),
+            Ok(json!({"keyframes": [{"time": 0.0, "value": 10.0, "interpolation": "hold"}]}))
+        );
+        assert!(matches!(
+            validate_property(&number, &json!("5")),
+            Err(ValidationErrorKind::WrongType { .. })
+        ));
+
+        let string = input(Type::String(3));
+        assert_eq!(validate_property(&string, &json!("abc")), Ok(json!("abc")));
+        assert_eq!(
+            validate_property(&string, &json!("abcd")),
+            Err(ValidationErrorKind::TooLong { max_length: 3 })
+        );
+        assert!(validate_property(&string, &json!(3)).is_err());
+
+        assert!(validate_property(&input(Type::Clip), &json!({"id": 1})).is_err());
+
+        let boolean = input(Type::Boolean(false));
+        assert_eq!(validate_property(&boolean, &json!(true)), Ok(json!(true)));
+        assert!(validate_property(&boolean, &json!(1)).is_err());
+    }
+
+    #[test]
+    fn test_validate_new_node() {
+        let mut register = HashMap::new();
+        register.insert(String::from(grid_node::IDENTIFIER), grid_node());
+        let store = Store::new();
+
+        let mut node = Node::new(String::from(grid_node::IDENTIFIER), None);
+        node.properties
+            .insert(String::from(grid_node::inputs::COLUMNS), json!("two"));
+        let errors = validate_new_node(&mut node, &store, &register);
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].property, grid_node::inputs::COLUMNS);
+        assert_eq!(
+            node.properties.get(grid_node::inputs::COLUMNS),
+            Some(&json!(0.0))
+        );
+        assert_eq!(
+            node.properties.get(grid_node::inputs::NUMBER_OF_INPUTS),
+            Some(&json!(4.0))
+        );
+        assert_eq!(
+            node.properties.get(grid_node::inputs::BACKGROUND_COLOUR),
+            Some(&json!(""))
+        );
+        assert_eq!(node.type_version, grid_node().version);
+    }
+}